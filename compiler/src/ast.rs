@@ -1,4 +1,9 @@
 // ast.rs - COMPLETE FOR PHASE 1.3
+pub mod pretty;
+pub mod debug;
+
+use crate::gates;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Int,
@@ -28,6 +33,14 @@ pub enum Gate {
     T,
     S,
     SWAP,
+    CH,
+    CY,
+    // Controlled rotations: angle, then control and target
+    CRX(Box<Expr>),
+    CRY(Box<Expr>),
+    CRZ(Box<Expr>),
+    // Gate registered from a gate set file: name and qubit count
+    Custom(String, usize),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -117,6 +130,10 @@ pub enum Expr {
     MemberAccess(Box<Expr>, String, Span),
     
     Measure(Box<Expr>, Span),
+    // measure(qubit, target): measures into an existing cbit
+    MeasureInto(Box<Expr>, Box<Expr>, Span),
+    Reset(Box<Expr>, Span),
+    Barrier(Vec<Expr>, Span),
     GateApply(Box<Gate>, Vec<Expr>, Span),
     
     Tuple(Vec<Expr>, Span),
@@ -149,6 +166,7 @@ pub enum Stmt {
     Expr(Expr, Span),
     Let(String, Type, Expr, bool, Span),
     Assign(String, Expr, Span),
+    AssignIndex(String, Box<Expr>, Expr, Span),
     Block(Vec<Stmt>, Span),
     If(Expr, Box<Stmt>, Option<Box<Stmt>>, Span),
     While(Expr, Box<Stmt>, Span),
@@ -205,6 +223,9 @@ impl Expr {
             | Expr::Index(_, _, span)
             | Expr::MemberAccess(_, _, span)
             | Expr::Measure(_, span)
+            | Expr::MeasureInto(_, _, span)
+            | Expr::Reset(_, span)
+            | Expr::Barrier(_, span)
             | Expr::GateApply(_, _, span)
             | Expr::Tuple(_, span)
             | Expr::StructLiteral(_, _, span) => span,
@@ -218,6 +239,7 @@ impl Stmt {
             Stmt::Expr(_, span)
             | Stmt::Let(_, _, _, _, span)
             | Stmt::Assign(_, _, span)
+            | Stmt::AssignIndex(_, _, _, span)
             | Stmt::Block(_, span)
             | Stmt::If(_, _, _, span)
             | Stmt::While(_, _, span)
@@ -231,14 +253,100 @@ impl Stmt {
             | Stmt::StructDef(_, span) => span,
         }
     }
+    
+    /// The variable and new value of the assignment an `x++;` or `x--;`
+    /// statement stands for, i.e. `x = x + 1;` or `x = x - 1;`.
+    pub fn step_assignment(&self) -> Option<(&str, Expr)> {
+        let Stmt::Expr(Expr::UnaryOp(op, operand, span), _) = self else {
+            return None;
+        };
+        let arith = match op {
+            UnaryOp::PostIncrement => BinaryOp::Add,
+            UnaryOp::PostDecrement => BinaryOp::Sub,
+            _ => return None,
+        };
+        let Expr::Variable(name, _) = &**operand else {
+            return None;
+        };
+        let one = Expr::LiteralInt(1, span.clone());
+        Some((name, Expr::BinaryOp(operand.clone(), arith, Box::new(one), span.clone())))
+    }
 }
 
 impl Gate {
+    /// Number of qubit operands, as listed in `gates::all_supported`.
     pub fn arity(&self) -> usize {
         match self {
-            Gate::H | Gate::X | Gate::Y | Gate::Z | Gate::RX(_) | 
-            Gate::RY(_) | Gate::RZ(_) | Gate::T | Gate::S => 1,
-            Gate::CNOT | Gate::SWAP => 2,
+            Gate::Custom(_, arity) => *arity,
+            gate => gates::lookup(gate.canonical_name()).map_or(0, |info| info.qubits),
+        }
+    }
+    
+    /// The built-in gate called `name`, ignoring case. Rotations get a
+    /// zero angle, to be replaced through [`angle_mut`](Self::angle_mut).
+    /// Custom gates need the registry and are never returned.
+    pub fn from_name(name: &str) -> Option<Gate> {
+        let info = gates::lookup(name)?;
+        let zero = || Box::new(Expr::LiteralFloat(0.0, Span::default()));
+        let gate = match info.name {
+            "H" => Gate::H,
+            "X" => Gate::X,
+            "Y" => Gate::Y,
+            "Z" => Gate::Z,
+            "CNOT" => Gate::CNOT,
+            "T" => Gate::T,
+            "S" => Gate::S,
+            "SWAP" => Gate::SWAP,
+            "CH" => Gate::CH,
+            "CY" => Gate::CY,
+            "RX" => Gate::RX(zero()),
+            "RY" => Gate::RY(zero()),
+            "RZ" => Gate::RZ(zero()),
+            "CRX" => Gate::CRX(zero()),
+            "CRY" => Gate::CRY(zero()),
+            "CRZ" => Gate::CRZ(zero()),
+            _ => return None,
+        };
+        Some(gate)
+    }
+    
+    /// Source spelling of the gate, as listed in `gates::all_supported`.
+    pub fn canonical_name(&self) -> &str {
+        match self {
+            Gate::H => "H",
+            Gate::X => "X",
+            Gate::Y => "Y",
+            Gate::Z => "Z",
+            Gate::CNOT => "CNOT",
+            Gate::T => "T",
+            Gate::S => "S",
+            Gate::SWAP => "SWAP",
+            Gate::CH => "CH",
+            Gate::CY => "CY",
+            Gate::RX(_) => "RX",
+            Gate::RY(_) => "RY",
+            Gate::RZ(_) => "RZ",
+            Gate::CRX(_) => "CRX",
+            Gate::CRY(_) => "CRY",
+            Gate::CRZ(_) => "CRZ",
+            Gate::Custom(name, _) => name,
+        }
+    }
+    
+    /// The rotation angle of a parametric gate.
+    pub fn angle(&self) -> Option<&Expr> {
+        match self {
+            Gate::RX(angle) | Gate::RY(angle) | Gate::RZ(angle)
+            | Gate::CRX(angle) | Gate::CRY(angle) | Gate::CRZ(angle) => Some(angle),
+            _ => None,
+        }
+    }
+    
+    pub fn angle_mut(&mut self) -> Option<&mut Box<Expr>> {
+        match self {
+            Gate::RX(angle) | Gate::RY(angle) | Gate::RZ(angle)
+            | Gate::CRX(angle) | Gate::CRY(angle) | Gate::CRZ(angle) => Some(angle),
+            _ => None,
         }
     }
 }
\ No newline at end of file
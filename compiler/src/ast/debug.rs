@@ -0,0 +1,212 @@
+// src/ast/debug.rs - indented tree dump of the AST used by `qclang check --ast`
+use crate::ast::pretty::format_type;
+use crate::ast::*;
+
+/// A labelled node in the printed tree.
+struct Node {
+    label: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn leaf(label: impl Into<String>) -> Self {
+        Node { label: label.into(), children: Vec::new() }
+    }
+
+    fn new(label: impl Into<String>, children: Vec<Node>) -> Self {
+        Node { label: label.into(), children }
+    }
+}
+
+/// Renders `program` as an indented tree, one node per line:
+///
+/// ```text
+/// Program
+/// └─ Function main -> int
+///    └─ Let x: int
+///       └─ Int 1
+/// ```
+pub fn format_tree(program: &Program) -> String {
+    let mut children = Vec::new();
+    for alias in &program.type_aliases {
+        children.push(type_alias_node(alias));
+    }
+    for struct_def in &program.struct_defs {
+        children.push(struct_def_node(struct_def));
+    }
+    for function in &program.functions {
+        children.push(function_node(function));
+    }
+
+    let mut output = String::new();
+    render(&Node::new("Program", children), "", None, &mut output);
+    output
+}
+
+/// Writes `node` and its subtree. `last` is `None` for the root, otherwise
+/// whether the node is the final child of its parent.
+fn render(node: &Node, prefix: &str, last: Option<bool>, output: &mut String) {
+    let child_prefix = match last {
+        None => String::new(),
+        Some(last) => {
+            output.push_str(prefix);
+            output.push_str(if last { "└─ " } else { "├─ " });
+            format!("{}{}", prefix, if last { "   " } else { "│  " })
+        }
+    };
+    output.push_str(&node.label);
+    output.push('\n');
+
+    for (index, child) in node.children.iter().enumerate() {
+        render(child, &child_prefix, Some(index + 1 == node.children.len()), output);
+    }
+}
+
+fn type_alias_node(alias: &TypeAlias) -> Node {
+    Node::leaf(format!("TypeAlias {} = {}", alias.name, format_type(&alias.target)))
+}
+
+fn struct_def_node(struct_def: &StructDef) -> Node {
+    let fields = struct_def.fields.iter()
+        .map(|field| Node::leaf(format!("Field {}: {}", field.name, format_type(&field.ty))))
+        .collect();
+    Node::new(format!("Struct {}", struct_def.name), fields)
+}
+
+fn function_node(function: &Function) -> Node {
+    let mut children: Vec<Node> = function.params.iter()
+        .map(|param| {
+            let keyword = if param.mutable { "mut " } else { "" };
+            Node::leaf(format!("Param {}{}: {}", keyword, param.name, format_type(&param.ty)))
+        })
+        .collect();
+    children.extend(function.body.iter().map(stmt_node));
+
+    Node::new(
+        format!("Function {} -> {}", function.name, format_type(&function.return_type)),
+        children,
+    )
+}
+
+fn stmt_node(stmt: &Stmt) -> Node {
+    match stmt {
+        Stmt::Expr(expr, _) => Node::new("Expr", vec![expr_node(expr)]),
+        Stmt::Let(name, ty, expr, mutable, _) => {
+            let keyword = if *mutable { "mut " } else { "" };
+            Node::new(format!("Let {}{}: {}", keyword, name, format_type(ty)), vec![expr_node(expr)])
+        }
+        Stmt::Assign(name, expr, _) => Node::new(format!("Assign {}", name), vec![expr_node(expr)]),
+        Stmt::AssignIndex(name, index, expr, _) => Node::new(format!("AssignIndex {}", name), vec![
+            Node::new("Index", vec![expr_node(index)]),
+            Node::new("Value", vec![expr_node(expr)]),
+        ]),
+        Stmt::Block(stmts, _) => Node::new("Block", stmts.iter().map(stmt_node).collect()),
+        Stmt::If(condition, then_branch, else_branch, _) => {
+            Node::new("If", branch_nodes(condition, then_branch, else_branch.as_deref()))
+        }
+        Stmt::QIf(condition, then_branch, else_branch, _) => {
+            Node::new("QIf", branch_nodes(condition, then_branch, else_branch.as_deref()))
+        }
+        Stmt::While(condition, body, _) => Node::new("While", vec![
+            Node::new("Condition", vec![expr_node(condition)]),
+            Node::new("Body", vec![stmt_node(body)]),
+        ]),
+        Stmt::ForRange(var, start, end, step, body, _) => {
+            Node::new(format!("For {}", var), range_nodes(start, end, step.as_deref(), body))
+        }
+        Stmt::QForRange(var, start, end, step, body, _) => {
+            Node::new(format!("QFor {}", var), range_nodes(start, end, step.as_deref(), body))
+        }
+        Stmt::Return(expr, _) => Node::new("Return", expr.iter().map(expr_node).collect()),
+        Stmt::Break(_) => Node::leaf("Break"),
+        Stmt::Continue(_) => Node::leaf("Continue"),
+        Stmt::TypeAlias(alias, _) => type_alias_node(alias),
+        Stmt::StructDef(struct_def, _) => struct_def_node(struct_def),
+    }
+}
+
+fn branch_nodes(condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> Vec<Node> {
+    let mut nodes = vec![
+        Node::new("Condition", vec![expr_node(condition)]),
+        Node::new("Then", vec![stmt_node(then_branch)]),
+    ];
+    if let Some(else_branch) = else_branch {
+        nodes.push(Node::new("Else", vec![stmt_node(else_branch)]));
+    }
+    nodes
+}
+
+fn range_nodes(start: &Expr, end: &Expr, step: Option<&Expr>, body: &Stmt) -> Vec<Node> {
+    let mut nodes = vec![
+        Node::new("Start", vec![expr_node(start)]),
+        Node::new("End", vec![expr_node(end)]),
+    ];
+    if let Some(step) = step {
+        nodes.push(Node::new("Step", vec![expr_node(step)]));
+    }
+    nodes.push(Node::new("Body", vec![stmt_node(body)]));
+    nodes
+}
+
+fn expr_node(expr: &Expr) -> Node {
+    match expr {
+        Expr::LiteralInt(value, _) => Node::leaf(format!("Int {}", value)),
+        Expr::LiteralFloat(value, _) => Node::leaf(format!("Float {}", value)),
+        Expr::LiteralBool(value, _) => Node::leaf(format!("Bool {}", value)),
+        Expr::LiteralString(value, _) => Node::leaf(format!("String {:?}", value)),
+        Expr::LiteralQubit(bits, _) => Node::leaf(format!("Qubit {}", bits.to_string())),
+        Expr::Variable(name, _) => Node::leaf(format!("Variable {}", name)),
+        Expr::BinaryOp(lhs, op, rhs, _) => {
+            Node::new(format!("BinaryOp {:?}", op), vec![expr_node(lhs), expr_node(rhs)])
+        }
+        Expr::UnaryOp(op, operand, _) => Node::new(format!("UnaryOp {:?}", op), vec![expr_node(operand)]),
+        Expr::Call(name, args, _) => Node::new(format!("Call {}", name), args.iter().map(expr_node).collect()),
+        Expr::Index(base, index, _) => Node::new("Index", vec![expr_node(base), expr_node(index)]),
+        Expr::MemberAccess(base, field, _) => Node::new(format!("Member .{}", field), vec![expr_node(base)]),
+        Expr::Measure(target, _) => Node::new("Measure", vec![expr_node(target)]),
+        Expr::MeasureInto(qubit, target, _) => Node::new("MeasureInto", vec![expr_node(qubit), expr_node(target)]),
+        Expr::Reset(target, _) => Node::new("Reset", vec![expr_node(target)]),
+        Expr::Barrier(args, _) => Node::new("Barrier", args.iter().map(expr_node).collect()),
+        Expr::GateApply(gate, args, _) => gate_node(gate, args),
+        Expr::Tuple(elements, _) => Node::new("Tuple", elements.iter().map(expr_node).collect()),
+        Expr::StructLiteral(name, fields, _) => {
+            let fields = fields.iter()
+                .map(|(field, value)| Node::new(format!("Field {}", field), vec![expr_node(value)]))
+                .collect();
+            Node::new(format!("StructLiteral {}", name), fields)
+        }
+    }
+}
+
+fn gate_node(gate: &Gate, args: &[Expr]) -> Node {
+    let (name, angle) = (gate.canonical_name(), gate.angle());
+
+    let mut children = Vec::new();
+    if let Some(angle) = angle {
+        children.push(Node::new("Angle", vec![expr_node(angle)]));
+    }
+    children.extend(args.iter().map(expr_node));
+    Node::new(format!("Gate {}", name), children)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compiler;
+
+    #[test]
+    fn tree_lists_functions_and_statements() {
+        let program = Compiler::parse_only("fn main() -> int { let x: int = 1 + 2; return x; }").unwrap();
+        let tree = format_tree(&program);
+        assert_eq!(tree, "\
+Program
+└─ Function main -> int
+   ├─ Let x: int
+   │  └─ BinaryOp Add
+   │     ├─ Int 1
+   │     └─ Int 2
+   └─ Return
+      └─ Variable x
+");
+    }
+}
@@ -0,0 +1,512 @@
+// src/ast/pretty.rs - canonical source printer used by `qclang format`
+use crate::ast::*;
+use crate::lexer::Comment;
+use std::collections::VecDeque;
+
+const INDENT: &str = "    ";
+
+/// Re-emits `program` as canonical QCLang source.
+///
+/// Type aliases come first, then struct definitions, then functions, each
+/// separated by a blank line. Bodies are indented by four spaces with one
+/// statement per line. Comments are not part of the AST and are dropped;
+/// use [`format_program_with_comments`] to keep them. Formatting the output
+/// again yields the same text.
+pub fn format_program(program: &Program) -> String {
+    format_program_with_comments(program, &[], &[])
+}
+
+/// Like [`format_program`], but keeps the comments of the source.
+///
+/// `token_lines` holds the line of every token the program was parsed from,
+/// in order, and `comments` the comments found by [`crate::lexer::comments`].
+/// A comment at the end of the first line of a statement stays there; any
+/// other comment goes on its own line before the next statement, or before
+/// the closing brace of its block.
+pub fn format_program_with_comments(program: &Program, token_lines: &[usize], comments: &[Comment]) -> String {
+    let mut items: Vec<(&Span, Item)> = Vec::new();
+    items.extend(program.type_aliases.iter().map(|alias| (&alias.span, Item::TypeAlias(alias))));
+    items.extend(program.struct_defs.iter().map(|struct_def| (&struct_def.span, Item::StructDef(struct_def))));
+    items.extend(program.functions.iter().map(|function| (&function.span, Item::Function(function))));
+
+    // Each item keeps the comments up to its last line in the source, which
+    // may differ from the order it is printed in
+    let mut by_source: Vec<usize> = (0..items.len()).collect();
+    by_source.sort_by_key(|&i| items[i].0.line);
+    let mut owned = vec![Vec::new(); items.len()];
+    let mut pending = comments.iter().peekable();
+    for &i in &by_source {
+        let end_line = Comments::end_line_of(token_lines, items[i].0);
+        while let Some(comment) = pending.next_if(|comment| comment.line <= end_line) {
+            owned[i].push(comment);
+        }
+    }
+
+    let mut parts = Vec::new();
+    for ((_, item), owned) in items.iter().zip(owned) {
+        let mut comments = Comments { pending: owned.into(), token_lines };
+        let mut output = String::new();
+        match item {
+            Item::TypeAlias(alias) => format_type_alias(alias, &mut comments, &mut output),
+            Item::StructDef(struct_def) => {
+                format_struct_def(struct_def, 0, &mut comments, &mut output);
+                output.push('\n');
+            }
+            Item::Function(function) => format_function(function, &mut comments, &mut output),
+        }
+        comments.rest(0, &mut output);
+        parts.push(output);
+    }
+
+    let mut rest = Comments { pending: pending.collect(), token_lines };
+    let mut trailing = String::new();
+    rest.rest(0, &mut trailing);
+    if !trailing.is_empty() {
+        parts.push(trailing);
+    }
+
+    let mut output = parts.join("\n");
+    if output.is_empty() {
+        return output;
+    }
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+enum Item<'a> {
+    TypeAlias(&'a TypeAlias),
+    StructDef(&'a StructDef),
+    Function(&'a Function),
+}
+
+// Comments still to be written, in source order
+struct Comments<'a> {
+    pending: VecDeque<&'a Comment>,
+    token_lines: &'a [usize],
+}
+
+impl<'a> Comments<'a> {
+    // Spans end at the index of the token after the node
+    fn end_line_of(token_lines: &[usize], span: &Span) -> usize {
+        span.end.checked_sub(1)
+            .and_then(|last| token_lines.get(last))
+            .copied()
+            .unwrap_or(span.line)
+    }
+
+    fn end_line(&self, span: &Span) -> usize {
+        Self::end_line_of(self.token_lines, span)
+    }
+
+    // Writes the comments that come before code starting on `line`
+    fn leading(&mut self, line: usize, level: usize, output: &mut String) {
+        while let Some(comment) = self.pending.front() {
+            if comment.line > line || (comment.line == line && comment.trailing) {
+                break;
+            }
+            output.push_str(&format!("{}{}\n", INDENT.repeat(level), comment.text));
+            self.pending.pop_front();
+        }
+    }
+
+    // Takes the comment that ends `line`, if any
+    fn trailing(&mut self, line: usize) -> Option<&'a Comment> {
+        match self.pending.front() {
+            Some(comment) if comment.line == line && comment.trailing => self.pending.pop_front(),
+            _ => None,
+        }
+    }
+
+    fn rest(&mut self, level: usize, output: &mut String) {
+        self.leading(usize::MAX, level, output);
+    }
+}
+
+// Appends `comment` to the first line of `text`
+fn attach_trailing(text: &mut String, comment: Option<&Comment>) {
+    if let Some(comment) = comment {
+        let at = text.find('\n').unwrap_or(text.len());
+        text.insert_str(at, &format!(" {}", comment.text));
+    }
+}
+
+fn format_type_alias(alias: &TypeAlias, comments: &mut Comments, output: &mut String) {
+    comments.leading(alias.span.line, 0, output);
+    let mut text = format!("type {} = {};", alias.name, format_type(&alias.target));
+    attach_trailing(&mut text, comments.trailing(alias.span.line));
+    output.push_str(&text);
+    output.push('\n');
+}
+
+fn format_struct_def(struct_def: &StructDef, level: usize, comments: &mut Comments, output: &mut String) {
+    let pad = INDENT.repeat(level);
+    comments.leading(struct_def.span.line, level, output);
+    let mut text = format!("{}struct {} {{", pad, struct_def.name);
+    attach_trailing(&mut text, comments.trailing(struct_def.span.line));
+    output.push_str(&text);
+    output.push('\n');
+    for field in &struct_def.fields {
+        comments.leading(field.span.line, level + 1, output);
+        let mut text = format!("{}{}{}: {},", pad, INDENT, field.name, format_type(&field.ty));
+        attach_trailing(&mut text, comments.trailing(field.span.line));
+        output.push_str(&text);
+        output.push('\n');
+    }
+    comments.leading(comments.end_line(&struct_def.span), level + 1, output);
+    output.push_str(&format!("{}}};", pad));
+}
+
+fn format_function(function: &Function, comments: &mut Comments, output: &mut String) {
+    let params: Vec<String> = function.params.iter()
+        .map(|param| {
+            let prefix = if param.mutable { "mut " } else { "" };
+            format!("{}{}: {}", prefix, param.name, format_type(&param.ty))
+        })
+        .collect();
+
+    comments.leading(function.span.line, 0, output);
+    let mut header = format!(
+        "fn {}({}) -> {} {{",
+        function.name,
+        params.join(", "),
+        format_type(&function.return_type)
+    );
+    attach_trailing(&mut header, comments.trailing(function.span.line));
+    output.push_str(&header);
+    output.push('\n');
+    for stmt in &function.body {
+        format_stmt(stmt, 1, comments, output);
+    }
+
+    let end_line = comments.end_line(&function.span);
+    comments.leading(end_line, 1, output);
+    let mut close = "}".to_string();
+    attach_trailing(&mut close, comments.trailing(end_line));
+    output.push_str(&close);
+    output.push('\n');
+}
+
+/// Formats a type the way the parser reads it back.
+pub fn format_type(ty: &Type) -> String {
+    match ty {
+        Type::Int => "int".to_string(),
+        Type::Float => "float".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::String => "string".to_string(),
+        Type::Qubit => "qubit".to_string(),
+        Type::Cbit => "cbit".to_string(),
+        Type::Qreg(size) => format!("qreg[{}]", size),
+        Type::Array(elem, size) => format!("{}[{}]", format_type(elem), size),
+        Type::Unit => "()".to_string(),
+        Type::Tuple(types) if types.len() == 1 => format!("({},)", format_type(&types[0])),
+        Type::Tuple(types) => {
+            let parts: Vec<String> = types.iter().map(format_type).collect();
+            format!("({})", parts.join(", "))
+        }
+        Type::Function(params, ret) => {
+            let parts: Vec<String> = params.iter().map(format_type).collect();
+            format!("fn({}) -> {}", parts.join(", "), format_type(ret))
+        }
+        Type::Named(name) => name.clone(),
+    }
+}
+
+fn format_stmt(stmt: &Stmt, level: usize, comments: &mut Comments, output: &mut String) {
+    let line = stmt.span().line;
+    comments.leading(line, level, output);
+    let trailing = comments.trailing(line);
+
+    let mut text = INDENT.repeat(level);
+    format_stmt_inline(stmt, level, comments, &mut text);
+    attach_trailing(&mut text, trailing);
+    output.push_str(&text);
+    output.push('\n');
+}
+
+// Writes `stmt` starting at the current column; nested lines use `level`.
+fn format_stmt_inline(stmt: &Stmt, level: usize, comments: &mut Comments, output: &mut String) {
+    let pad = INDENT.repeat(level);
+
+    match stmt {
+        Stmt::Expr(expr, _) => {
+            output.push_str(&format!("{};", format_expr(expr)));
+        }
+        Stmt::Let(name, ty, expr, mutable, _) => {
+            output.push_str(&format_let(name, ty, expr, *mutable));
+        }
+        Stmt::Assign(name, expr, _) => {
+            output.push_str(&format!("{} = {};", name, format_expr(expr)));
+        }
+        Stmt::AssignIndex(name, index, expr, _) => {
+            output.push_str(&format!("{}[{}] = {};", name, format_expr(index), format_expr(expr)));
+        }
+        Stmt::Block(stmts, span) => {
+            output.push_str("{\n");
+            for inner in stmts {
+                format_stmt(inner, level + 1, comments, output);
+            }
+            comments.leading(comments.end_line(span), level + 1, output);
+            output.push_str(&pad);
+            output.push('}');
+        }
+        Stmt::If(condition, then_branch, else_branch, _) => {
+            output.push_str(&format!("if ({}) ", format_expr(condition)));
+            format_branches(then_branch, else_branch.as_deref(), "else", level, comments, output);
+        }
+        Stmt::QIf(condition, then_branch, else_branch, _) => {
+            output.push_str(&format!("qif ({}) ", format_expr(condition)));
+            format_branches(then_branch, else_branch.as_deref(), "qelse", level, comments, output);
+        }
+        Stmt::While(condition, body, _) => {
+            output.push_str(&format!("while ({}) ", format_expr(condition)));
+            format_stmt_inline(body, level, comments, output);
+        }
+        Stmt::ForRange(var, start, end, step, body, _) => {
+            output.push_str(&format!("for {} in {} ", var, format_range(start, end, step.as_deref())));
+            format_stmt_inline(body, level, comments, output);
+        }
+        Stmt::QForRange(var, start, end, step, body, _) => {
+            output.push_str(&format!("qfor {} in {} ", var, format_range(start, end, step.as_deref())));
+            format_stmt_inline(body, level, comments, output);
+        }
+        Stmt::Return(Some(expr), _) => {
+            output.push_str(&format!("return {};", format_expr(expr)));
+        }
+        Stmt::Return(None, _) => output.push_str("return;"),
+        Stmt::Break(_) => output.push_str("break;"),
+        Stmt::Continue(_) => output.push_str("continue;"),
+        Stmt::TypeAlias(alias, _) => {
+            output.push_str(&format!("type {} = {};", alias.name, format_type(&alias.target)));
+        }
+        Stmt::StructDef(struct_def, _) => {
+            let mut text = String::new();
+            format_struct_def(struct_def, level, comments, &mut text);
+            output.push_str(text.trim_start());
+        }
+    }
+}
+
+fn format_branches(then_branch: &Stmt, else_branch: Option<&Stmt>, else_keyword: &str, level: usize, comments: &mut Comments, output: &mut String) {
+    format_stmt_inline(then_branch, level, comments, output);
+
+    if let Some(else_branch) = else_branch {
+        // `} else {` for blocks, otherwise the else goes on its own line
+        if matches!(then_branch, Stmt::Block(..)) {
+            output.push(' ');
+        } else {
+            output.push('\n');
+            output.push_str(&INDENT.repeat(level));
+        }
+        output.push_str(else_keyword);
+        output.push(' ');
+        format_stmt_inline(else_branch, level, comments, output);
+    }
+}
+
+fn format_range(start: &Expr, end: &Expr, step: Option<&Expr>) -> String {
+    match step {
+        Some(step) => format!("range({}, {}, {})", format_expr(start), format_expr(end), format_expr(step)),
+        None => format!("range({}, {})", format_expr(start), format_expr(end)),
+    }
+}
+
+fn format_let(name: &str, ty: &Type, expr: &Expr, mutable: bool) -> String {
+    let prefix = if mutable { "mut " } else { "" };
+
+    match (ty, expr) {
+        // Registers keep their dedicated declaration form
+        (Type::Qreg(size), Expr::LiteralQubit(bits, _)) if !mutable && bits.bits.len() == *size => {
+            format!("qreg {}[{}] = {};", name, size, bits.to_string())
+        }
+        // Arrays declared without an initializer carry a zero placeholder
+        (Type::Array(elem, size), Expr::LiteralInt(0, _)) => {
+            format!("{}{} {}[{}];", prefix, format_type(elem), name, size)
+        }
+        _ => format!("let {}{}: {} = {};", prefix, name, format_type(ty), format_expr(expr)),
+    }
+}
+
+/// Formats an expression with the minimum parentheses needed to preserve
+/// its structure under the parser's precedence rules.
+pub fn format_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::LiteralInt(value, _) => value.to_string(),
+        Expr::LiteralFloat(value, _) => format!("{:?}", value),
+        Expr::LiteralBool(value, _) => value.to_string(),
+        Expr::LiteralString(value, _) => format!("\"{}\"", value),
+        Expr::LiteralQubit(bits, _) => bits.to_string(),
+        Expr::Variable(name, _) => name.clone(),
+        Expr::BinaryOp(lhs, op, rhs, _) => {
+            let prec = binary_precedence(op);
+            let right_assoc = is_assignment(op);
+            let lhs_text = wrap_operand(lhs, if right_assoc { prec + 1 } else { prec });
+            let rhs_text = wrap_operand(rhs, if right_assoc { prec } else { prec + 1 });
+            format!("{} {} {}", lhs_text, binary_symbol(op), rhs_text)
+        }
+        Expr::UnaryOp(op, operand, _) => {
+            let inner = wrap_operand(operand, UNARY_PRECEDENCE);
+            match op {
+                UnaryOp::Neg if inner.starts_with('-') => format!("-({})", inner),
+                UnaryOp::Neg => format!("-{}", inner),
+                UnaryOp::Not => format!("!{}", inner),
+                UnaryOp::PreIncrement | UnaryOp::Increment => format!("++{}", inner),
+                UnaryOp::PreDecrement | UnaryOp::Decrement => format!("--{}", inner),
+                UnaryOp::PostIncrement => format!("{}++", inner),
+                UnaryOp::PostDecrement => format!("{}--", inner),
+            }
+        }
+        Expr::Call(name, args, _) => format!("{}({})", name, format_args(args)),
+        Expr::Index(base, index, _) => {
+            format!("{}[{}]", wrap_operand(base, POSTFIX_PRECEDENCE), format_expr(index))
+        }
+        Expr::MemberAccess(base, field, _) => {
+            format!("{}.{}", wrap_operand(base, POSTFIX_PRECEDENCE), field)
+        }
+        Expr::Measure(target, _) => format!("measure({})", format_expr(target)),
+        Expr::MeasureInto(qubit, target, _) => {
+            format!("measure({}, {})", format_expr(qubit), format_expr(target))
+        }
+        Expr::Reset(target, _) => format!("reset({})", format_expr(target)),
+        Expr::Barrier(targets, _) => format!("barrier({})", format_args(targets)),
+        Expr::GateApply(gate, args, _) => format_gate(gate, args),
+        Expr::Tuple(elements, _) if elements.len() == 1 => format!("({},)", format_expr(&elements[0])),
+        Expr::Tuple(elements, _) => format!("({})", format_args(elements)),
+        Expr::StructLiteral(name, fields, _) => {
+            if fields.is_empty() {
+                return format!("{} {{}}", name);
+            }
+            let parts: Vec<String> = fields.iter()
+                .map(|(field, value)| format!("{}: {}", field, format_expr(value)))
+                .collect();
+            format!("{} {{ {} }}", name, parts.join(", "))
+        }
+    }
+}
+
+fn format_gate(gate: &Gate, args: &[Expr]) -> String {
+    let (name, angle) = (gate.canonical_name(), gate.angle());
+
+    match angle {
+        Some(angle) if args.is_empty() => format!("{}({})", name, format_expr(angle)),
+        Some(angle) => format!("{}({}, {})", name, format_expr(angle), format_args(args)),
+        None => format!("{}({})", name, format_args(args)),
+    }
+}
+
+fn format_args(args: &[Expr]) -> String {
+    args.iter().map(format_expr).collect::<Vec<_>>().join(", ")
+}
+
+const UNARY_PRECEDENCE: u8 = 9;
+const POSTFIX_PRECEDENCE: u8 = 10;
+
+fn expr_precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::BinaryOp(_, op, _, _) => binary_precedence(op),
+        Expr::UnaryOp(..) => UNARY_PRECEDENCE,
+        // Negative literals print with a leading '-'
+        Expr::LiteralInt(value, _) if *value < 0 => UNARY_PRECEDENCE,
+        Expr::LiteralFloat(value, _) if value.is_sign_negative() => UNARY_PRECEDENCE,
+        _ => POSTFIX_PRECEDENCE,
+    }
+}
+
+fn wrap_operand(expr: &Expr, min_precedence: u8) -> String {
+    let text = format_expr(expr);
+    if expr_precedence(expr) < min_precedence {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn is_assignment(op: &BinaryOp) -> bool {
+    matches!(op,
+        BinaryOp::Assign | BinaryOp::AddAssign | BinaryOp::SubAssign |
+        BinaryOp::MulAssign | BinaryOp::DivAssign)
+}
+
+fn binary_precedence(op: &BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Assign | BinaryOp::AddAssign | BinaryOp::SubAssign |
+        BinaryOp::MulAssign | BinaryOp::DivAssign => 1,
+        BinaryOp::Or => 2,
+        BinaryOp::And => 3,
+        BinaryOp::Eq | BinaryOp::Neq => 4,
+        BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge => 5,
+        BinaryOp::Xor => 6,
+        BinaryOp::Add | BinaryOp::Sub => 7,
+        BinaryOp::Mul | BinaryOp::Div => 8,
+    }
+}
+
+fn binary_symbol(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Eq => "==",
+        BinaryOp::Neq => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Gt => ">",
+        BinaryOp::Le => "<=",
+        BinaryOp::Ge => ">=",
+        BinaryOp::And => "&",
+        BinaryOp::Or => "|",
+        BinaryOp::Xor => "^",
+        BinaryOp::Assign => "=",
+        BinaryOp::AddAssign => "+=",
+        BinaryOp::SubAssign => "-=",
+        BinaryOp::MulAssign => "*=",
+        BinaryOp::DivAssign => "/=",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{comments, tokenize_with_errors};
+    use crate::parser::Parser;
+
+    fn format_source(source: &str) -> String {
+        let (tokens, lex_errors) = tokenize_with_errors(source);
+        assert!(lex_errors.is_empty(), "{:?}", lex_errors);
+        let token_lines: Vec<usize> = tokens.iter().map(|(_, line, _)| *line).collect();
+        let mut parser = Parser::new(tokens.into_iter(), source.to_string());
+        let program = parser.parse_program();
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+        format_program_with_comments(&program, &token_lines, &comments(source))
+    }
+
+    const SOURCE: &str = "// header
+fn main() -> int { // entry
+    let mut x: int = 1+2;
+    if (x == 3) { x = 4; /* inline */ }
+    // before return
+    return x;
+} // end
+";
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let once = format_source(SOURCE);
+        assert_eq!(format_source(&once), once);
+    }
+
+    #[test]
+    fn comments_are_kept() {
+        assert_eq!(format_source(SOURCE), "// header
+fn main() -> int { // entry
+    let mut x: int = 1 + 2;
+    if (x == 3) { /* inline */
+        x = 4;
+    }
+    // before return
+    return x;
+} // end
+");
+    }
+}
@@ -2,12 +2,17 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
-use qclang_compiler::{Compiler, CompileStats};
+use qclang_compiler::{error, gates, Compiler, CompileError, CompileOptions, CompileStats, PassTimings};
+use qclang_compiler::gates::GateRegistry;
+use qclang_compiler::qir::{OptimizationReport, QirSnapshots};
+use qclang_compiler::simulator::Simulator;
+use num_complex::Complex;
+use qclang_compiler::targets::{self, Target};
 use self_update::cargo_crate_version;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 const APP_NAME: &str = "qclang";
 const REPO_URL: &str = "https://github.com/Asmodeus14/qclang";
@@ -55,6 +60,98 @@ enum Commands {
         /// Enable optimizations (Dead Qubit Elimination, Gate Cancellation)
         #[arg(short = 'O', long)]
         optimize: bool,
+        
+        /// Fail if the circuit depth exceeds N
+        #[arg(long, value_name = "N")]
+        max_depth: Option<usize>,
+        
+        /// Fail if the circuit has more than N gates
+        #[arg(long, value_name = "N")]
+        max_gates: Option<usize>,
+        
+        /// Fail if the program allocates more than N qubits
+        #[arg(long, value_name = "N")]
+        max_qubits: Option<usize>,
+        
+        /// Fail if a gate outside this comma-separated set is used (e.g. rz,sx,cx)
+        #[arg(long, value_name = "GATES", value_delimiter = ',')]
+        basis_gates: Option<Vec<String>>,
+        
+        /// Device preset setting the basis gates and qubit limit (e.g. ibmq_small)
+        #[arg(long, value_name = "NAME", value_parser = parse_target)]
+        target: Option<&'static Target>,
+        
+        /// Fail if a two-qubit gate acts on qubits not joined by one of these edges (e.g. 0-1,1-2)
+        #[arg(long, value_name = "EDGES", value_delimiter = ',', value_parser = parse_edge)]
+        coupling_map: Option<Vec<(usize, usize)>>,
+        
+        /// Insert SWAPs so two-qubit gates act on connected qubits
+        #[arg(long)]
+        route: bool,
+        
+        /// JSON file of extra gates, e.g. {"myh": {"arity": 1, "qasm": "h {0};"}}
+        #[arg(long, value_name = "FILE", value_parser = parse_gates_file)]
+        gates_file: Option<GateRegistry>,
+        
+        /// Fail if a loop unrolls to more than N iterations
+        #[arg(long, value_name = "N")]
+        unroll_threshold: Option<usize>,
+        
+        /// Rewrite SWAP gates as three CNOTs
+        #[arg(long)]
+        decompose_swap: bool,
+        
+        /// Fuse adjacent single-qubit gates on the same qubit into one u3 gate
+        #[arg(long)]
+        fuse_single_qubit: bool,
+        
+        /// Function to compile as the circuit entry point [default: main]
+        #[arg(long, value_name = "NAME")]
+        entry: Option<String>,
+        
+        /// Report how long each compilation phase took
+        #[arg(long)]
+        time_passes: bool,
+        
+        /// Report the gates each optimizer pass removed and how long it ran, slowest first
+        #[arg(long)]
+        profile: bool,
+        
+        /// Emit only gate and measurement instructions, without the OPENQASM header
+        #[arg(long, alias = "no-std-header")]
+        no_header: bool,
+        
+        /// Warn wherever an int is implicitly converted to float
+        #[arg(long)]
+        warn_coercion: bool,
+        
+        /// Warn when a variable in a nested block shadows an outer one
+        #[arg(long)]
+        warn_shadowing: bool,
+        
+        /// Reject qubits that are never measured, reset, returned or passed to a function
+        #[arg(long)]
+        deny_quantum_leaks: bool,
+        
+        /// Only report qubit, gate and measurement counts, without generating or writing QASM
+        #[arg(long)]
+        count_only: bool,
+        
+        /// Print the QIR before and after optimization
+        #[arg(long)]
+        dump_qir_before_after: bool,
+        
+        /// Reset measured qubits and reuse them instead of allocating new ones
+        #[arg(long)]
+        reuse_ancillas: bool,
+        
+        /// Emit QASM with section comments and aligned operands
+        #[arg(long)]
+        pretty_qasm: bool,
+        
+        /// Compile the inverse (dagger) of the circuit
+        #[arg(long)]
+        emit_inverse: bool,
     },
     
     /// Compile and show detailed statistics
@@ -70,6 +167,22 @@ enum Commands {
         /// Disable optimizations for this run
         #[arg(long)]
         no_opt: bool,
+        
+        /// Also write the statistics as JSON to FILE
+        #[arg(long, value_name = "FILE")]
+        stats_json: Option<PathBuf>,
+        
+        /// Run the simulation N times and print a histogram of the measured bitstrings
+        #[arg(long, value_name = "N", requires = "simulate")]
+        shots: Option<usize>,
+        
+        /// Seed the simulator's random number generator for reproducible outcomes
+        #[arg(long, value_name = "SEED", requires = "simulate")]
+        seed: Option<u64>,
+        
+        /// Print the final state's amplitude and probability for every basis state
+        #[arg(long, requires = "simulate", conflicts_with = "shots")]
+        amplitudes: bool,
     },
     
     /// Run the test suite
@@ -104,6 +217,16 @@ enum Commands {
     /// Show compiler capabilities
     Capabilities,
     
+    /// List supported gates with their qubit and angle counts
+    Gates,
+    
+    /// Explain an error code, e.g. E0003
+    #[command(arg_required_else_help = true)]
+    Explain {
+        /// Error code shown in brackets before an error message
+        code: String,
+    },
+    
     /// Validate syntax without compilation
     Check {
         /// Input QCLang files
@@ -115,6 +238,51 @@ enum Commands {
         ast: bool,
     },
     
+    /// Compare the compiled QASM of two QCLang files
+    #[command(arg_required_else_help = true)]
+    Diff {
+        /// First QCLang file
+        a: PathBuf,
+        
+        /// Second QCLang file
+        b: PathBuf,
+        
+        /// Optimize the first file before comparing
+        #[arg(long)]
+        optimize_a: bool,
+        
+        /// Optimize the second file before comparing
+        #[arg(long)]
+        optimize_b: bool,
+    },
+    
+    /// Reformat QCLang source files into canonical style
+    #[command(arg_required_else_help = true)]
+    Format {
+        /// Input QCLang files
+        #[arg(required = true, num_args = 1..)]
+        input: Vec<PathBuf>,
+        
+        /// Rewrite files in place instead of printing to stdout
+        #[arg(short, long)]
+        write: bool,
+    },
+    
+    /// Recompile a QCLang file whenever it changes on disk
+    #[command(arg_required_else_help = true)]
+    Watch {
+        /// Input QCLang file
+        input: PathBuf,
+        
+        /// Enable optimization passes
+        #[arg(short = 'O', long)]
+        optimize: bool,
+        
+        /// Polling interval in milliseconds
+        #[arg(long, value_name = "MS", default_value_t = 500)]
+        interval: u64,
+    },
+    
     /// Show compiler version and info
     Version,
     
@@ -135,6 +303,7 @@ enum OutputFormat {
     Json,
     Both,
     Qir,
+    Latex,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -150,11 +319,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     
     match cli.command {
-        Commands::Compile { input, output, format, show, optimize } => {
-            compile_files(input, output.as_deref(), format, show, optimize, cli.verbose)?;
+        Commands::Compile { input, output, format, show, optimize, max_depth, max_gates, max_qubits, basis_gates, target, coupling_map, route, gates_file, unroll_threshold, decompose_swap, fuse_single_qubit, entry, time_passes, profile, no_header, warn_coercion, warn_shadowing, deny_quantum_leaks, count_only, dump_qir_before_after, reuse_ancillas, pretty_qasm, emit_inverse } => {
+            let options = CompileOptions {
+                optimize, max_depth, max_gates, max_qubits, basis_gates, unroll_threshold, decompose_swap, fuse_single_qubit, entry,
+                time_passes, profile, no_header, warn_coercion, warn_shadowing, deny_quantum_leaks, dump_qir: dump_qir_before_after, reuse_ancillas,
+                pretty_qasm, emit_inverse, target, coupling_map, route,
+                gates: gates_file.unwrap_or_default(), count_only,
+                source_path: None,
+            };
+            compile_files(input, output.as_deref(), format, show, &options, cli.verbose)?;
         }
-        Commands::Run { input, simulate, no_opt } => {
-            run_file(&input, simulate, !no_opt, cli.verbose)?;
+        Commands::Run { input, simulate, no_opt, stats_json, shots, seed, amplitudes } => {
+            let simulation = SimulationOptions { enabled: simulate, shots, seed, amplitudes };
+            run_file(&input, &simulation, !no_opt, stats_json.as_deref(), cli.verbose)?;
         }
         Commands::Test { pattern, report } => {
             run_tests(pattern, report, cli.verbose)?;
@@ -190,9 +367,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Commands::Capabilities => {
             show_capabilities();
         }
+        Commands::Gates => {
+            show_gates();
+        }
+        Commands::Explain { code } => {
+            explain_code(&code);
+        }
         Commands::Check { input, ast } => {
             check_files(&input, ast, cli.verbose)?;
         }
+        Commands::Diff { a, b, optimize_a, optimize_b } => {
+            diff_files(&a, &b, optimize_a, optimize_b)?;
+        }
+        Commands::Format { input, write } => {
+            format_files(&input, write)?;
+        }
+        Commands::Watch { input, optimize, interval } => {
+            watch_file(&input, optimize, interval);
+        }
         Commands::Version => {
             show_version(cli.verbose);
         }
@@ -363,16 +555,18 @@ fn compile_files(
     output_dir: Option<&Path>,
     format: OutputFormat,
     show: bool,
-    optimize: bool,
+    options: &CompileOptions,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if !verbose {
-        let opt_msg = if optimize { "Enabled" } else { "Disabled" };
+        let opt_msg = if options.optimize { "Enabled" } else { "Disabled" };
         println!("{} Compilation started (Optimization: {})", "[INFO]".blue().bold(), opt_msg);
     }
     
     let total_files = inputs.len();
     let mut success_count = 0;
+    let mut totals = CompileStats::new();
+    let mut file_stats = Vec::new();
     
     let multi = MultiProgress::new();
     let style = ProgressStyle::with_template("{spinner:.cyan} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")
@@ -396,20 +590,40 @@ fn compile_files(
             }
         };
         
-        let result = Compiler::compile_with_stats(&source, optimize);
+        let file_options = CompileOptions { source_path: Some(input_path.clone()), ..options.clone() };
+        let (result, warnings) = Compiler::run_pipeline(&source, &file_options);
+        for warning in &warnings {
+            main_pb.suspend(|| eprintln!("Warning: {}", warning));
+        }
         
         match result {
             Ok(res) => {
                 success_count += 1;
+                totals.merge(&res.stats);
+                file_stats.push(FileStats { file: file_name.to_string(), stats: res.stats });
                 
-                let output_path = if let Some(dir) = output_dir {
-                    dir.join(input_path.file_name().unwrap()).with_extension("qasm")
-                } else {
-                    input_path.with_extension("qasm")
+                if options.count_only {
+                    main_pb.suspend(|| print_counts(&file_name, &res.stats));
+                    main_pb.inc(1);
+                    continue;
+                }
+                
+                let extension = match format {
+                    OutputFormat::Latex => "tex",
+                    OutputFormat::Json => "json",
+                    _ => "qasm",
                 };
                 
+                let output_path = Compiler::output_path(&input_path, output_dir, extension);
+                
                 match format {
                     OutputFormat::Qasm => fs::write(&output_path, &res.qasm)?,
+                    OutputFormat::Latex => fs::write(&output_path, latex_document(&res.ir))?,
+                    OutputFormat::Json => fs::write(&output_path, qclang_compiler::codegen::generate_json(&res.ir))?,
+                    OutputFormat::Both => {
+                        fs::write(&output_path, &res.qasm)?;
+                        fs::write(output_path.with_extension("json"), qclang_compiler::codegen::generate_json(&res.ir))?;
+                    }
                     _ => fs::write(&output_path, &res.qasm)?, 
                 }
                 
@@ -420,11 +634,23 @@ fn compile_files(
                 if verbose {
                     main_pb.suspend(|| print_file_stats(&file_name, &res.stats));
                 }
+                
+                if let Some(timings) = &res.timings {
+                    main_pb.suspend(|| print_pass_timings(&file_name, timings));
+                }
+                
+                if let Some(report) = &res.optimization {
+                    main_pb.suspend(|| print_optimization_report(&file_name, report));
+                }
+                
+                if let Some(snapshots) = &res.qir_snapshots {
+                    main_pb.suspend(|| print_qir_snapshots(&file_name, snapshots));
+                }
             }
             Err(errors) => {
                 main_pb.suspend(|| {
                     eprintln!("{} Compilation failed: {}", "[ERR]".red().bold(), input_path.display());
-                    print_errors(&errors);
+                    print_errors(&source, &errors);
                 });
             }
         }
@@ -433,6 +659,21 @@ fn compile_files(
     
     main_pb.finish_and_clear();
     
+    // Multi-file builds into an output directory also get a combined report
+    if let Some(dir) = output_dir {
+        if total_files > 1 {
+            let report = AggregateReport {
+                files: total_files,
+                compiled: success_count,
+                totals,
+                per_file: file_stats,
+            };
+            let report_path = dir.join("stats.json");
+            fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+            println!("{} Aggregate statistics written to {}", "[INFO]".blue().bold(), report_path.display());
+        }
+    }
+    
     if success_count == total_files {
         println!("{} All files compiled successfully.", "[OK]".green().bold());
     } else {
@@ -441,10 +682,22 @@ fn compile_files(
     Ok(())
 }
 
+/// The `--simulate` flags of `run`.
+struct SimulationOptions {
+    enabled: bool,
+    shots: Option<usize>,
+    seed: Option<u64>,
+    amplitudes: bool,
+}
+
+/// Circuits larger than this are too big to list amplitude by amplitude.
+const MAX_AMPLITUDE_QUBITS: usize = 10;
+
 fn run_file(
     input_path: &Path,
-    simulate: bool,
+    simulation: &SimulationOptions,
     optimize: bool,
+    stats_json: Option<&Path>,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("{} Processing: {}", "[INFO]".blue().bold(), input_path.display());
@@ -452,8 +705,15 @@ fn run_file(
     let source = fs::read_to_string(input_path)?;
     let start_time = Instant::now();
     
-    let result = Compiler::compile_with_stats(&source, optimize);
+    let (result, warnings) = Compiler::run_pipeline(&source, &CompileOptions {
+        optimize,
+        source_path: Some(input_path.to_path_buf()),
+        ..CompileOptions::default()
+    });
     let elapsed = start_time.elapsed();
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
     
     match result {
         Ok(res) => {
@@ -472,30 +732,62 @@ fn run_file(
             println!("{:<15}: {}", "Qubits", res.stats.qubits);
             println!("{:<15}: {}", "Gates", res.stats.gates);
             println!("{:<15}: {}", "Measurements", res.stats.measurements);
-            println!("{:<15}: {}", "Depth", res.stats.gates);
+            println!("{:<15}: {}", "Depth", res.ir.circuit_depth());
             println!();
+            
+            if let Some(stats_path) = stats_json {
+                let report = StatsReport::new(&res.stats, res.ir.circuit_depth(), elapsed);
+                fs::write(stats_path, serde_json::to_string_pretty(&report)?)?;
+                println!("{} Statistics written to {}", "[OK]".green().bold(), stats_path.display());
+                println!();
+            }
 
             // === SIMULATION BLOCK ===
-            if simulate {
+            if simulation.enabled {
                 println!("{}", "Quantum Simulation".bold().underline());
                 println!("{} Initializing Statevector Simulator...", "[INFO]".blue().bold());
                 
-                let mut sim = qclang_compiler::simulator::Simulator::new();
-                match sim.execute(&res.ir) {
-                    Ok(log) => {
-                        // Print raw log (cleaned of internal emojis if they existed in lib)
-                        println!("{}", log.replace("🚀 ", "").replace("🏁 ", ""));
-                        println!("{} Simulation finished successfully.", "[OK]".green().bold());
+                let mut sim = match simulation.seed {
+                    Some(seed) => Simulator::with_seed(seed),
+                    None => Simulator::new(),
+                };
+                if simulation.amplitudes && res.stats.measurements > 0 {
+                    println!("{} --amplitudes needs a circuit without measurements, which would collapse the state", "[ERR]".red().bold());
+                } else if simulation.amplitudes && res.stats.qubits > MAX_AMPLITUDE_QUBITS {
+                    println!(
+                        "{} --amplitudes supports at most {} qubits, the circuit has {}",
+                        "[ERR]".red().bold(), MAX_AMPLITUDE_QUBITS, res.stats.qubits
+                    );
+                } else if let Some(shots) = simulation.shots {
+                    match sim.sample(&res.ir, shots) {
+                        Ok(histogram) => {
+                            print_histogram(&histogram, shots);
+                            println!("{} Simulated {} shots.", "[OK]".green().bold(), shots);
+                        }
+                        Err(e) => {
+                            println!("{} Simulation Error: {}", "[ERR]".red().bold(), e);
+                        }
                     }
-                    Err(e) => {
-                        println!("{} Simulation Error: {}", "[ERR]".red().bold(), e);
+                } else {
+                    match sim.execute(&res.ir) {
+                        Ok(log) => {
+                            // Print raw log (cleaned of internal emojis if they existed in lib)
+                            println!("{}", log.replace("🚀 ", "").replace("🏁 ", ""));
+                            if simulation.amplitudes {
+                                print_amplitudes(sim.state_vector(), sim.num_qubits());
+                            }
+                            println!("{} Simulation finished successfully.", "[OK]".green().bold());
+                        }
+                        Err(e) => {
+                            println!("{} Simulation Error: {}", "[ERR]".red().bold(), e);
+                        }
                     }
                 }
                 println!();
             }
             // ========================
 
-            let output_path = input_path.with_extension("qasm");
+            let output_path = Compiler::output_path(input_path, None, "qasm");
             fs::write(&output_path, &res.qasm)?;
             println!("{} Output written to {}", "[OK]".green().bold(), output_path.display());
             
@@ -505,12 +797,47 @@ fn run_file(
         }
         Err(errors) => {
             eprintln!("{} Compilation failed", "[ERR]".red().bold());
-            print_errors(&errors);
+            print_errors(&source, &errors);
         }
     }
     Ok(())
 }
 
+/// Prints sampled bitstring counts with their share of `shots` and a bar.
+fn print_histogram(histogram: &[(String, usize)], shots: usize) {
+    const BAR_WIDTH: usize = 40;
+    let width = histogram.iter().map(|(bits, _)| bits.len()).max().unwrap_or(0).max("Outcome".len());
+    
+    println!("{:<width$} | {:>6} | {:>7}", "Outcome", "Count", "Prob", width = width);
+    println!("{:-<1$}", "-", width + 19);
+    for (bits, count) in histogram {
+        let probability = *count as f64 / shots as f64;
+        let bar = "#".repeat((probability * BAR_WIDTH as f64).round() as usize);
+        println!("{:<width$} | {:>6} | {:>7.4} {}", bits, count, probability, bar.cyan(), width = width);
+    }
+}
+
+/// Prints the amplitude and probability of every basis state, written
+/// `|q(n-1)...q0>` to match the bitstrings of `--shots`.
+fn print_amplitudes(state: &[Complex<f64>], num_qubits: usize) {
+    print!("{}", amplitude_table(state, num_qubits));
+}
+
+fn amplitude_table(state: &[Complex<f64>], num_qubits: usize) -> String {
+    let width = (num_qubits + 2).max("State".len());
+    
+    let mut table = format!("{:<width$} | {:>16} | {:>7}\n", "State", "Amplitude", "Prob", width = width);
+    table.push_str(&format!("{:-<1$}\n", "-", width + 29));
+    for (index, amplitude) in state.iter().enumerate() {
+        let label = format!("|{:0bits$b}>", index, bits = num_qubits);
+        table.push_str(&format!(
+            "{:<width$} | {:>8.4}{:+.4}i | {:>7.4}\n",
+            label, amplitude.re, amplitude.im, amplitude.norm_sqr(), width = width
+        ));
+    }
+    table
+}
+
 fn show_version(verbose: bool) {
     println!("qclang {} ({})", cargo_crate_version!(), qclang_compiler::build_timestamp());
     if verbose {
@@ -534,7 +861,13 @@ fn check_files(inputs: &[PathBuf], show_ast: bool, _verbose: bool) -> Result<(),
             }
         };
         
-        let tokens = qclang_compiler::lexer::tokenize(&source);
+        let (tokens, lex_errors) = qclang_compiler::lexer::tokenize_with_errors(&source);
+        if !lex_errors.is_empty() {
+            println!("{} {}: Lexer Error", "[ERR]".red().bold(), input_path.display());
+            for err in lex_errors { println!("  - {}", err); }
+            error_count += 1;
+            continue;
+        }
         let mut parser = qclang_compiler::parser::Parser::new(tokens.into_iter(), source.clone());
         let program = parser.parse_program();
         
@@ -547,7 +880,7 @@ fn check_files(inputs: &[PathBuf], show_ast: bool, _verbose: bool) -> Result<(),
              } else {
                  println!("{} {}: OK", "[OK]".green().bold(), input_path.display());
              }
-             if show_ast { println!("{:#?}", program); }
+             if show_ast { print!("{}", qclang_compiler::ast::debug::format_tree(&program)); }
         } else {
             println!("{} {}: Syntax Error", "[ERR]".red().bold(), input_path.display());
             for err in parser.errors { println!("  - {}", err); }
@@ -561,30 +894,271 @@ fn check_files(inputs: &[PathBuf], show_ast: bool, _verbose: bool) -> Result<(),
     Ok(())
 }
 
+fn diff_files(a: &Path, b: &Path, optimize_a: bool, optimize_b: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(left), Some(right)) = (compile_for_diff(a, optimize_a)?, compile_for_diff(b, optimize_b)?) else {
+        std::process::exit(1);
+    };
+    
+    let first_difference = left.iter().zip(&right).position(|(l, r)| l != r)
+        .or_else(|| (left.len() != right.len()).then(|| left.len().min(right.len())));
+    
+    match first_difference {
+        None => {
+            println!("{} {} and {} are equivalent ({} statements)",
+                "[OK]".green().bold(), a.display(), b.display(), left.len());
+        }
+        Some(index) => {
+            let statement = |lines: &[String]| lines.get(index).cloned().unwrap_or_else(|| "<end of program>".to_string());
+            println!("{} {} and {} differ at statement {}",
+                "[DIFF]".yellow().bold(), a.display(), b.display(), index + 1);
+            println!("  {}: {}", a.display(), statement(&left));
+            println!("  {}: {}", b.display(), statement(&right));
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// Normalized QASM statements for `path`, or `None` after reporting errors.
+fn compile_for_diff(path: &Path, optimize: bool) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+    let source = fs::read_to_string(path)?;
+    let options = CompileOptions { optimize, source_path: Some(path.to_path_buf()), ..CompileOptions::default() };
+    
+    match Compiler::run_pipeline(&source, &options).0 {
+        Ok(res) => Ok(Some(qclang_compiler::codegen::normalize_qasm(&res.qasm))),
+        Err(errors) => {
+            eprintln!("{} Compilation failed: {}", "[ERR]".red().bold(), path.display());
+            print_errors(&source, &errors);
+            Ok(None)
+        }
+    }
+}
+
+/// Whether `path` was modified since `prev_mtime`. Always true on the first
+/// check; a file that is temporarily missing (e.g. mid-save) is not rebuilt.
+fn should_recompile(prev_mtime: Option<SystemTime>, path: &Path) -> bool {
+    let current = fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (prev_mtime, current) {
+        (None, _) => true,
+        (Some(prev), Some(current)) => current != prev,
+        (Some(_), None) => false,
+    }
+}
+
+fn watch_file(input_path: &Path, optimize: bool, interval: u64) {
+    let options = CompileOptions { optimize, source_path: Some(input_path.to_path_buf()), ..CompileOptions::default() };
+    let mut last_mtime: Option<SystemTime> = None;
+    
+    loop {
+        if should_recompile(last_mtime, input_path) {
+            last_mtime = Some(fs::metadata(input_path).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH));
+            
+            // Clear the screen so each run starts from a clean view
+            print!("\x1B[2J\x1B[H");
+            println!("{} Compiling: {}", "[INFO]".blue().bold(), input_path.display());
+            
+            match fs::read_to_string(input_path) {
+                Ok(source) => {
+                    let start_time = Instant::now();
+                    let (result, warnings) = Compiler::run_pipeline(&source, &options);
+                    let elapsed = start_time.elapsed();
+                    for warning in &warnings {
+                        eprintln!("Warning: {}", warning);
+                    }
+                    
+                    match result {
+                        Ok(res) => {
+                            println!("{} Compiled in {:.4}s", "[OK]".green().bold(), elapsed.as_secs_f64());
+                            println!("{:<15}: {}", "Qubits", res.stats.qubits);
+                            println!("{:<15}: {}", "Gates", res.stats.gates);
+                            println!("{:<15}: {}", "Measurements", res.stats.measurements);
+                            println!("{:<15}: {}", "Depth", res.ir.circuit_depth());
+                        }
+                        Err(errors) => {
+                            eprintln!("{} Compilation failed", "[ERR]".red().bold());
+                            print_errors(&source, &errors);
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{} {}: {}", "[ERR]".red().bold(), input_path.display(), e);
+                }
+            }
+            
+            println!("\n{} Watching {} for changes (Ctrl+C to stop)", "[INFO]".blue().bold(), input_path.display());
+            let _ = io::stdout().flush();
+        }
+        
+        std::thread::sleep(Duration::from_millis(interval));
+    }
+}
+
+fn format_files(inputs: &[PathBuf], write: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut error_count = 0;
+    
+    for input_path in inputs {
+        let source = match fs::read_to_string(input_path) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("{} {}: {}", "[ERR]".red().bold(), input_path.display(), e);
+                error_count += 1;
+                continue;
+            }
+        };
+        
+        // Never rewrite a file we could not fully lex or parse
+        let (tokens, lex_errors) = qclang_compiler::lexer::tokenize_with_errors(&source);
+        if !lex_errors.is_empty() {
+            eprintln!("{} {}: Lexer Error", "[ERR]".red().bold(), input_path.display());
+            for err in lex_errors { eprintln!("  - {}", err); }
+            error_count += 1;
+            continue;
+        }
+        let token_lines: Vec<usize> = tokens.iter().map(|(_, line, _)| *line).collect();
+        let mut parser = qclang_compiler::parser::Parser::new(tokens.into_iter(), source.clone());
+        let program = parser.parse_program();
+        
+        if !parser.errors.is_empty() {
+            eprintln!("{} {}: Syntax Error", "[ERR]".red().bold(), input_path.display());
+            for err in parser.errors { eprintln!("  - {}", err); }
+            error_count += 1;
+            continue;
+        }
+        
+        let comments = qclang_compiler::lexer::comments(&source);
+        let formatted = qclang_compiler::ast::pretty::format_program_with_comments(&program, &token_lines, &comments);
+        
+        if write {
+            if formatted != source {
+                fs::write(input_path, &formatted)?;
+                println!("{} Formatted {}", "[OK]".green().bold(), input_path.display());
+            } else {
+                println!("{} {} already formatted", "[INFO]".blue().bold(), input_path.display());
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+    
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 fn start_repl() -> Result<(), Box<dyn std::error::Error>> {
     print_banner();
-    println!("Type 'exit' to quit.");
+    let stdin = io::stdin();
+    run_repl(&mut stdin.lock(), &mut io::stdout())
+}
+
+/// Wraps the accumulated REPL statements in an implicit `fn main()`.
+fn repl_source(statements: &[String]) -> String {
+    let mut source = String::from("fn main() -> int {\n");
+    for stmt in statements {
+        source.push_str("    ");
+        source.push_str(stmt);
+        source.push('\n');
+    }
+    source.push_str("    return 0;\n}\n");
+    source
+}
+
+/// Counts braces and parens left open in `text`, ignoring `//` comments.
+fn open_delimiters(text: &str) -> isize {
+    let mut depth = 0;
+    for line in text.lines() {
+        let code = line.split("//").next().unwrap_or("");
+        for ch in code.chars() {
+            match ch {
+                '{' | '(' => depth += 1,
+                '}' | ')' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    depth
+}
+
+fn run_repl<R: io::BufRead, W: Write>(reader: &mut R, out: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(out, "Type 'exit' to quit, ':show' to print the session, ':save FILE' to write it out, ':reset' to clear it.")?;
+    
+    let mut statements: Vec<String> = Vec::new();
+    let mut last_stats = CompileStats::new();
     
     loop {
-        print!("> ");
-        io::stdout().flush()?;
+        write!(out, "> ")?;
+        out.flush()?;
         
         let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+        if reader.read_line(&mut input)? == 0 { break; }
+        
+        // Keep reading continuation lines until braces and parens balance
+        while open_delimiters(&input) > 0 {
+            write!(out, "...> ")?;
+            out.flush()?;
+            if reader.read_line(&mut input)? == 0 { break; }
+        }
         let input = input.trim();
         
         if input == "quit" || input == "exit" { break; }
         if input.is_empty() { continue; }
         
+        match input {
+            ":reset" => {
+                statements.clear();
+                last_stats = CompileStats::new();
+                writeln!(out, "{} Session cleared.", "[INFO]".blue().bold())?;
+                continue;
+            }
+            ":show" => {
+                write!(out, "{}", repl_source(&statements))?;
+                continue;
+            }
+            _ if input == ":save" || input.starts_with(":save ") => {
+                let path = input[":save".len()..].trim();
+                if path.is_empty() {
+                    writeln!(out, "{} Usage: :save FILE", "[ERR]".red().bold())?;
+                } else {
+                    match fs::write(path, repl_source(&statements)) {
+                        Ok(()) => writeln!(out, "{} Saved {} statement(s) to {}", "[OK]".green().bold(), statements.len(), path)?,
+                        Err(e) => writeln!(out, "{} Cannot write {}: {}", "[ERR]".red().bold(), path, e)?,
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+        
         if input.starts_with("fn") {
-            // Note: REPL logic might need updates if we want to run simulation here too,
-            // but for now we keep it simple compilation.
+            // A full function is compiled on its own, outside the session buffer
             match Compiler::compile_with_stats(input, true) {
-                Ok(res) => println!("{}", res.qasm),
-                Err(e) => for err in e { println!("Error: {}", err); }
+                Ok(res) => writeln!(out, "{}", res.qasm)?,
+                Err(e) => for err in e { writeln!(out, "Error: {}", err)?; }
+            }
+            continue;
+        }
+        
+        // Session stats are unoptimized so qubits show up before they are measured
+        statements.push(input.to_string());
+        match Compiler::compile_with_stats(&repl_source(&statements), false) {
+            Ok(res) => {
+                let stats = res.stats;
+                writeln!(
+                    out,
+                    "{} qubits: {} ({:+}), gates: {} ({:+}), measurements: {} ({:+})",
+                    "[OK]".green().bold(),
+                    stats.qubits, stats.qubits as isize - last_stats.qubits as isize,
+                    stats.gates, stats.gates as isize - last_stats.gates as isize,
+                    stats.measurements, stats.measurements as isize - last_stats.measurements as isize,
+                )?;
+                last_stats = stats;
+            }
+            Err(e) => {
+                // Keep the session buffer valid by dropping the rejected line
+                statements.pop();
+                for err in e { writeln!(out, "Error: {}", err)?; }
             }
-        } else {
-            println!("Note: Only full functions supported in REPL currently.");
         }
     }
     Ok(())
@@ -645,10 +1219,57 @@ fn show_capabilities() {
     for cap in Compiler::capabilities() {
         println!(" - {}", cap);
     }
+    println!(" - Gates: {}", gates::name_list());
+}
+
+fn show_gates() {
+    println!("{:<8} | {:<6} | Angles", "Gate", "Qubits");
+    println!("{}", "-".repeat(26));
+    for gate in gates::all_supported() {
+        println!("{:<8} | {:<6} | {}", gate.name, gate.qubits, gate.angles);
+    }
+}
+
+fn explain_code(code: &str) {
+    match error::explain(code) {
+        Some(entry) => {
+            println!("{}: {}\n", entry.code.bold(), entry.summary);
+            println!("{}", entry.explanation);
+        }
+        None => {
+            eprintln!("{} no such error code: {} (known codes: {})", "[ERR]".red().bold(), code,
+                error::ERROR_CODES.iter().map(|entry| entry.code).collect::<Vec<_>>().join(", "));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_target(name: &str) -> Result<&'static Target, String> {
+    targets::lookup(name)
+        .ok_or_else(|| format!("unknown target '{}' (available: {})", name, targets::name_list()))
+}
+
+/// Loads the custom gate definitions in a `--gates-file`.
+fn parse_gates_file(path: &str) -> Result<GateRegistry, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    GateRegistry::from_json(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// A coupling-map edge written `a-b`.
+fn parse_edge(edge: &str) -> Result<(usize, usize), String> {
+    let (a, b) = edge.split_once('-').ok_or_else(|| format!("expected an edge like '0-1', got '{}'", edge))?;
+    let qubit = |text: &str| text.trim().parse::<usize>()
+        .map_err(|_| format!("invalid qubit index '{}' in edge '{}'", text.trim(), edge));
+    Ok((qubit(a)?, qubit(b)?))
 }
 
-fn print_errors(errors: &[String]) {
-    for e in errors { eprintln!("  - {}", e); }
+fn print_errors(source: &str, errors: &[CompileError]) {
+    for e in errors {
+        eprintln!("  - {}", e.render(source).replace('\n', "\n    "));
+    }
+    if let Some(first) = errors.first() {
+        eprintln!("  For more information about an error, try `qclang explain {}`.", first.code());
+    }
 }
 
 fn show_generated_code(code: &str, label: &str) {
@@ -663,6 +1284,181 @@ fn print_file_stats(filename: &str, stats: &CompileStats) {
     println!("Stats for {}: {} qubits, {} gates", filename, stats.qubits, stats.gates);
 }
 
+fn print_counts(filename: &str, stats: &CompileStats) {
+    println!(
+        "{}: {} qubits, {} cbits, {} gates, {} measurements",
+        filename, stats.qubits, stats.cbits, stats.gates, stats.measurements
+    );
+}
+
+fn print_pass_timings(filename: &str, timings: &PassTimings) {
+    println!("{} Pass timings for {}:", "[INFO]".blue().bold(), filename);
+    for (phase, elapsed) in &timings.phases {
+        println!("  {:<20} {:>10.3} ms", phase, elapsed.as_secs_f64() * 1000.0);
+    }
+    println!("  {:<20} {:>10.3} ms", "total", timings.total().as_secs_f64() * 1000.0);
+}
+
+/// Both dumps start with a `; ==== ... ====` line naming the stage, so the
+/// output can be split in two and passed to `diff`.
+fn print_qir_snapshots(filename: &str, snapshots: &QirSnapshots) {
+    println!("; ==== QIR before optimization: {} ====", filename);
+    print!("{}", snapshots.before);
+    println!("; ==== QIR after optimization: {} ====", filename);
+    print!("{}", snapshots.after);
+}
+
+/// The optimizer passes that ran, slowest first (`--profile`).
+fn print_optimization_report(filename: &str, report: &OptimizationReport) {
+    println!("{} Optimizer profile for {}:", "[INFO]".blue().bold(), filename);
+    if report.passes.is_empty() {
+        println!("  no optimizer passes ran (enable them with -O)");
+        return;
+    }
+    for pass in report.hottest() {
+        let change = match (pass.gates_removed, pass.gates_added) {
+            (0, 0) => "no gate change".to_string(),
+            (removed, 0) => format!("removed {} gate{}", removed, if removed == 1 { "" } else { "s" }),
+            (0, added) => format!("added {} gate{}", added, if added == 1 { "" } else { "s" }),
+            (removed, added) => format!("removed {} and added {} gates", removed, added),
+        };
+        println!("  {}: {} in {:.1?}", pass.name, change, pass.elapsed);
+    }
+}
+
+/// Machine-readable form of the `run` statistics (`--stats-json`).
+#[derive(serde::Serialize)]
+struct StatsReport {
+    qubits: usize,
+    cbits: usize,
+    gates: usize,
+    measurements: usize,
+    depth: usize,
+    compile_time_ms: f64,
+}
+
+impl StatsReport {
+    fn new(stats: &CompileStats, depth: usize, elapsed: Duration) -> Self {
+        Self {
+            qubits: stats.qubits,
+            cbits: stats.cbits,
+            gates: stats.gates,
+            measurements: stats.measurements,
+            depth,
+            compile_time_ms: elapsed.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Combined statistics for a multi-file `compile` with `--output`.
+#[derive(serde::Serialize)]
+struct AggregateReport {
+    files: usize,
+    compiled: usize,
+    totals: CompileStats,
+    per_file: Vec<FileStats>,
+}
+
+#[derive(serde::Serialize)]
+struct FileStats {
+    file: String,
+    stats: CompileStats,
+}
+
+fn latex_document(module: &qclang_compiler::qir::QirModule) -> String {
+    format!(
+        "\\documentclass{{standalone}}\n\\usepackage{{tikz}}\n\\usetikzlibrary{{quantikz}}\n\\begin{{document}}\n{}\\end{{document}}\n",
+        qclang_compiler::codegen::generate_quantikz(module)
+    )
+}
+
 fn print_circuit_diagram(stats: &CompileStats) {
     println!("Circuit Topography: [{} Qubits] -- [{} Gates]", stats.qubits, stats.gates);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repl(input: &str) -> String {
+        let mut out = Vec::new();
+        run_repl(&mut input.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn repl_statements_compile_together() {
+        let output = repl("let q: qubit = |0>;\nH(q);\n");
+        assert!(output.contains("qubits: 1 (+1), gates: 1 (+1)"), "{}", output);
+    }
+
+    #[test]
+    fn repl_reads_a_function_across_lines() {
+        let output = repl("fn main() -> int {\n    let q: qubit = |0>; H(q); let c: cbit = measure(q);\n    return 0; }\n");
+        assert_eq!(output.matches("...> ").count(), 2, "{}", output);
+        assert!(output.contains("h q[0];"), "{}", output);
+        assert!(!output.contains("Error"), "{}", output);
+    }
+
+    #[test]
+    fn stats_json_is_written_for_run() {
+        let dir = std::env::temp_dir().join(format!("qclang-stats-json-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("bell_state.qc");
+        fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests/bell_state.qc"), &input).unwrap();
+        let stats_path = dir.join("stats.json");
+
+        let simulation = SimulationOptions { enabled: false, shots: None, seed: None, amplitudes: false };
+        run_file(&input, &simulation, true, Some(&stats_path), false).unwrap();
+
+        let stats: serde_json::Value = serde_json::from_str(&fs::read_to_string(&stats_path).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        for (key, expected) in [("qubits", 2), ("cbits", 2), ("gates", 2), ("measurements", 2), ("depth", 3)] {
+            assert_eq!(stats[key], expected, "{}", stats);
+        }
+        assert!(stats["compile_time_ms"].is_f64(), "{}", stats);
+    }
+
+    #[test]
+    fn should_recompile_after_a_modification() {
+        let path = std::env::temp_dir().join(format!("qclang-watch-{}.qc", std::process::id()));
+        fs::write(&path, "fn main() -> int { return 0; }").unwrap();
+        let first = fs::metadata(&path).unwrap().modified().unwrap();
+
+        assert!(should_recompile(None, &path));
+        assert!(!should_recompile(Some(first), &path));
+
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(first + Duration::from_secs(5)).unwrap();
+        assert!(should_recompile(Some(first), &path));
+
+        fs::remove_file(&path).unwrap();
+        assert!(!should_recompile(Some(first), &path));
+    }
+
+    #[test]
+    fn repl_save_writes_a_compilable_program() {
+        let dir = std::env::temp_dir().join(format!("qclang-repl-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.qc");
+
+        let output = repl(&format!("let q: qubit = |0>;\nH(q);\nlet c: cbit = measure(q);\n:save {}\n", path.display()));
+        assert!(output.contains("Saved 3 statement(s)"), "{}", output);
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("fn main()"), "{}", saved);
+        let qasm = Compiler::compile_with_stats(&saved, false).unwrap().qasm;
+        assert!(qasm.contains("h q[0];"), "{}", qasm);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn amplitudes_of_a_hadamard_are_listed() {
+        let source = "fn main() -> int { let q: qubit = |0>; H(q); return 0; }";
+        let result = Compiler::compile_with_stats(source, false).unwrap();
+        let mut sim = Simulator::with_seed(1);
+        sim.execute(&result.ir).unwrap();
+        let table = amplitude_table(sim.state_vector(), sim.num_qubits());
+        assert!(table.contains("|0>   |   0.7071+0.0000i |  0.5000"), "{}", table);
+        assert!(table.contains("|1>   |   0.7071+0.0000i |  0.5000"), "{}", table);
+    }
+}
@@ -0,0 +1,137 @@
+// src/codegen/json.rs - JSON circuit exporter
+use crate::qir::{QirModule, QirOp, QirGate, QirValue, QirAngle};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Flat, ordered view of a compiled circuit for external tools.
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitJson {
+    pub name: String,
+    pub qubits: usize,
+    pub cbits: usize,
+    pub operations: Vec<OperationJson>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationJson {
+    pub op: String,
+    pub qubits: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub angle: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cbits: Option<Vec<usize>>,
+}
+
+impl CircuitJson {
+    pub fn from_module(module: &QirModule) -> Self {
+        let mut operations = Vec::new();
+        let mut temp_to_qubit: HashMap<usize, usize> = HashMap::new();
+        let mut qubits = HashSet::new();
+        let mut cbits = HashSet::new();
+
+        for func in &module.functions {
+            let block_ids = func.block_ids();
+
+            for block_id in block_ids {
+                for op in &func.blocks[&block_id].ops {
+                    match op {
+                        QirOp::AllocQubit { result, .. } => {
+                            let qubit = temp_to_qubit.len();
+                            temp_to_qubit.insert(result.id(), qubit);
+                        }
+                        QirOp::ApplyGate { gate, args, .. } => {
+                            let targets: Vec<usize> = args.iter()
+                                .filter_map(|arg| match arg {
+                                    QirValue::Qubit(q) => Some(q.id()),
+                                    QirValue::Temp(t) => temp_to_qubit.get(&t.id()).copied(),
+                                    _ => None,
+                                })
+                                .collect();
+                            if targets.is_empty() {
+                                continue;
+                            }
+                            qubits.extend(targets.iter().copied());
+                            operations.push(OperationJson {
+                                op: gate.base_name(),
+                                qubits: targets,
+                                angle: gate_angle(gate),
+                                cbits: None,
+                            });
+                        }
+                        QirOp::Measure { qubit, cbit } => {
+                            qubits.insert(qubit.id());
+                            cbits.insert(cbit.id());
+                            operations.push(OperationJson {
+                                op: "measure".to_string(),
+                                qubits: vec![qubit.id()],
+                                angle: None,
+                                cbits: Some(vec![cbit.id()]),
+                            });
+                        }
+                        QirOp::Barrier { qubits: barrier_qubits } => {
+                            let targets: Vec<usize> = barrier_qubits.iter().map(|q| q.id()).collect();
+                            qubits.extend(targets.iter().copied());
+                            operations.push(OperationJson {
+                                op: "barrier".to_string(),
+                                qubits: targets,
+                                angle: None,
+                                cbits: None,
+                            });
+                        }
+                        QirOp::Reset { qubit } => {
+                            qubits.insert(qubit.id());
+                            operations.push(OperationJson {
+                                op: "reset".to_string(),
+                                qubits: vec![qubit.id()],
+                                angle: None,
+                                cbits: None,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Self {
+            name: module.name.clone(),
+            qubits: qubits.len(),
+            cbits: cbits.len(),
+            operations,
+        }
+    }
+}
+
+/// Serializes the circuit in `module` as pretty-printed JSON.
+pub fn generate_json(module: &QirModule) -> String {
+    serde_json::to_string_pretty(&CircuitJson::from_module(module))
+        .unwrap_or_else(|_| "{}".to_string())
+}
+
+fn gate_angle(gate: &QirGate) -> Option<serde_json::Value> {
+    match gate {
+        QirGate::RX(angle) | QirGate::RY(angle) | QirGate::RZ(angle)
+        | QirGate::CRX(angle) | QirGate::CRY(angle) | QirGate::CRZ(angle) => Some(match angle {
+            QirAngle::Value(v) => serde_json::json!(v),
+            QirAngle::Symbolic(text) => serde_json::json!(text),
+        }),
+        QirGate::U3(theta, phi, lambda) => Some(serde_json::json!([theta, phi, lambda])),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, Compiler};
+
+    #[test]
+    fn single_h_program_lists_the_gate() {
+        let source = "fn main() -> int { let q: qubit = |0>; H(q); let c: cbit = measure(q); return 0; }";
+        let module = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&generate_json(&module)).unwrap();
+        let operations = json["operations"].as_array().unwrap();
+        assert!(operations.contains(&serde_json::json!({"op": "h", "qubits": [0]})), "{}", json);
+        assert!(operations.contains(&serde_json::json!({"op": "measure", "qubits": [0], "cbits": [0]})), "{}", json);
+    }
+}
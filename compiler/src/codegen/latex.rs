@@ -0,0 +1,270 @@
+// src/codegen/latex.rs - quantikz circuit diagram exporter
+use crate::qir::{QirModule, QirOp, QirGate, QirValue, QirAngle, BitState};
+use std::collections::HashMap;
+
+/// Renders the quantum operations of `module` as a `quantikz` environment.
+///
+/// Every qubit gets its own wire; gates are placed in the earliest timestep
+/// where all wires they touch (including the wires a multi-qubit gate spans)
+/// are free. Classical bits that condition a gate get a classical wire
+/// below the qubits, with the gate controlled from it (an open control when
+/// the condition is 0) and the measurement writing the bit drawn onto it.
+pub fn generate_quantikz(module: &QirModule) -> String {
+    let mut wires = 0;
+    let mut init_one: Vec<usize> = Vec::new();
+    let mut temp_to_qubit: HashMap<usize, usize> = HashMap::new();
+    let mut condition_cbits: Vec<usize> = Vec::new();
+
+    // First pass: size the wires the same way the QASM generator does
+    for func in &module.functions {
+        let block_ids = func.block_ids();
+
+        for block_id in block_ids {
+            for op in &func.blocks[&block_id].ops {
+                match op {
+                    QirOp::AllocQubit { result, init_state } => {
+                        let qubit = temp_to_qubit.len();
+                        temp_to_qubit.insert(result.id(), qubit);
+                        if let Some(BitState::One) = init_state {
+                            init_one.push(qubit);
+                        }
+                        wires = wires.max(qubit + 1);
+                    }
+                    QirOp::ApplyGate { args, .. } => {
+                        for arg in args {
+                            if let QirValue::Qubit(q) = arg {
+                                wires = wires.max(q.id() + 1);
+                            }
+                        }
+                    }
+                    QirOp::Measure { qubit, .. } => {
+                        wires = wires.max(qubit.id() + 1);
+                    }
+                    QirOp::ConditionalGate { cbit, qubits, .. } => {
+                        for q in qubits {
+                            wires = wires.max(q.id() + 1);
+                        }
+                        if !condition_cbits.contains(&cbit.id()) {
+                            condition_cbits.push(cbit.id());
+                        }
+                    }
+                    QirOp::Barrier { qubits } => {
+                        for q in qubits {
+                            wires = wires.max(q.id() + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    condition_cbits.sort_unstable();
+    let classical_wire = |cbit: usize| condition_cbits.iter()
+        .position(|&c| c == cbit)
+        .map(|index| wires + index);
+    let rows = wires + condition_cbits.len();
+    let mut empty_column = vec!["\\qw".to_string(); wires];
+    empty_column.resize(rows, "\\cw".to_string());
+
+    let mut columns: Vec<Vec<String>> = Vec::new();
+    let mut depth = vec![0usize; rows];
+
+    // Second pass: place each operation on the grid
+    for func in &module.functions {
+        let block_ids = func.block_ids();
+
+        for block_id in block_ids {
+            for op in &func.blocks[&block_id].ops {
+                let cells = match op {
+                    QirOp::ApplyGate { gate, args, .. } => {
+                        let qubits: Vec<usize> = args.iter()
+                            .filter_map(|arg| match arg {
+                                QirValue::Qubit(q) => Some(q.id()),
+                                QirValue::Temp(t) => temp_to_qubit.get(&t.id()).copied(),
+                                _ => None,
+                            })
+                            .collect();
+                        gate_cells(gate, &qubits)
+                    }
+                    QirOp::Measure { qubit, cbit } => match classical_wire(cbit.id()) {
+                        Some(wire) => vec![
+                            (qubit.id(), format!("\\meter{{}} \\vcw{{{}}}", wire - qubit.id())),
+                            (wire, "\\cw".to_string()),
+                        ],
+                        None => vec![(qubit.id(), "\\meter{}".to_string())],
+                    },
+                    QirOp::ConditionalGate { cbit, value, gate, qubits } => {
+                        let qubits: Vec<usize> = qubits.iter().map(|q| q.id()).collect();
+                        let mut cells = gate_cells(gate, &qubits);
+                        if let (Some(wire), Some(lowest)) = (classical_wire(cbit.id()), qubits.iter().max()) {
+                            let control = if *value != 0 { "ctrl" } else { "octrl" };
+                            cells.push((wire, format!("\\{}[vertical wire=c]{{{}}}", control, *lowest as isize - wire as isize)));
+                        }
+                        cells
+                    }
+                    QirOp::Barrier { qubits } => {
+                        let lo = qubits.iter().map(|q| q.id()).min();
+                        let hi = qubits.iter().map(|q| q.id()).max();
+                        match (lo, hi) {
+                            (Some(lo), Some(hi)) => vec![
+                                (lo, format!("\\qw \\barrier{{{}}}", hi - lo)),
+                                (hi, "\\qw".to_string()),
+                            ],
+                            _ => Vec::new(),
+                        }
+                    }
+                    QirOp::Reset { qubit } => vec![(qubit.id(), "\\gate{\\ket{0}}".to_string())],
+                    _ => continue,
+                };
+
+                if cells.is_empty() {
+                    continue;
+                }
+
+                // Multi-qubit gates block every wire between their outermost qubits
+                let lo = cells.iter().map(|(w, _)| *w).min().unwrap();
+                let hi = cells.iter().map(|(w, _)| *w).max().unwrap();
+                let column = (lo..=hi).map(|w| depth[w]).max().unwrap_or(0);
+
+                while columns.len() <= column {
+                    columns.push(empty_column.clone());
+                }
+                for (wire, cell) in cells {
+                    columns[column][wire] = cell;
+                }
+                for d in &mut depth[lo..=hi] {
+                    *d = column + 1;
+                }
+            }
+        }
+    }
+
+    let mut output = String::new();
+    output.push_str("\\begin{quantikz}\n");
+
+    for wire in 0..wires {
+        let ket = if init_one.contains(&wire) { "1" } else { "0" };
+        output.push_str(&format!("\\lstick{{$q_{{{}}}: \\ket{{{}}}$}}", wire, ket));
+        for column in &columns {
+            output.push_str(&format!(" & {}", column[wire]));
+        }
+        output.push_str(" & \\qw");
+        if wire + 1 < rows {
+            output.push_str(" \\\\");
+        }
+        output.push('\n');
+    }
+    for (index, cbit) in condition_cbits.iter().enumerate() {
+        output.push_str(&format!("\\lstick{{$c_{{{}}}$}}", cbit));
+        for column in &columns {
+            output.push_str(&format!(" & {}", column[wires + index]));
+        }
+        output.push_str(" & \\cw");
+        if wires + index + 1 < rows {
+            output.push_str(" \\\\");
+        }
+        output.push('\n');
+    }
+
+    output.push_str("\\end{quantikz}\n");
+    output
+}
+
+/// Returns the `(wire, cell)` pairs drawn for a single gate application.
+fn gate_cells(gate: &QirGate, qubits: &[usize]) -> Vec<(usize, String)> {
+    let offset = |from: usize, to: usize| to as isize - from as isize;
+
+    match gate {
+        QirGate::CNOT if qubits.len() >= 2 => vec![
+            (qubits[0], format!("\\ctrl{{{}}}", offset(qubits[0], qubits[1]))),
+            (qubits[1], "\\targ{}".to_string()),
+        ],
+        QirGate::SWAP if qubits.len() >= 2 => vec![
+            (qubits[0], format!("\\swap{{{}}}", offset(qubits[0], qubits[1]))),
+            (qubits[1], "\\targX{}".to_string()),
+        ],
+        QirGate::Toffoli if qubits.len() >= 3 => vec![
+            (qubits[0], format!("\\ctrl{{{}}}", offset(qubits[0], qubits[2]))),
+            (qubits[1], format!("\\ctrl{{{}}}", offset(qubits[1], qubits[2]))),
+            (qubits[2], "\\targ{}".to_string()),
+        ],
+        QirGate::CH | QirGate::CY | QirGate::CRX(_) | QirGate::CRY(_) | QirGate::CRZ(_) if qubits.len() >= 2 => vec![
+            (qubits[0], format!("\\ctrl{{{}}}", offset(qubits[0], qubits[1]))),
+            (qubits[1], format!("\\gate{{{}}}", gate_label(gate))),
+        ],
+        QirGate::Fredkin if qubits.len() >= 3 => vec![
+            (qubits[0], format!("\\ctrl{{{}}}", offset(qubits[0], qubits[1]))),
+            (qubits[1], format!("\\swap{{{}}}", offset(qubits[1], qubits[2]))),
+            (qubits[2], "\\targX{}".to_string()),
+        ],
+        _ if qubits.len() == 1 => vec![(qubits[0], format!("\\gate{{{}}}", gate_label(gate)))],
+        _ => Vec::new(),
+    }
+}
+
+fn gate_label(gate: &QirGate) -> String {
+    match gate {
+        QirGate::H | QirGate::CH => "H".to_string(),
+        QirGate::X => "X".to_string(),
+        QirGate::Y | QirGate::CY => "Y".to_string(),
+        QirGate::Z => "Z".to_string(),
+        QirGate::T => "T".to_string(),
+        QirGate::Tdg => "T^\\dagger".to_string(),
+        QirGate::S => "S".to_string(),
+        QirGate::Sdg => "S^\\dagger".to_string(),
+        QirGate::RX(angle) => format!("R_x({})", angle_label(angle)),
+        QirGate::RY(angle) => format!("R_y({})", angle_label(angle)),
+        QirGate::RZ(angle) => format!("R_z({})", angle_label(angle)),
+        QirGate::CRX(angle) => format!("R_x({})", angle_label(angle)),
+        QirGate::CRY(angle) => format!("R_y({})", angle_label(angle)),
+        QirGate::CRZ(angle) => format!("R_z({})", angle_label(angle)),
+        QirGate::U3(theta, phi, lambda) => format!("U_3({}, {}, {})", theta, phi, lambda),
+        QirGate::Custom { name, .. } => name.clone(),
+        other => format!("{:?}", other),
+    }
+}
+
+fn angle_label(angle: &QirAngle) -> String {
+    angle.to_string().replace("pi", "\\pi")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, Compiler};
+
+    fn quantikz(body: &str) -> String {
+        let source = format!("fn main() -> int {{ {} return 0; }}", body);
+        let module = Compiler::compile_to_qir(&source, &CompileOptions::default()).unwrap();
+        generate_quantikz(&module)
+    }
+
+    #[test]
+    fn bell_state_uses_gate_and_target_cells() {
+        let output = quantikz("let a: qubit = |0>; let b: qubit = |0>; H(a); CNOT(a, b);");
+        assert!(output.contains("\\gate{H}"), "{}", output);
+        assert!(output.contains("\\ctrl{1}"), "{}", output);
+        assert!(output.contains("\\targ{}"), "{}", output);
+    }
+
+    #[test]
+    fn conditional_gates_are_controlled_from_a_classical_wire() {
+        let output = quantikz("let q: qubit = |0>; let t: qubit = |0>; H(q); let c: cbit = measure(q); \
+            if (c == 1) { X(t); } if (c == 0) { Z(t); }");
+        assert!(output.contains("\\meter{} \\vcw{2}"), "{}", output);
+        assert!(output.contains("\\gate{X}"), "{}", output);
+        assert!(output.contains("\\lstick{$c_{0}$}"), "{}", output);
+        assert!(output.contains("\\ctrl[vertical wire=c]{-1}"), "{}", output);
+        assert!(output.contains("\\octrl[vertical wire=c]{-1}"), "{}", output);
+    }
+
+    #[test]
+    fn barriers_span_their_qubits() {
+        let output = quantikz("qreg r[3] = |000>; H(r[0]); barrier(r); X(r[2]);");
+        assert!(output.contains("\\qw \\barrier{2}"), "{}", output);
+        // The barrier keeps X from moving into the column of H
+        assert!(output.contains("\\gate{H} & \\qw \\barrier{2} & \\qw"), "{}", output);
+        assert!(output.contains("\\qw & \\qw & \\gate{X}"), "{}", output);
+    }
+}
@@ -1,4 +1,10 @@
 // src/codegen/mod.rs
 pub mod qasm;
+pub mod latex;
+pub mod json;
+pub mod qasm_parser;
 
-pub use qasm::QASMGenerator;
\ No newline at end of file
+pub use qasm::{QASMGenerator, normalize_qasm};
+pub use latex::generate_quantikz;
+pub use json::{generate_json, CircuitJson};
+pub use qasm_parser::{parse_qasm, QasmOp, QasmProgram};
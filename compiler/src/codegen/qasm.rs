@@ -1,5 +1,6 @@
 // src/codegen/qasm.rs - COMPLETE FIXED VERSION
-use crate::qir::{QirModule, QirFunction, QirBlock, QirOp, QirGate, QirValue, QubitId, CbitId, BitState, TempId};
+use crate::qir::{QirModule, QirFunction, QirBlock, QirOp, QirGate, QirAngle, QirValue, QubitId, CbitId, BitState, TempId};
+use crate::gates::GateRegistry;
 use std::collections::{HashMap, HashSet};
 
 pub struct QASMGenerator {
@@ -15,9 +16,32 @@ pub struct QASMGenerator {
     max_cbit_id: usize,                      // Track maximum cbit ID used
     used_qubits: HashSet<usize>,             // Qubits actually used in operations
     used_cbits: HashSet<usize>,              // Cbits actually used in operations
+    // OpenQASM 2.0 `if` compares a whole creg, so when there is more than one
+    // cbit each cbit that conditions a gate lives in its own `c<N>[1]`
+    condition_cbits: HashSet<usize>,
     initialization_gates: HashMap<usize, String>, // Qubit initialization gates
+    qubit_names: HashMap<usize, (String, usize)>, // Map from QubitId to (register, index)
+    qregs: Vec<(String, usize)>,             // Quantum registers in declaration order
+    /// Emit the version line, include and register declarations; when false
+    /// the output is a bare list of gate and measurement instructions.
+    pub emit_header: bool,
+    /// Label runs of gates, measurements, resets and barriers with section
+    /// comments and align operands into one column, for reading by hand.
+    pub pretty: bool,
+    /// QASM templates for custom gates; a custom gate without one is
+    /// emitted as a call to a gate of the same name.
+    pub gates: GateRegistry,
 }
 
+/// Names that would clash with the classical register or QASM keywords and
+/// gate names, so a source register using one falls back to the shared `q`.
+const RESERVED_REGISTER_NAMES: &[&str] = &[
+    "c", "qreg", "creg", "gate", "opaque", "measure", "reset", "barrier", "if", "include", "pi",
+    "id", "h", "x", "y", "z", "s", "sdg", "t", "tdg", "rx", "ry", "rz", "u1", "u2", "u3",
+    "crx", "cry",
+    "cx", "cy", "cz", "ch", "crz", "cu1", "cu3", "ccx", "cswap", "swap",
+];
+
 impl QASMGenerator {
     pub fn new() -> Self {
         Self {
@@ -33,7 +57,13 @@ impl QASMGenerator {
             max_cbit_id: 0,
             used_qubits: HashSet::new(),
             used_cbits: HashSet::new(),
+            condition_cbits: HashSet::new(),
             initialization_gates: HashMap::new(),
+            qubit_names: HashMap::new(),
+            qregs: Vec::new(),
+            emit_header: true,
+            pretty: false,
+            gates: GateRegistry::default(),
         }
     }
 
@@ -49,28 +79,143 @@ impl QASMGenerator {
         
         // First pass: collect all resources
         self.collect_resources(module);
+        self.build_register_layout(module);
+        if self.max_cbit_id == 0 {
+            self.condition_cbits.clear();
+        }
         
         // Generate register declarations
-        output.push_str(&format!("qreg q[{}];\n", self.max_qubit_id + 1));
-        output.push_str(&format!("creg c[{}];\n\n", self.max_cbit_id + 1));
+        for (name, size) in &self.qregs {
+            output.push_str(&format!("qreg {}[{}];\n", name, size));
+        }
+        output.push_str(&format!("creg c[{}];\n", self.max_cbit_id + 1));
+        let mut condition_cbits: Vec<usize> = self.condition_cbits.iter().copied().collect();
+        condition_cbits.sort();
+        for cbit in condition_cbits {
+            output.push_str(&format!("creg c{}[1];\n", cbit));
+        }
+        output.push('\n');
         
         // Generate initialization gates first
         output.push_str("// Initialization gates\n");
         for i in 0..=self.max_qubit_id {
-            if let Some(gate) = self.initialization_gates.get(&i) {
-                output.push_str(gate);
+            if self.initialization_gates.contains_key(&i) {
+                output.push_str(&format!("x {}; // Initialize |1>\n", self.qubit_ref(i)));
+                self.used_qubits.insert(i);
+                self.gate_count += 1;
             }
         }
         output.push_str("\n");
         
         // Generate code for each function
         for func in &module.functions {
-            output.push_str(&self.generate_function(func));
+            let code = self.generate_function(func);
+            if self.pretty {
+                output.push_str(&Self::prettify(&code));
+            } else {
+                output.push_str(&code);
+            }
         }
         
+        if !self.emit_header {
+            return Self::strip_header(&output);
+        }
         output
     }
     
+    /// Inserts a section comment wherever the kind of instruction changes
+    /// and pads instruction names so every operand list starts in the same
+    /// column. Existing comments and indentation are kept.
+    fn prettify(code: &str) -> String {
+        let split: Vec<Option<(&str, &str, &str)>> = code.lines()
+            .map(|line| {
+                let trimmed = line.trim_start();
+                if trimmed.is_empty() || trimmed.starts_with("//") {
+                    return None;
+                }
+                let indent = &line[..line.len() - trimmed.len()];
+                let (head, operands) = Self::split_instruction(trimmed);
+                Some((indent, head, operands))
+            })
+            .collect();
+        let width = split.iter().flatten().map(|(_, head, _)| head.len()).max().unwrap_or(0);
+        
+        let mut output = String::new();
+        let mut section = "";
+        for (line, parts) in code.lines().zip(&split) {
+            let Some((indent, head, operands)) = parts else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+            
+            let kind = match head.split(' ').next().unwrap_or_default() {
+                "measure" => "measurements",
+                "reset" => "resets",
+                "barrier" => "barriers",
+                _ => "gates",
+            };
+            if kind != section {
+                output.push_str(&format!("{}// {}\n", indent, kind));
+                section = kind;
+            }
+            output.push_str(&format!("{}{:<width$} {}\n", indent, head, operands, width = width));
+        }
+        output
+    }
+    
+    /// Splits an instruction into its name, with any parameters and `if`
+    /// condition, and its operands: `if (c[0]==1) rz(pi/2) q[1];` gives
+    /// `if (c[0]==1) rz(pi/2)` and `q[1];`.
+    fn split_instruction(line: &str) -> (&str, &str) {
+        // An `if` prefix adds two more spaces before the operands
+        let mut spaces = if line.starts_with("if") { 3 } else { 1 };
+        let mut depth = 0;
+        for (index, ch) in line.char_indices() {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ' ' if depth == 0 => {
+                    spaces -= 1;
+                    if spaces == 0 {
+                        return (&line[..index], line[index..].trim_start());
+                    }
+                }
+                _ => {}
+            }
+        }
+        (line, "")
+    }
+    
+    /// Keeps only instruction lines: drops the version line, include,
+    /// register declarations, comment-only lines and blank lines.
+    fn strip_header(qasm: &str) -> String {
+        qasm.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .filter(|line| !["OPENQASM", "include", "qreg", "creg"].iter().any(|keyword| line.starts_with(keyword)))
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+    
+    /// Flat index of every qubit id across the QASM registers, in
+    /// declaration order: the physical qubit a device would run it on.
+    pub fn physical_qubits(&mut self, module: &QirModule) -> HashMap<usize, usize> {
+        self.reset();
+        self.collect_resources(module);
+        self.build_register_layout(module);
+        
+        let mut offsets = HashMap::new();
+        let mut offset = 0;
+        for (name, size) in &self.qregs {
+            offsets.insert(name.clone(), offset);
+            offset += size;
+        }
+        self.qubit_names.iter()
+            .map(|(id, (name, index))| (*id, offsets[name] + index))
+            .collect()
+    }
+    
     pub fn qubit_count(&self) -> usize {
         self.used_qubits.len()
     }
@@ -100,7 +245,10 @@ impl QASMGenerator {
         self.max_cbit_id = 0;
         self.used_qubits.clear();
         self.used_cbits.clear();
+        self.condition_cbits.clear();
         self.initialization_gates.clear();
+        self.qubit_names.clear();
+        self.qregs.clear();
     }
     
     fn collect_resources(&mut self, module: &QirModule) {
@@ -123,7 +271,8 @@ impl QASMGenerator {
         // Track the next temp ID for this function
         let mut temp_counter = 0;
         
-        for (_, block) in &func.blocks {
+        for block_id in func.block_ids() {
+            let block = &func.blocks[&block_id];
             for op in &block.ops {
                 match op {
                     QirOp::AllocQubit { result, init_state } => {
@@ -141,10 +290,7 @@ impl QASMGenerator {
                         
                         // Store initialization gate if needed
                         if let Some(BitState::One) = init_state {
-                            self.initialization_gates.insert(
-                                qubit_id,
-                                format!("x q[{}]; // Initialize |1>\n", qubit_id)
-                            );
+                            self.initialization_gates.insert(qubit_id, "x".to_string());
                         }
                     }
                     QirOp::AllocCbit { result, init_value } => {
@@ -178,6 +324,21 @@ impl QASMGenerator {
                         self.register_qubit(qubit_id);
                         self.register_cbit(cbit_id);
                     }
+                    QirOp::Reset { qubit } => {
+                        self.register_qubit(qubit.id());
+                    }
+                    QirOp::Barrier { qubits } => {
+                        for qubit in qubits {
+                            self.register_qubit(qubit.id());
+                        }
+                    }
+                    QirOp::ConditionalGate { cbit, qubits, .. } => {
+                        self.register_cbit(cbit.id());
+                        self.condition_cbits.insert(cbit.id());
+                        for qubit in qubits {
+                            self.register_qubit(qubit.id());
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -219,20 +380,68 @@ impl QASMGenerator {
         }
     }
     
+    /// Assigns every qubit to a QASM register. Each `qreg` declared in the
+    /// source keeps its name (recorded by the builder as `qreg.<name>` module
+    /// metadata); the remaining qubits share a register named `q`.
+    fn build_register_layout(&mut self, module: &QirModule) {
+        let mut declared: Vec<(String, usize, usize)> = module.metadata.iter()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix("qreg.")?;
+                let (start, size) = value.split_once(',')?;
+                Some((name.to_string(), start.parse().ok()?, size.parse().ok()?))
+            })
+            .collect();
+        declared.sort_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+        
+        for (name, start, size) in declared {
+            let overlaps = (start..start + size).any(|id| self.qubit_names.contains_key(&id));
+            if size == 0 || overlaps || !is_register_name(&name) {
+                continue;
+            }
+            for index in 0..size {
+                self.qubit_names.insert(start + index, (name.clone(), index));
+            }
+            self.qregs.push((name, size));
+        }
+        
+        let shared: Vec<usize> = (0..=self.max_qubit_id)
+            .filter(|id| !self.qubit_names.contains_key(id))
+            .collect();
+        if self.qregs.is_empty() || !shared.is_empty() {
+            let mut name = "q".to_string();
+            while self.qregs.iter().any(|(taken, _)| *taken == name) {
+                name.push('_');
+            }
+            for (index, id) in shared.iter().enumerate() {
+                self.qubit_names.insert(*id, (name.clone(), index));
+            }
+            self.qregs.push((name, shared.len()));
+        }
+    }
+    
+    /// Formats a qubit as a `register[index]` operand.
+    fn qubit_ref(&self, qubit_id: usize) -> String {
+        match self.qubit_names.get(&qubit_id) {
+            Some((name, index)) => format!("{}[{}]", name, index),
+            None => format!("q[{}]", qubit_id),
+        }
+    }
+    
     fn generate_function(&mut self, func: &QirFunction) -> String {
         let mut output = String::new();
         
         output.push_str(&format!("// Function: {}\n", func.name));
         
         // Process blocks in order
-        let mut block_ids: Vec<_> = func.blocks.keys().collect();
-        block_ids.sort_by_key(|id| id.id());
+        let block_ids = func.block_ids();
         
         for block_id in block_ids {
-            if let Some(block) = func.blocks.get(block_id) {
-                if !block.ops.is_empty() {
+            if let Some(block) = func.blocks.get(&block_id) {
+                // Blocks that only hold classical ops or jumps emit nothing
+                let code = self.generate_block(block);
+                if !code.is_empty() {
                     output.push_str(&format!("  // Block {}\n", block_id.id()));
-                    output.push_str(&self.generate_block(block));
+                    output.push_str(&code);
                 }
             }
         }
@@ -244,15 +453,110 @@ impl QASMGenerator {
     fn generate_block(&mut self, block: &QirBlock) -> String {
         let mut output = String::new();
         
-        for op in &block.ops {
-            let op_str = self.generate_op(op);
-            if !op_str.is_empty() {
-                output.push_str(&format!("  {}", op_str));
+        let mut i = 0;
+        while i < block.ops.len() {
+            // Collapse a full-register measurement into a single statement
+            let run = self.whole_register_measure_len(&block.ops[i..]);
+            if run > 0 {
+                for op in &block.ops[i..i + run] {
+                    if let QirOp::Measure { qubit, cbit } = op {
+                        self.used_qubits.insert(qubit.id());
+                        self.used_cbits.insert(cbit.id());
+                    }
+                }
+                self.measurement_count += run;
+                output.push_str(&format!("  measure {} -> c;\n", self.qregs[0].0));
+                i += run;
+                continue;
+            }
+            
+            // Likewise a single-qubit gate applied to each qubit of a register
+            if let Some((register, width)) = self.whole_register_gate(&block.ops[i..]) {
+                if let QirOp::ApplyGate { gate, args, .. } = &block.ops[i] {
+                    // Emit the first application and widen its operand to the register
+                    let first = self.generate_gate(gate, args);
+                    let operand = format!("{}[0];", register);
+                    let statement = first.trim_end().strip_suffix(&operand).unwrap_or_default();
+                    output.push_str(&format!("  {}{};\n", statement, register));
+                }
+                for op in &block.ops[i + 1..i + width] {
+                    if let QirOp::ApplyGate { args: op_args, .. } = op {
+                        if let Some(QirValue::Qubit(qubit)) = op_args.first() {
+                            self.used_qubits.insert(qubit.id());
+                        }
+                    }
+                }
+                self.gate_count += width - 1;
+                i += width;
+                continue;
+            }
+            
+            // Custom gates may expand to several statements
+            let op_str = self.generate_op(&block.ops[i]);
+            for line in op_str.lines() {
+                output.push_str(&format!("  {}\n", line));
             }
+            i += 1;
         }
         
         output
     }
+    
+    /// Returns the number of leading ops that measure `q[k] -> c[k]` for every
+    /// index of both registers, or 0 if the ops don't cover the whole register.
+    /// Only applies when there is a single quantum register.
+    fn whole_register_measure_len(&self, ops: &[QirOp]) -> usize {
+        let (name, width) = match self.qregs.as_slice() {
+            [(name, width)] => (name, *width),
+            _ => return 0,
+        };
+        if width < 2 || self.max_cbit_id + 1 != width || ops.len() < width {
+            return 0;
+        }
+        
+        for (k, op) in ops.iter().take(width).enumerate() {
+            match op {
+                QirOp::Measure { qubit, cbit }
+                    if self.qubit_names.get(&qubit.id()) == Some(&(name.clone(), k))
+                        && cbit.id() == k && !self.condition_cbits.contains(&k) => {}
+                _ => return 0,
+            }
+        }
+        
+        width
+    }
+
+    /// If the leading ops apply the same single-qubit gate to `r[0]`,
+    /// `r[1]`, ... through the last qubit of a register `r` of at least two
+    /// qubits, returns the register's name and width. Custom gates, which
+    /// expand to templates, are left alone.
+    fn whole_register_gate(&self, ops: &[QirOp]) -> Option<(String, usize)> {
+        let qubit_of = |op: &QirOp| match op {
+            QirOp::ApplyGate { gate, args, .. } => match args.as_slice() {
+                [QirValue::Qubit(qubit)] => Some((gate.clone(), qubit.id())),
+                _ => None,
+            },
+            _ => None,
+        };
+        
+        let (gate, first) = qubit_of(ops.first()?)?;
+        if gate.arity() != 1 || matches!(gate, QirGate::Custom { .. }) {
+            return None;
+        }
+        let (name, index) = self.qubit_names.get(&first)?;
+        let width = self.qregs.iter().find(|(register, _)| register == name)?.1;
+        if *index != 0 || width < 2 || ops.len() < width {
+            return None;
+        }
+        
+        for (k, op) in ops.iter().enumerate().take(width).skip(1) {
+            let (next_gate, qubit) = qubit_of(op)?;
+            if next_gate != gate || self.qubit_names.get(&qubit) != Some(&(name.clone(), k)) {
+                return None;
+            }
+        }
+        Some((name.clone(), width))
+    }
 
 // In qasm.rs - Update the generate_op function
 fn generate_op(&mut self, op: &QirOp) -> String {
@@ -270,6 +574,36 @@ fn generate_op(&mut self, op: &QirOp) -> String {
             self.generate_measurement(*qubit, *cbit)
         }
         
+        QirOp::Reset { qubit } => {
+            self.used_qubits.insert(qubit.id());
+            format!("reset {};\n", self.qubit_ref(qubit.id()))
+        }
+        
+        QirOp::Barrier { qubits } => {
+            let operands: Vec<String> = qubits.iter()
+                .map(|qubit| {
+                    self.used_qubits.insert(qubit.id());
+                    self.qubit_ref(qubit.id())
+                })
+                .collect();
+            format!("barrier {};\n", operands.join(","))
+        }
+        
+        QirOp::ConditionalGate { cbit, value, gate, qubits } => {
+            let args: Vec<QirValue> = qubits.iter().map(|qubit| QirValue::Qubit(*qubit)).collect();
+            let gate_str = self.generate_gate(gate, &args);
+            if gate_str.is_empty() {
+                return gate_str;
+            }
+            
+            let condition = if self.condition_cbits.contains(&cbit.id()) {
+                format!("c{}=={}", cbit.id(), value)
+            } else {
+                format!("c=={}", value)
+            };
+            gate_str.lines().map(|line| format!("if ({}) {}\n", condition, line)).collect()
+        }
+        
         QirOp::AllocCbit { result: _, init_value: _ } => {
             // Already handled in resource collection
             String::new()
@@ -323,78 +657,68 @@ fn generate_op(&mut self, op: &QirOp) -> String {
             return String::new();
         }
         
+        let operands: Vec<String> = qubit_indices.iter().map(|&idx| self.qubit_ref(idx)).collect();
+        
+        // Operands are controls first, then targets; a gate with the wrong
+        // number of resolved qubits is reported rather than emitted shifted
+        let Some((controls, targets)) = gate.split_operands(&operands) else {
+            return format!(
+                "// Gate {} expects {} qubit operand(s), got {}\n",
+                gate.base_name(), gate.arity(), operands.len()
+            );
+        };
+        
         let gate_str = match gate {
-            QirGate::H => {
+            QirGate::H | QirGate::X | QirGate::Y | QirGate::Z |
+            QirGate::T | QirGate::Tdg | QirGate::S | QirGate::Sdg => {
                 self.gate_count += 1;
-                format!("h q[{}];\n", qubit_indices[0])
+                format!("{} {};\n", gate.base_name(), targets[0])
             }
-            QirGate::X => {
+            QirGate::RX(angle) | QirGate::RY(angle) | QirGate::RZ(angle) => {
                 self.gate_count += 1;
-                format!("x q[{}];\n", qubit_indices[0])
+                format!("{}({}) {};\n", gate.base_name(), angle, targets[0])
             }
-            QirGate::Y => {
-                self.gate_count += 1;
-                format!("y q[{}];\n", qubit_indices[0])
-            }
-            QirGate::Z => {
+            QirGate::SWAP => {
                 self.gate_count += 1;
-                format!("z q[{}];\n", qubit_indices[0])
+                format!("swap {}, {};\n", targets[0], targets[1])
             }
-            QirGate::T => {
+            QirGate::CNOT | QirGate::CH | QirGate::CY => {
                 self.gate_count += 1;
-                format!("t q[{}];\n", qubit_indices[0])
+                format!("{} {}, {};\n", gate.base_name(), controls[0], targets[0])
             }
-            QirGate::S => {
+            QirGate::CRX(angle) | QirGate::CRY(angle) | QirGate::CRZ(angle) => {
                 self.gate_count += 1;
-                format!("s q[{}];\n", qubit_indices[0])
+                format!("{}({}) {}, {};\n", gate.base_name(), angle, controls[0], targets[0])
             }
-            QirGate::RX(angle) => {
+            QirGate::Toffoli => {
                 self.gate_count += 1;
-                format!("rx({}) q[{}];\n", angle, qubit_indices[0])
+                format!("ccx {}, {}, {};\n", controls[0], controls[1], targets[0])
             }
-            QirGate::RY(angle) => {
+            QirGate::Fredkin => {
                 self.gate_count += 1;
-                format!("ry({}) q[{}];\n", angle, qubit_indices[0])
+                format!("cswap {}, {}, {};\n", controls[0], targets[0], targets[1])
             }
-            QirGate::RZ(angle) => {
+            QirGate::U3(theta, phi, lambda) => {
                 self.gate_count += 1;
-                format!("rz({}) q[{}];\n", angle, qubit_indices[0])
+                let angle = |value: &f64| QirAngle::Value(*value).to_string();
+                format!("u3({}, {}, {}) {};\n", angle(theta), angle(phi), angle(lambda), targets[0])
             }
-            QirGate::CNOT => {
-                if qubit_indices.len() >= 2 {
-                    self.gate_count += 1;
-                    format!("cx q[{}], q[{}];\n", qubit_indices[0], qubit_indices[1])
-                } else {
-                    String::new()
+            QirGate::Custom { name, .. } => match self.gates.custom(name) {
+                Some(custom) => {
+                    // One line, and one counted gate, per template statement
+                    let lines: Vec<String> = custom.expand(&operands).split(';')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(|s| format!("{};\n", s))
+                        .collect();
+                    self.gate_count += lines.len();
+                    lines.concat()
                 }
-            }
-            QirGate::SWAP => {
-                if qubit_indices.len() >= 2 {
-                    self.gate_count += 3;
-                    format!("cx q[{}], q[{}];\ncx q[{}], q[{}];\ncx q[{}], q[{}]; // SWAP\n", 
-                           qubit_indices[0], qubit_indices[1], 
-                           qubit_indices[1], qubit_indices[0], 
-                           qubit_indices[0], qubit_indices[1])
-                } else {
-                    String::new()
-                }
-            }
-            QirGate::Toffoli => {
-                if qubit_indices.len() >= 3 {
+                None => {
                     self.gate_count += 1;
-                    format!("ccx q[{}], q[{}], q[{}];\n", 
-                           qubit_indices[0], qubit_indices[1], qubit_indices[2])
-                } else {
-                    String::new()
+                    format!("{} {};\n", name, operands.join(", "))
                 }
-            }
-            QirGate::U3(theta, phi, lambda) => {
-                self.gate_count += 1;
-                format!("u3({}, {}, {}) q[{}];\n", theta, phi, lambda, qubit_indices[0])
-            }
-            _ => {
-                format!("// Unsupported gate: {:?} on qubits {:?}\n", gate, qubit_indices)
-            }
+            },
         };
         
         gate_str
@@ -417,6 +741,237 @@ fn generate_op(&mut self, op: &QirOp) -> String {
         }
         
         self.measurement_count += 1;
-        format!("measure q[{}] -> c[{}];\n", qubit_idx, cbit_idx)
+        if self.condition_cbits.contains(&cbit_idx) {
+            return format!("measure {} -> c{}[0];\n", self.qubit_ref(qubit_idx), cbit_idx);
+        }
+        format!("measure {} -> c[{}];\n", self.qubit_ref(qubit_idx), cbit_idx)
+    }
+}
+
+/// OpenQASM 2.0 identifiers start with a lowercase letter. `c<N>` is kept
+/// for the registers of cbits that condition gates.
+fn is_register_name(name: &str) -> bool {
+    let condition_register = name.strip_prefix('c')
+        .is_some_and(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()));
+    
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED_REGISTER_NAMES.contains(&name)
+        && !condition_register
+}
+
+/// Reduces QASM text to its statements, one per line, with comments dropped
+/// and spacing made uniform, so two programs can be compared structurally.
+pub fn normalize_qasm(qasm: &str) -> Vec<String> {
+    qasm.lines()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" ").replace(" ,", ",").replace(", ", ","))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, Compiler};
+
+    #[test]
+    fn condition_cbits_get_their_own_register() {
+        let source = "fn main() -> int {
+            qreg q[3] = |000>;
+            let m: cbit = measure(q[0]);
+            let n: cbit = measure(q[1]);
+            if (m == 1) { X(q[2]); }
+            return 0;
+        }";
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        for line in ["creg c[2];", "creg c0[1];", "measure q[0] -> c0[0];", "measure q[1] -> c[1];", "if (c0==1) x q[2];"] {
+            assert!(qasm.contains(line), "missing `{}` in\n{}", line, qasm);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn measuring_a_register_measures_every_qubit() {
+        let source = "fn main() -> int {
+            qreg qs[3] = |000>;
+            H(qs[0]);
+            let r: cbit[3] = measure(qs);
+            return 0;
+        }";
+        let result = Compiler::compile_with_stats(source, false).unwrap();
+        assert_eq!(result.stats.measurements, 3);
+        assert!(result.qasm.contains("measure qs -> c;"), "{}", result.qasm);
+    }
+
+    #[test]
+    fn rotation_angles_keep_pi() {
+        let source = "fn main() -> int {
+            let q: qubit = |0>;
+            RX(pi, q);
+            RZ(pi / 2, q);
+            let c: cbit = measure(q);
+            return 0;
+        }";
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        assert!(qasm.contains("rx(pi) q[0];"), "{}", qasm);
+        assert!(qasm.contains("rz(pi/2) q[0];"), "{}", qasm);
+    }
+
+    #[test]
+    fn reset_makes_a_measured_qubit_usable() {
+        let source = "fn main() -> int {
+            let q: qubit = |0>;
+            H(q);
+            let c: cbit = measure(q);
+            reset(q);
+            X(q);
+            let d: cbit = measure(q);
+            return 0;
+        }";
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        assert!(qasm.contains("measure q[0] -> c[0];\n  reset q[0];\n  x q[0];"), "{}", qasm);
+    }
+
+    #[test]
+    fn source_registers_keep_their_names() {
+        let source = "fn main() -> int {
+            qreg data[3] = |000>;
+            let q: qubit = |0>;
+            H(data[1]);
+            CNOT(data[1], q);
+            return 0;
+        }";
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        for line in ["qreg data[3];", "qreg q[1];", "h data[1];", "cx data[1], q[0];"] {
+            assert!(qasm.contains(line), "missing `{}` in\n{}", line, qasm);
+        }
+    }
+
+    #[test]
+    fn controlled_rotation_is_one_two_qubit_gate() {
+        let source = "fn main() -> int { let q1: qubit = |0>; let q2: qubit = |0>; CRZ(pi / 2, q1, q2); return 0; }";
+        let result = Compiler::compile_with_stats(source, false).unwrap();
+        assert_eq!((result.stats.gates, result.stats.qubits), (1, 2));
+        assert!(result.qasm.contains("crz(pi/2) q[0], q[1];"), "{}", result.qasm);
+    }
+
+    #[test]
+    fn normalized_qasm_compares_statements() {
+        let compile = |gates: &str, optimize: bool| {
+            let source = format!("fn main() -> int {{ let q: qubit = |0>; {} return 0; }}", gates);
+            super::normalize_qasm(&Compiler::compile_with_stats(&source, optimize).unwrap().qasm)
+        };
+        assert_eq!(compile("H(q); H(q);", true), compile("", false));
+        assert_ne!(compile("H(q);", false), compile("X(q);", false));
+        assert_eq!(super::normalize_qasm("cx  q[0] ,q[1]; // entangle\n\n"), vec!["cx q[0],q[1];"]);
+    }
+
+    #[test]
+    fn controlled_hadamard_is_one_two_qubit_gate() {
+        let source = "fn main() -> int { let q1: qubit = |0>; let q2: qubit = |0>; CH(q1, q2); return 0; }";
+        let result = Compiler::compile_with_stats(source, false).unwrap();
+        assert_eq!((result.stats.gates, result.stats.qubits), (1, 2));
+        assert!(result.qasm.contains("ch q[0], q[1];"), "{}", result.qasm);
+    }
+
+    #[test]
+    fn controlled_y_is_one_two_qubit_gate() {
+        let source = "fn main() -> int { let q1: qubit = |0>; let q2: qubit = |0>; CY(q1, q2); return 0; }";
+        let result = Compiler::compile_with_stats(source, false).unwrap();
+        assert_eq!((result.stats.gates, result.stats.qubits), (1, 2));
+        assert!(result.qasm.contains("cy q[0], q[1];"), "{}", result.qasm);
+
+        let errors = Compiler::compile_with_stats("fn main() -> int { let q1: qubit = |0>; CY(q1); return 0; }", false).err().unwrap();
+        assert!(errors[0].contains("Gate CY expects 2 arguments, got 1"), "{:?}", errors);
+    }
+
+    #[test]
+    fn output_is_deterministic() {
+        let source = "fn main() -> int {
+            qreg q[3] = |000>;
+            H(q[0]);
+            let m: cbit = measure(q[0]);
+            let mut x: int = 0;
+            if (m == 1) { x = 1; } else { x = 2; }
+            if (m == 1) { X(q[1]); } else { Z(q[2]); }
+            let r: cbit = measure(q[1]);
+            return x;
+        }";
+        let first = Compiler::compile_with_stats(source, false).unwrap();
+        assert!(first.ir.functions[0].blocks.len() > 1);
+        for _ in 0..10 {
+            let again = Compiler::compile_with_stats(source, false).unwrap();
+            assert_eq!(again.qasm, first.qasm);
+            assert_eq!(again.ir.to_string(), first.ir.to_string());
+        }
+    }
+
+    #[test]
+    fn measure_writes_to_an_explicit_target() {
+        let source = "fn main() -> int {
+            let q0: qubit = |0>;
+            let q1: qubit = |0>;
+            H(q0);
+            cbit c[2];
+            measure(q1, c[1]);
+            measure(q0, c[0]);
+            return 0;
+        }";
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        assert!(qasm.contains("measure q[1] -> c[1];\n  measure q[0] -> c[0];"), "{}", qasm);
+    }
+
+    #[test]
+    fn one_states_are_prepared_with_x() {
+        let result = Compiler::compile_with_stats("fn main() -> int { let q: qubit = |1>; return 0; }", false).unwrap();
+        assert!(result.qasm.contains("x q[0]; // Initialize |1>"), "{}", result.qasm);
+        assert_eq!(result.stats.gates, 1);
+
+        let result = Compiler::compile_with_stats("fn main() -> int { qreg r[3] = |101>; return 0; }", false).unwrap();
+        assert!(result.qasm.contains("x r[0]; // Initialize |1>\nx r[2]; // Initialize |1>"), "{}", result.qasm);
+        assert_eq!(result.stats.gates, 2);
+    }
+
+    #[test]
+    fn no_header_leaves_only_the_body() {
+        let source = "fn main() -> int { let q: qubit = |0>; H(q); let c: cbit = measure(q); return 0; }";
+        let options = CompileOptions { no_header: true, ..CompileOptions::default() };
+        let qasm = Compiler::compile_with_options(source, &options).unwrap().qasm;
+        assert_eq!(qasm, "h q[0];\nmeasure q[0] -> c[0];\n");
+    }
+
+    #[test]
+    fn basis_measurements_rotate_first() {
+        let source = "fn main() -> int {
+            let q: qubit = |0>; let p: qubit = |0>;
+            let c: cbit = measure_x(q);
+            let d: cbit = measure_y(p);
+            return 0;
+        }";
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        assert!(qasm.contains("h q[0];\n  measure q[0] -> c[0];\n  sdg q[1];\n  h q[1];\n  measure q[1] -> c[1];"), "{}", qasm);
+    }
+
+    #[test]
+    fn cnot_keeps_control_before_target() {
+        let source = "fn main() -> int {
+            let target: qubit = |0>; let control: qubit = |0>;
+            H(control);
+            CNOT(control, target);
+            return 0;
+        }";
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        assert!(qasm.contains("cx q[1], q[0];"), "{}", qasm);
+        assert!(!qasm.contains("cx q[0], q[1];"), "{}", qasm);
+    }
+
+    #[test]
+    fn pretty_qasm_adds_section_comments_and_alignment() {
+        let source = "fn main() -> int { let q: qubit = |0>; H(q); let c: cbit = measure(q); return 0; }";
+        let pretty = Compiler::compile_with_options(source, &CompileOptions { pretty_qasm: true, ..CompileOptions::default() }).unwrap().qasm;
+        assert!(pretty.contains("// gates\n  h       q[0];\n  // measurements\n  measure q[0] -> c[0];"), "{}", pretty);
+
+        let terse = Compiler::compile_with_options(source, &CompileOptions::default()).unwrap().qasm;
+        assert!(!terse.contains("// measurements"), "{}", terse);
+        assert!(terse.contains("h q[0];"), "{}", terse);
+    }
+}
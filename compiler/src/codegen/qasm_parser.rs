@@ -0,0 +1,267 @@
+// src/codegen/qasm_parser.rs - Reads back the OpenQASM 2.0 subset we emit
+
+/// One instruction read back from OpenQASM. Qubits and cbits are flat
+/// indices across the registers in declaration order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QasmOp {
+    /// Lower-case QASM name: a gate such as `cx`, or `measure`, `reset`, `barrier`.
+    pub name: String,
+    /// Angle expressions exactly as written, e.g. `pi/2`.
+    pub params: Vec<String>,
+    pub qubits: Vec<usize>,
+    pub cbits: Vec<usize>,
+    /// `(cbit, value)` for `if (c[i]==value) ...` instructions.
+    pub condition: Option<(usize, i64)>,
+}
+
+/// The registers and instructions of a parsed OpenQASM program.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QasmProgram {
+    pub qregs: Vec<(String, usize)>,
+    pub cregs: Vec<(String, usize)>,
+    pub ops: Vec<QasmOp>,
+}
+
+impl QasmProgram {
+    pub fn qubit_count(&self) -> usize {
+        self.qregs.iter().map(|(_, size)| size).sum()
+    }
+
+    pub fn cbit_count(&self) -> usize {
+        self.cregs.iter().map(|(_, size)| size).sum()
+    }
+
+    /// Number of gate instructions, excluding measurements, resets and barriers.
+    pub fn gate_count(&self) -> usize {
+        self.ops.iter()
+            .filter(|op| !matches!(op.name.as_str(), "measure" | "reset" | "barrier"))
+            .count()
+    }
+}
+
+/// Gates the QASM backend can emit, with their qubit and parameter counts.
+const QASM_GATES: &[(&str, usize, usize)] = &[
+    ("h", 1, 0), ("x", 1, 0), ("y", 1, 0), ("z", 1, 0),
+    ("s", 1, 0), ("sdg", 1, 0), ("t", 1, 0), ("tdg", 1, 0),
+    ("rx", 1, 1), ("ry", 1, 1), ("rz", 1, 1), ("u3", 1, 3),
+    ("cx", 2, 0), ("swap", 2, 0), ("ch", 2, 0), ("cy", 2, 0),
+    ("crx", 2, 1), ("cry", 2, 1), ("crz", 2, 1), ("ccx", 3, 0), ("cswap", 3, 0),
+];
+
+/// Parses OpenQASM 2.0 as produced by [`QASMGenerator`](super::QASMGenerator):
+/// register declarations, the supported gates, conditional gates,
+/// measurements, resets and barriers. Returns the first error with its line.
+pub fn parse_qasm(source: &str) -> Result<QasmProgram, String> {
+    let mut program = QasmProgram::default();
+
+    for (index, line) in source.lines().enumerate() {
+        let code = line.split("//").next().unwrap_or_default();
+        for statement in code.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            parse_statement(statement, &mut program)
+                .map_err(|message| format!("line {}: {}", index + 1, message))?;
+        }
+    }
+
+    Ok(program)
+}
+
+fn parse_statement(statement: &str, program: &mut QasmProgram) -> Result<(), String> {
+    let (keyword, rest) = statement.split_once(char::is_whitespace).unwrap_or((statement, ""));
+    let rest = rest.trim();
+
+    match keyword {
+        "OPENQASM" => {
+            if !rest.starts_with("2.") {
+                return Err(format!("Unsupported OpenQASM version '{}'", rest));
+            }
+        }
+        "include" => {}
+        "qreg" => program.qregs.push(parse_declaration(rest)?),
+        "creg" => program.cregs.push(parse_declaration(rest)?),
+        "measure" => {
+            let (qubit_part, cbit_part) = rest.split_once("->")
+                .ok_or_else(|| format!("Expected '->' in measurement '{}'", statement))?;
+            let qubits = resolve_operand(qubit_part.trim(), &program.qregs)?;
+            let cbits = resolve_operand(cbit_part.trim(), &program.cregs)?;
+            if qubits.len() != cbits.len() {
+                return Err(format!("Measurement '{}' has mismatched register sizes", statement));
+            }
+            for (qubit, cbit) in qubits.into_iter().zip(cbits) {
+                program.ops.push(QasmOp {
+                    name: "measure".to_string(),
+                    params: Vec::new(),
+                    qubits: vec![qubit],
+                    cbits: vec![cbit],
+                    condition: None,
+                });
+            }
+        }
+        "reset" => {
+            for qubit in resolve_operand(rest, &program.qregs)? {
+                program.ops.push(QasmOp {
+                    name: "reset".to_string(),
+                    params: Vec::new(),
+                    qubits: vec![qubit],
+                    cbits: Vec::new(),
+                    condition: None,
+                });
+            }
+        }
+        "barrier" => {
+            let mut qubits = Vec::new();
+            for operand in rest.split(',') {
+                qubits.extend(resolve_operand(operand.trim(), &program.qregs)?);
+            }
+            program.ops.push(QasmOp {
+                name: "barrier".to_string(),
+                params: Vec::new(),
+                qubits,
+                cbits: Vec::new(),
+                condition: None,
+            });
+        }
+        _ if statement.starts_with("if") => {
+            let open = statement.find('(').ok_or("Expected '(' after 'if'")?;
+            let close = statement.find(')').ok_or("Expected ')' closing the condition")?;
+            let (cbit_part, value) = statement[open + 1..close].split_once("==")
+                .ok_or("Expected '==' in condition")?;
+            let cbit = match resolve_operand(cbit_part.trim(), &program.cregs)?.as_slice() {
+                &[cbit] => cbit,
+                _ => return Err("Condition must test a single cbit".to_string()),
+            };
+            let value = value.trim().parse::<i64>()
+                .map_err(|_| format!("Invalid condition value '{}'", value.trim()))?;
+
+            let mut op = parse_gate(statement[close + 1..].trim(), &program.qregs)?;
+            op.condition = Some((cbit, value));
+            program.ops.push(op);
+        }
+        _ => {
+            let op = parse_gate(statement, &program.qregs)?;
+            program.ops.push(op);
+        }
+    }
+
+    Ok(())
+}
+
+/// `name[size]` from a `qreg`/`creg` declaration.
+fn parse_declaration(text: &str) -> Result<(String, usize), String> {
+    let (name, size) = text.strip_suffix(']').and_then(|t| t.split_once('['))
+        .ok_or_else(|| format!("Expected 'name[size]' in declaration '{}'", text))?;
+    let size = size.trim().parse::<usize>()
+        .map_err(|_| format!("Invalid register size '{}'", size))?;
+    Ok((name.trim().to_string(), size))
+}
+
+/// `h q[0]`, `rz(pi/2) q[1]` or `cx q[0], q[1]`.
+fn parse_gate(text: &str, qregs: &[(String, usize)]) -> Result<QasmOp, String> {
+    let name_end = text.find(|c: char| c == '(' || c.is_whitespace()).unwrap_or(text.len());
+    let name = &text[..name_end];
+    let mut rest = &text[name_end..];
+
+    let mut params = Vec::new();
+    if rest.starts_with('(') {
+        let close = rest.find(')').ok_or_else(|| format!("Expected ')' after parameters of '{}'", name))?;
+        params = rest[1..close].split(',').map(|p| p.trim().to_string()).collect();
+        rest = &rest[close + 1..];
+    }
+
+    let &(_, qubit_count, param_count) = QASM_GATES.iter()
+        .find(|(gate, _, _)| *gate == name)
+        .ok_or_else(|| format!("Unsupported instruction '{}'", name))?;
+    if params.len() != param_count {
+        return Err(format!("Gate '{}' expects {} parameter(s), got {}", name, param_count, params.len()));
+    }
+
+    let mut qubits = Vec::new();
+    for operand in rest.split(',') {
+        match resolve_operand(operand.trim(), qregs)?.as_slice() {
+            &[qubit] => qubits.push(qubit),
+            _ => return Err(format!("Gate '{}' must be applied to single qubits", name)),
+        }
+    }
+    if qubits.len() != qubit_count {
+        return Err(format!("Gate '{}' expects {} qubit(s), got {}", name, qubit_count, qubits.len()));
+    }
+
+    Ok(QasmOp { name: name.to_string(), params, qubits, cbits: Vec::new(), condition: None })
+}
+
+/// Flat indices for `reg[i]`, or for every bit of `reg`.
+fn resolve_operand(operand: &str, registers: &[(String, usize)]) -> Result<Vec<usize>, String> {
+    let (name, index) = match operand.strip_suffix(']').and_then(|o| o.split_once('[')) {
+        Some((name, index)) => {
+            let index = index.trim().parse::<usize>()
+                .map_err(|_| format!("Invalid index in '{}'", operand))?;
+            (name.trim(), Some(index))
+        }
+        None => (operand, None),
+    };
+
+    let mut offset = 0;
+    for (register, size) in registers {
+        if register == name {
+            return match index {
+                Some(index) if index < *size => Ok(vec![offset + index]),
+                Some(index) => Err(format!("Index {} out of range for register '{}' of size {}", index, name, size)),
+                None => Ok((offset..offset + size).collect()),
+            };
+        }
+        offset += size;
+    }
+
+    Err(format!("Undeclared register '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compiler;
+
+    fn round_trip(source: &str) -> QasmProgram {
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        parse_qasm(&qasm).unwrap_or_else(|e| panic!("{}\n{}", e, qasm))
+    }
+
+    #[test]
+    fn bell_state_round_trips() {
+        let program = round_trip(include_str!("../../../tests/bell_state.qc"));
+        assert_eq!(program.qubit_count(), 2);
+        let names: Vec<&str> = program.ops.iter().map(|op| op.name.as_str()).collect();
+        assert_eq!(names, ["h", "cx", "measure", "measure"]);
+        assert_eq!(program.ops[1].qubits, [0, 1]);
+        assert_eq!(program.ops[2].qubits, [0]);
+        assert_eq!(program.ops[3].qubits, [1]);
+        assert_eq!(program.gate_count(), 2);
+    }
+
+    #[test]
+    fn conditions_read_back_their_cbit() {
+        let program = round_trip("fn main() -> int {
+            qreg q[2] = |00>;
+            H(q[0]);
+            let m: cbit = measure(q[0]);
+            if (m == 1) { X(q[1]); }
+            return 0;
+        }");
+        let conditional = program.ops.iter().find(|op| op.condition.is_some()).unwrap();
+        assert_eq!(conditional.name, "x");
+        assert_eq!(conditional.qubits, [1]);
+        let measure = program.ops.iter().find(|op| op.name == "measure").unwrap();
+        assert_eq!(conditional.condition, Some((measure.cbits[0], 1)));
+    }
+
+    #[test]
+    fn unsupported_instructions_are_errors() {
+        let error = parse_qasm("OPENQASM 2.0;\nqreg q[1];\nfoo q[0];\n").unwrap_err();
+        assert!(error.contains("foo"), "{}", error);
+    }
+
+    #[test]
+    fn controlled_swaps_parse() {
+        let program = parse_qasm("OPENQASM 2.0;\nqreg q[3];\ncswap q[0], q[1], q[2];\n").unwrap();
+        assert_eq!(program.ops[0].name, "cswap");
+        assert_eq!(program.ops[0].qubits, [0, 1, 2]);
+    }
+}
@@ -1,20 +1,392 @@
 // error.rs - COMPLETE
-use thiserror::Error;
+use crate::ast::Span;
+use crate::lexer::LexError;
+use crate::parser::ParseError;
+use crate::qir::QirError;
+use crate::semantics::SemanticError;
+use std::fmt;
 
-#[derive(Error, Debug)]
-pub enum CompilerError {
-    #[error("Lexer error at position {pos}: {msg}")]
-    LexerError { pos: usize, msg: String },
+/// Any error the compilation pipeline can stop on, tagged by the stage
+/// that produced it.
+#[derive(Debug, Clone)]
+pub enum CompileError {
+    Lex(LexError),
+    Parse(ParseError),
+    Semantic(SemanticError),
+    Qir(QirError),
+}
+
+impl CompileError {
+    /// Source location of the error; `None` for QIR-level errors without a
+    /// source span and parse errors at end of input.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CompileError::Lex(error) => Some(Span::new(error.line, error.column, 0, 0)),
+            CompileError::Parse(error) if error.line > 0 => Some(Span::new(error.line, error.column, 0, 0)),
+            CompileError::Parse(_) => None,
+            CompileError::Semantic(error) => Some(error.span.clone()),
+            CompileError::Qir(error) => error.span().cloned(),
+        }
+    }
     
-    #[error("Parser error: expected {expected}, found {found}")]
-    ParseError { expected: String, found: String },
+    /// Code identifying the kind of error, documented by [`explain`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompileError::Lex(_) => "E0001",
+            CompileError::Parse(_) => "E0002",
+            CompileError::Semantic(_) => "E0003",
+            CompileError::Qir(QirError::SsaViolation { .. })
+            | CompileError::Qir(QirError::MissingTerminator { .. })
+            | CompileError::Qir(QirError::UndefinedTemp { .. }) => "E0004",
+            CompileError::Qir(QirError::UnreachableBlock { .. }) => "E0005",
+            CompileError::Qir(QirError::Other(_)) => "E0006",
+        }
+    }
     
-    #[error("Type error: {0}")]
-    TypeError(String),
+    /// Renders the error with its source snippet, see [`Diagnostic::render`].
+    pub fn render(&self, source: &str) -> String {
+        Diagnostic::from(self).render(source)
+    }
+}
+
+impl From<LexError> for CompileError {
+    fn from(error: LexError) -> Self {
+        CompileError::Lex(error)
+    }
+}
+
+impl From<ParseError> for CompileError {
+    fn from(error: ParseError) -> Self {
+        CompileError::Parse(error)
+    }
+}
+
+impl From<SemanticError> for CompileError {
+    fn from(error: SemanticError) -> Self {
+        CompileError::Semantic(error)
+    }
+}
+
+impl From<QirError> for CompileError {
+    fn from(error: QirError) -> Self {
+        CompileError::Qir(error)
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Diagnostic::from(self).fmt(f)
+    }
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A compiler message with enough structure for tools to render it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// Source location; `None` for messages from stages after the AST
+    pub span: Option<Span>,
+    pub severity: Severity,
+    pub hint: Option<String>,
+    pub code: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: &str) -> Self {
+        Self::new(message, Severity::Error)
+    }
+    
+    pub fn warning(message: &str) -> Self {
+        Self::new(message, Severity::Warning)
+    }
     
-    #[error("Quantum resource error: {0}")]
-    QuantumError(String),
+    fn new(message: &str, severity: Severity) -> Self {
+        Self {
+            message: message.to_string(),
+            span: None,
+            severity,
+            hint: None,
+            code: None,
+        }
+    }
     
-    #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error),
-}
\ No newline at end of file
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+    
+    pub fn with_hint(mut self, hint: Option<String>) -> Self {
+        self.hint = hint;
+        self
+    }
+    
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        let diagnostic = Diagnostic::error(&error.message).with_hint(error.hint.clone());
+        if error.line > 0 {
+            diagnostic.with_span(Span::new(error.line, error.column, 0, 0))
+        } else {
+            diagnostic
+        }
+    }
+}
+
+impl From<&SemanticError> for Diagnostic {
+    fn from(error: &SemanticError) -> Self {
+        Diagnostic::error(&error.message)
+            .with_span(error.span.clone())
+            .with_hint(error.hint.clone())
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(error: &CompileError) -> Self {
+        let diagnostic = match error {
+            CompileError::Lex(error) => Diagnostic::error(&error.message)
+                .with_span(Span::new(error.line, error.column, 0, 0)),
+            CompileError::Parse(error) => Diagnostic::from(error),
+            CompileError::Semantic(error) => Diagnostic::from(error),
+            CompileError::Qir(error) => match error.span() {
+                Some(span) => Diagnostic::error(&error.to_string()).with_span(span.clone()),
+                None => Diagnostic::error(&error.to_string()),
+            },
+        };
+        diagnostic.with_code(error.code())
+    }
+}
+
+impl Diagnostic {
+    /// Renders the message with the offending source line and a caret, or
+    /// the plain message when there is no usable location.
+    pub fn render(&self, source: &str) -> String {
+        match &self.span {
+            Some(span) => render_snippet(source, span, &self.labelled_message(), self.hint.as_deref()),
+            None => self.to_string(),
+        }
+    }
+    
+    /// The message prefixed with its code, e.g. `[E0003] Undefined variable 'x'`.
+    fn labelled_message(&self) -> String {
+        match &self.code {
+            Some(code) => format!("[{}] {}", code, self.message),
+            None => self.message.clone(),
+        }
+    }
+}
+
+/// Formats an error rustc-style: the message, then the source line at
+/// `span` with a `^` under its column, then the hint.
+///
+/// ```text
+/// 4:7: Qubit 'q' used after measurement
+///   |
+/// 4 |     H(q);
+///   |       ^
+///   = hint: reset() makes a qubit usable again
+/// ```
+pub fn render_snippet(source: &str, span: &Span, message: &str, hint: Option<&str>) -> String {
+    let mut output = format!("{}:{}: {}", span.line, span.column, message);
+    
+    if let Some(line) = span.line.checked_sub(1).and_then(|index| source.lines().nth(index)) {
+        let gutter = " ".repeat(span.line.to_string().len());
+        // Keep tabs so the caret lines up with the source line
+        let indent: String = line.chars()
+            .take(span.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        
+        output.push_str(&format!("\n{} |", gutter));
+        output.push_str(&format!("\n{} | {}", span.line, line));
+        output.push_str(&format!("\n{} | {}^", gutter, indent));
+        if let Some(hint) = hint {
+            output.push_str(&format!("\n{} = hint: {}", gutter, hint));
+        }
+    } else if let Some(hint) = hint {
+        output.push_str(&format!("\n  hint: {}", hint));
+    }
+    
+    output
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "{}:{}: {}", span.line, span.column, self.labelled_message())?,
+            None => write!(f, "{}", self.labelled_message())?,
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Long-form documentation for a diagnostic code, shown by `qclang explain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    /// One-line title.
+    pub summary: &'static str,
+    /// What triggers the error and how to fix it, with an example.
+    pub explanation: &'static str,
+}
+
+/// Every code [`CompileError::code`] can return, in numeric order.
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        summary: "Invalid token",
+        explanation: "\
+The lexer found characters that don't form any QCLang token, such as a
+stray `$` or an unterminated string literal.
+
+Example:
+
+    let x: int = 3$;      // '$' is not a token
+
+Fix: remove the character, or close the string on the same line:
+
+    let x: int = 3;",
+    },
+    ErrorCode {
+        code: "E0002",
+        summary: "Syntax error",
+        explanation: "\
+The tokens are valid but don't form a program, e.g. a missing semicolon,
+an unbalanced brace, or a `let` without a type annotation.
+
+Example:
+
+    let q = |0>;          // every let needs a type
+
+Fix: follow the grammar in SYNTAX.md; the hint under the error usually
+names the expected token:
+
+    let q: qubit = |0>;",
+    },
+    ErrorCode {
+        code: "E0003",
+        summary: "Type or quantum safety error",
+        explanation: "\
+The program parses but breaks a typing, scoping or quantum safety rule:
+an undefined name, mismatched types, calling a function before its
+definition, or using a qubit after it was measured or moved.
+
+Example:
+
+    let q: qubit = |0>;
+    let c: cbit = measure(q);
+    H(q);                 // q was consumed by measure
+
+Fix: qubits are affine, so each one can be measured once. Reset it to make
+it usable again, or allocate a fresh qubit:
+
+    reset(q);
+    H(q);",
+    },
+    ErrorCode {
+        code: "E0004",
+        summary: "Malformed QIR",
+        explanation: "\
+The intermediate representation built from a valid program failed its
+consistency checks: a temp defined twice, a temp used before it is
+defined, or a block without a terminator.
+
+This is a compiler bug rather than a problem with the program. Please
+report it with the source file; compiling without -O may work around it.",
+    },
+    ErrorCode {
+        code: "E0005",
+        summary: "Unreachable code",
+        explanation: "\
+No control-flow path from the start of the function reaches this code,
+typically statements after a `return`. The compiler only warns about it;
+`QirModule::validate` reports it as this error.
+
+Example:
+
+    fn main() -> int {
+        return 0;
+        let q: qubit = |0>;   // never runs
+    }
+
+Fix: delete the unreachable statements or move them before the return.",
+    },
+    ErrorCode {
+        code: "E0006",
+        summary: "Circuit rejected",
+        explanation: "\
+The program is valid but its circuit could not be produced as requested:
+the --entry function doesn't exist, the circuit exceeds --max-depth,
+--max-gates or --max-qubits, it uses a gate outside --basis-gates or the
+--target, it breaks the --coupling-map, or --emit-inverse was asked to
+invert a measurement.
+
+Example:
+
+    qclang compile big.qc --max-gates 100
+    Circuit has 250 gates, exceeding the maximum of 100 (--max-gates)
+
+Fix: raise the limit, enable -O to shrink the circuit, or restructure the
+program. The message names the option that rejected it.",
+    },
+];
+
+/// Looks up a code such as `E0003` (case-insensitive).
+pub fn explain(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES.iter().find(|entry| entry.code.eq_ignore_ascii_case(code.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compiler;
+
+    #[test]
+    fn missing_semicolon_points_at_the_next_token() {
+        let source = "fn main() -> int {\n    let x: int = 1\n    return x;\n}\n";
+        let (result, _, _) = Compiler::compile_with_diagnostics(source);
+        let rendered = result.err().unwrap()[0].render(source);
+        assert_eq!(rendered, "3:5: [E0002] Expected ';' semicolon after let statement, found 'return'
+  |
+3 |     return x;
+  |     ^
+  = hint: Add ';' here");
+    }
+
+    #[test]
+    fn stage_errors_convert_with_their_location() {
+        let parse: CompileError = ParseError { message: "Expected ';'".to_string(), line: 2, column: 7, hint: None }.into();
+        assert_eq!(parse.code(), "E0002");
+        assert_eq!(parse.to_string(), "2:7: [E0002] Expected ';'");
+
+        let semantic: CompileError = SemanticError::new(&Span::new(3, 5, 0, 0), "Undefined variable 'x'", Some("Declare it first")).into();
+        assert_eq!(semantic.code(), "E0003");
+        assert_eq!(semantic.span().map(|span| (span.line, span.column)), Some((3, 5)));
+        assert_eq!(semantic.to_string(), "3:5: [E0003] Undefined variable 'x'\n  hint: Declare it first");
+    }
+
+    #[test]
+    fn known_codes_have_explanations() {
+        let entry = explain("e0003").unwrap();
+        assert_eq!(entry.code, "E0003");
+        assert!(!entry.summary.is_empty() && !entry.explanation.is_empty());
+        assert!(explain("E9999").is_none());
+
+        for (i, entry) in ERROR_CODES.iter().enumerate() {
+            assert_eq!(entry.code, format!("E{:04}", i + 1));
+        }
+    }
+}
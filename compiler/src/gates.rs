@@ -0,0 +1,217 @@
+// src/gates.rs - Table of gates supported by the language
+
+/// A gate accepted in source, with its operand shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GateInfo {
+    /// Canonical spelling; source names are matched case-insensitively.
+    pub name: &'static str,
+    /// Number of qubit operands.
+    pub qubits: usize,
+    /// Number of angle parameters.
+    pub angles: usize,
+}
+
+const SUPPORTED_GATES: &[GateInfo] = &[
+    GateInfo { name: "H", qubits: 1, angles: 0 },
+    GateInfo { name: "X", qubits: 1, angles: 0 },
+    GateInfo { name: "Y", qubits: 1, angles: 0 },
+    GateInfo { name: "Z", qubits: 1, angles: 0 },
+    GateInfo { name: "T", qubits: 1, angles: 0 },
+    GateInfo { name: "S", qubits: 1, angles: 0 },
+    GateInfo { name: "RX", qubits: 1, angles: 1 },
+    GateInfo { name: "RY", qubits: 1, angles: 1 },
+    GateInfo { name: "RZ", qubits: 1, angles: 1 },
+    GateInfo { name: "CNOT", qubits: 2, angles: 0 },
+    GateInfo { name: "SWAP", qubits: 2, angles: 0 },
+    GateInfo { name: "CH", qubits: 2, angles: 0 },
+    GateInfo { name: "CY", qubits: 2, angles: 0 },
+    GateInfo { name: "CRX", qubits: 2, angles: 1 },
+    GateInfo { name: "CRY", qubits: 2, angles: 1 },
+    GateInfo { name: "CRZ", qubits: 2, angles: 1 },
+];
+
+/// Every gate the compiler accepts, in display order.
+pub fn all_supported() -> &'static [GateInfo] {
+    SUPPORTED_GATES
+}
+
+/// Looks up a gate by name, ignoring case.
+pub fn lookup(name: &str) -> Option<&'static GateInfo> {
+    SUPPORTED_GATES.iter().find(|g| g.name.eq_ignore_ascii_case(name))
+}
+
+/// Comma-separated list of gate names, used in hints and capability output.
+pub fn name_list() -> String {
+    SUPPORTED_GATES.iter().map(|g| g.name).collect::<Vec<_>>().join(", ")
+}
+
+/// A gate defined outside the compiler, e.g. loaded with `--gates-file`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomGate {
+    pub name: String,
+    /// Number of qubit operands.
+    pub arity: usize,
+    /// OpenQASM emitted for one application, with `{0}`, `{1}`, ... standing
+    /// for the qubit operands in order.
+    pub qasm: String,
+}
+
+impl CustomGate {
+    /// The template with its placeholders replaced by `operands`, in one
+    /// pass so substituted operands are never expanded again.
+    pub fn expand(&self, operands: &[String]) -> String {
+        let mut pieces = self.qasm.split('{');
+        let mut text = pieces.next().unwrap_or_default().to_string();
+        for piece in pieces {
+            let substituted = piece.split_once('}')
+                .and_then(|(index, rest)| Some((operands.get(index.parse::<usize>().ok()?)?, rest)));
+            match substituted {
+                Some((operand, rest)) => {
+                    text.push_str(operand);
+                    text.push_str(rest);
+                }
+                None => {
+                    text.push('{');
+                    text.push_str(piece);
+                }
+            }
+        }
+        text
+    }
+    
+    /// Number of statements in the template, which is how many gates one
+    /// application counts as.
+    pub fn statement_count(&self) -> usize {
+        self.qasm.split(';').filter(|s| !s.trim().is_empty()).count()
+    }
+}
+
+/// Gates the parser and backends accept on top of the built-in table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GateRegistry {
+    custom: Vec<CustomGate>,
+}
+
+impl GateRegistry {
+    /// Reads a JSON object mapping gate names to `{"arity": n, "qasm": "..."}`.
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(text)
+            .map_err(|e| format!("invalid JSON: {}", e))?;
+        let entries = value.as_object()
+            .ok_or("expected an object mapping gate names to definitions")?;
+        
+        let mut registry = Self::default();
+        for (name, definition) in entries {
+            let arity = definition.get("arity").and_then(|v| v.as_u64())
+                .ok_or_else(|| format!("gate '{}' needs a positive integer \"arity\"", name))?;
+            let qasm = definition.get("qasm").and_then(|v| v.as_str())
+                .ok_or_else(|| format!("gate '{}' needs a \"qasm\" template string", name))?;
+            registry.register(CustomGate { name: name.clone(), arity: arity as usize, qasm: qasm.to_string() })?;
+        }
+        Ok(registry)
+    }
+    
+    /// Adds `gate`, rejecting names the language already uses and templates
+    /// that refer to operands the gate doesn't have.
+    pub fn register(&mut self, gate: CustomGate) -> Result<(), String> {
+        let valid_name = gate.name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && gate.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(format!("'{}' is not a valid gate name", gate.name));
+        }
+        if lookup(&gate.name).is_some() || ["measure", "reset", "barrier"].contains(&gate.name.to_lowercase().as_str()) {
+            return Err(format!("gate '{}' is already built in", gate.name));
+        }
+        if self.is_gate(&gate.name) {
+            return Err(format!("gate '{}' is defined twice", gate.name));
+        }
+        if gate.arity == 0 {
+            return Err(format!("gate '{}' needs a positive integer \"arity\"", gate.name));
+        }
+        if let Some(i) = placeholders(&gate.qasm).find(|&i| i >= gate.arity) {
+            return Err(format!("gate '{}' template uses {{{}}} but the gate has {} qubit(s)", gate.name, i, gate.arity));
+        }
+        self.custom.push(gate);
+        Ok(())
+    }
+    
+    /// Whether `name` is a built-in or registered gate, ignoring case.
+    pub fn is_gate(&self, name: &str) -> bool {
+        lookup(name).is_some() || self.custom(name).is_some()
+    }
+    
+    /// Looks up a registered gate by name, ignoring case.
+    pub fn custom(&self, name: &str) -> Option<&CustomGate> {
+        self.custom.iter().find(|g| g.name.eq_ignore_ascii_case(name))
+    }
+    
+    /// Comma-separated list of built-in and registered gate names.
+    pub fn name_list(&self) -> String {
+        let mut names = name_list();
+        for gate in &self.custom {
+            names.push_str(", ");
+            names.push_str(&gate.name);
+        }
+        names
+    }
+}
+
+/// Operand indices named by the `{N}` placeholders of a template.
+fn placeholders(template: &str) -> impl Iterator<Item = usize> + '_ {
+    template.split('{').skip(1).filter_map(|rest| rest.split_once('}')?.0.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Gate;
+    use crate::{CompileOptions, Compiler};
+
+    #[test]
+    fn supported_gates_are_gate_names() {
+        let registry = GateRegistry::default();
+        for gate in all_supported() {
+            assert!(registry.is_gate(gate.name), "{}", gate.name);
+            assert!(registry.is_gate(&gate.name.to_lowercase()), "{}", gate.name);
+            assert_eq!(lookup(gate.name), Some(gate));
+        }
+        assert!(!registry.is_gate("CZ"));
+        let registry = GateRegistry::from_json(r#"{ "CZ": {"arity": 2, "qasm": "cz {0}, {1};"} }"#).unwrap();
+        assert!(registry.is_gate("cz"));
+    }
+
+    #[test]
+    fn gates_file_definitions_compile() {
+        let gates = GateRegistry::from_json(r#"{ "myh": {"arity": 1, "qasm": "h {0};"} }"#).unwrap();
+        assert!(gates.is_gate("myh"));
+        let source = "fn main() -> int { let q: qubit = |0>; myh(q); let c: cbit = measure(q); return 0; }";
+        let result = Compiler::compile_with_options(source, &CompileOptions { gates, ..CompileOptions::default() }).unwrap();
+        assert!(result.qasm.contains("h q[0];\n  measure q[0] -> c[0];"), "{}", result.qasm);
+
+        assert!(Compiler::compile_with_stats(source, false).is_err());
+        assert!(GateRegistry::from_json(r#"{ "H": {"arity": 1, "qasm": "h {0};"} }"#).unwrap_err().contains("already built in"));
+        assert!(GateRegistry::from_json(r#"{ "two": {"arity": 1, "qasm": "cx {0}, {1};"} }"#).unwrap_err().contains("uses {1}"));
+    }
+
+    #[test]
+    fn gate_names_round_trip_through_the_ast() {
+        for info in all_supported() {
+            for name in [info.name.to_string(), info.name.to_lowercase()] {
+                assert!(lookup(&name).is_some(), "{}", name);
+                let mut gate = Gate::from_name(&name).unwrap_or_else(|| panic!("no gate for {}", name));
+                assert_eq!(gate.canonical_name(), info.name);
+                assert_eq!(gate.arity(), info.qubits, "{}", name);
+                assert_eq!(gate.angle_mut().is_some(), info.angles == 1, "{}", name);
+            }
+        }
+        assert_eq!(Gate::from_name("measure"), None);
+        assert!(lookup("measure").is_none());
+    }
+
+    #[test]
+    fn expand_substitutes_each_placeholder_once() {
+        let gate = CustomGate { name: "zz".to_string(), arity: 2, qasm: "cx {0}, {1}; rz(pi) {1}; {x}".to_string() };
+        let operands = ["q[{1}]".to_string(), "q[1]".to_string()];
+        assert_eq!(gate.expand(&operands), "cx q[{1}], q[1]; rz(pi) q[1]; {x}");
+    }
+}
@@ -0,0 +1,136 @@
+// src/include.rs - Splices `include "file.qc";` directives into the token stream
+
+use crate::error::CompileError;
+use crate::lexer::{tokenize_with_errors, Token};
+use crate::parser::ParseError;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+type Spanned = (Token, usize, usize);
+
+/// Replaces every top-level `include "path";` in the tokens of `root` (or of a
+/// source not read from disk, when `None`) with the tokens of the named file.
+/// Paths are resolved relative to the directory of the file containing the
+/// directive; each file is spliced in at most once.
+pub fn expand_includes(tokens: Vec<Spanned>, root: Option<&Path>) -> Result<Vec<Spanned>, Vec<CompileError>> {
+    let dir = root.and_then(Path::parent)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    IncludeExpander::new(root).expand_in(tokens, &dir)
+}
+
+struct IncludeExpander {
+    /// Files currently being expanded, outermost first, for cycle detection.
+    stack: Vec<PathBuf>,
+    included: HashSet<PathBuf>,
+    /// Length of `stack` while expanding the root source itself.
+    root_depth: usize,
+}
+
+impl IncludeExpander {
+    fn new(root: Option<&Path>) -> Self {
+        let stack: Vec<PathBuf> = root.and_then(|path| fs::canonicalize(path).ok()).into_iter().collect();
+        let included = stack.iter().cloned().collect();
+        let root_depth = stack.len();
+        Self { stack, included, root_depth }
+    }
+
+    fn expand_in(&mut self, tokens: Vec<Spanned>, dir: &Path) -> Result<Vec<Spanned>, Vec<CompileError>> {
+        let mut output = Vec::with_capacity(tokens.len());
+        let mut depth = 0usize;
+        let mut i = 0;
+        
+        while i < tokens.len() {
+            match &tokens[i].0 {
+                Token::BraceOpen => depth += 1,
+                Token::BraceClose => depth = depth.saturating_sub(1),
+                Token::Ident(name) if depth == 0 && name == "include" => {
+                    if let (Some((Token::StringLiteral(file), _, _)), Some((Token::Semicolon, _, _))) = (tokens.get(i + 1), tokens.get(i + 2)) {
+                        let (_, line, column) = tokens[i];
+                        output.extend(self.splice(file, dir, line, column)?);
+                        i += 3;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            output.push(tokens[i].clone());
+            i += 1;
+        }
+        
+        Ok(output)
+    }
+
+    fn splice(&mut self, file: &str, dir: &Path, line: usize, column: usize) -> Result<Vec<Spanned>, Vec<CompileError>> {
+        // Positions in an included file would be shown against the root source,
+        // so name the file the directive came from.
+        let origin = match self.stack.last() {
+            Some(including) if self.stack.len() > self.root_depth => format!(" (in {})", including.display()),
+            _ => String::new(),
+        };
+        let error = |message: String, hint: Option<&str>| vec![CompileError::from(ParseError {
+            message: format!("{}{}", message, origin),
+            line,
+            column,
+            hint: hint.map(str::to_string),
+        })];
+        
+        let path = dir.join(file);
+        let canonical = fs::canonicalize(&path)
+            .map_err(|e| error(format!("Cannot read included file '{}': {}", path.display(), e), Some("Include paths are relative to the including file")))?;
+        
+        if self.stack.contains(&canonical) {
+            let chain: Vec<String> = self.stack.iter()
+                .skip_while(|p| **p != canonical)
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.file_name().map_or_else(|| p.display().to_string(), |n| n.to_string_lossy().into_owned()))
+                .collect();
+            return Err(error(format!("Include cycle: {}", chain.join(" -> ")), None));
+        }
+        if !self.included.insert(canonical.clone()) {
+            return Ok(Vec::new());
+        }
+        
+        let source = fs::read_to_string(&canonical)
+            .map_err(|e| error(format!("Cannot read included file '{}': {}", path.display(), e), None))?;
+        let (tokens, lex_errors) = tokenize_with_errors(&source);
+        if !lex_errors.is_empty() {
+            return Err(lex_errors.into_iter().map(|mut e| {
+                e.message = format!("{} (in {})", e.message, path.display());
+                CompileError::from(e)
+            }).collect());
+        }
+        
+        let included_dir = canonical.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.stack.push(canonical);
+        let expanded = self.expand_in(tokens, &included_dir);
+        self.stack.pop();
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, Compiler};
+    use std::fs;
+
+    #[test]
+    fn included_functions_can_be_called() {
+        let dir = std::env::temp_dir().join(format!("qclang-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(dir.join("lib/gates.qc"), "fn flip(q: qubit) -> qubit { X(q); return q; }\n").unwrap();
+        fs::write(dir.join("main.qc"), "include \"lib/gates.qc\";\n\
+            fn main() -> int { let q: qubit = |0>; let f: qubit = flip(q); let c: cbit = measure(f); return 0; }\n").unwrap();
+        fs::write(dir.join("a.qc"), "include \"b.qc\";\nfn main() -> int { return 0; }\n").unwrap();
+        fs::write(dir.join("b.qc"), "include \"a.qc\";\n").unwrap();
+
+        let output = Compiler::compile_file(&dir.join("main.qc"), &CompileOptions::default()).unwrap();
+        assert!(output.result.qasm.contains("x q[0];"), "{}", output.result.qasm);
+
+        let errors = Compiler::compile_file(&dir.join("a.qc"), &CompileOptions::default()).err().unwrap();
+        assert!(errors[0].message.starts_with("Include cycle:"), "{:?}", errors);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -556,13 +556,15 @@ impl IRGenerator {
             .and_then(|s| self.evaluate_int_expr(s))
             .unwrap_or(1);
         
-        if start >= end {
+        if step == 0 {
             return vec![];
         }
         
         let mut result = Vec::new();
         
-        for i in (start..end).step_by(step as usize) {
+        // Stepping away from `end` yields no iterations
+        let mut i = start;
+        while (step > 0 && i < end) || (step < 0 && i > end) {
             self.loop_variables.insert(var_name.to_string(), i);
             
             let body_result = self.generate_stmt(body_stmt);
@@ -572,6 +574,11 @@ impl IRGenerator {
             }
             
             self.loop_variables.remove(var_name);
+            
+            match i.checked_add(step) {
+                Some(next) => i = next,
+                None => break,
+            }
         }
         
         result
@@ -653,6 +660,7 @@ impl IRGenerator {
                     BinaryOp::Ge => Some(format!("({} >= {})", left_val, right_val)),
                     BinaryOp::And => Some(format!("({} && {})", left_val, right_val)),
                     BinaryOp::Or => Some(format!("({} || {})", left_val, right_val)),
+                    BinaryOp::Xor => Some(format!("({} ^ {})", left_val, right_val)),
                     BinaryOp::AddAssign => Some(format!("({} += {})", left_val, right_val)),
                     BinaryOp::SubAssign => Some(format!("({} -= {})", left_val, right_val)),
                     BinaryOp::MulAssign => Some(format!("({} *= {})", left_val, right_val)),
@@ -683,16 +691,11 @@ impl IRGenerator {
                 let right_val = self.evaluate_int_expr(&**right)?;
                 
                 match op {
-                    BinaryOp::Add => Some(left_val + right_val),
-                    BinaryOp::Sub => Some(left_val - right_val),
-                    BinaryOp::Mul => Some(left_val * right_val),
-                    BinaryOp::Div => {
-                        if right_val != 0 {
-                            Some(left_val / right_val)
-                        } else {
-                            None
-                        }
-                    }
+                    BinaryOp::Add => left_val.checked_add(right_val),
+                    BinaryOp::Sub => left_val.checked_sub(right_val),
+                    BinaryOp::Mul => left_val.checked_mul(right_val),
+                    BinaryOp::Div => left_val.checked_div(right_val),
+                    BinaryOp::Xor => Some(left_val ^ right_val),
                     _ => None,
                 }
             }
@@ -731,6 +734,8 @@ impl IRGenerator {
             Gate::T => Some(QIRGate::T),
             Gate::S => Some(QIRGate::S),
             Gate::SWAP => Some(QIRGate::SWAP),
+            // Controlled gates beyond CNOT/SWAP are only lowered by the QIR pipeline
+            Gate::CH | Gate::CY | Gate::CRX(_) | Gate::CRY(_) | Gate::CRZ(_) | Gate::Custom(..) => None,
         }
     }
     
@@ -738,6 +743,9 @@ impl IRGenerator {
         match expr {
             Expr::LiteralInt(val, _) => Some(*val as f64),
             Expr::LiteralFloat(val, _) => Some(*val),
+            Expr::Variable(name, _) if name == "pi" && !self.loop_variables.contains_key(name) => {
+                Some(std::f64::consts::PI)
+            }
             Expr::Variable(name, _) => {
                 self.loop_variables.get(name).map(|&v| v as f64)
             }
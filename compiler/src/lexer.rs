@@ -1,6 +1,7 @@
 // lexer.rs - COMPLETE FOR PHASE 1.3
-use logos::Logos;
+use logos::{FilterResult, Lexer, Logos};
 use crate::ast::{BitString, Span};
+use std::fmt;
 
 #[derive(Logos, Debug, PartialEq, Clone)]
 pub enum Token {
@@ -66,12 +67,16 @@ pub enum Token {
     #[token("tuple")]
     KwTuple,
 
-    // Literals
-    #[regex(r"[0-9]+", |lex| lex.slice().parse().ok())]
+    // Literals (`_` may separate digits; malformed forms fail to lex)
+    #[regex(r"[0-9][0-9_]*", |lex| normalize_number(lex.slice())?.parse().ok())]
+    #[regex(r"0[xX][0-9a-zA-Z_]*", |lex| parse_radix_number(lex.slice(), 16))]
+    #[regex(r"0[bB][0-9a-zA-Z_]*", |lex| parse_radix_number(lex.slice(), 2))]
     IntLiteral(i64),
-    #[regex(r"[0-9]+\.[0-9]*", |lex| lex.slice().parse().ok())]
+    #[regex(r"[0-9][0-9_]*(\.[0-9_]*)+([eE][+-]?[0-9_]+)?", |lex| normalize_number(lex.slice())?.parse().ok())]
+    #[regex(r"[0-9][0-9_]*[eE][+-]?[0-9_]+", |lex| normalize_number(lex.slice())?.parse().ok())]
     FloatLiteral(f64),
-    #[regex(r#""[^"]*""#, |lex| lex.slice()[1..lex.slice().len()-1].to_string())]
+    // Strings end on their line, so an unterminated one is reported there
+    #[regex(r#""[^"\n]*""#, |lex| lex.slice()[1..lex.slice().len()-1].to_string())]
     StringLiteral(String),
 
     #[regex(r"\|[01]+>", |lex| {
@@ -156,13 +161,79 @@ pub enum Token {
     #[token(".")]
     Dot,
 
+    // Produced by `tokenize` for input that fails to lex; never matched by logos
+    Error(String),
+
     // Skip token
     #[regex(r"//[^\n]*", logos::skip)]
-    #[regex(r"/\*[^*]*\*+(?:[^/*][^*]*\*+)*/", logos::skip)]
+    #[token("/*", block_comment)]
     #[regex(r"[ \t\n\r\f]+", logos::skip)]
     __Skip,
 }
 
+/// Skips a (possibly nested) `/* ... */` comment. An unterminated comment
+/// consumes the rest of the input and is reported as a lexer error.
+fn block_comment(lex: &mut Lexer<Token>) -> FilterResult<(), ()> {
+    let rest = lex.remainder();
+    
+    match block_comment_len(rest) {
+        Some(len) => {
+            lex.bump(len);
+            FilterResult::Skip
+        }
+        None => {
+            lex.bump(rest.len());
+            FilterResult::Error(())
+        }
+    }
+}
+
+// Length of a block comment after its opening `/*`, up to and including the
+// matching `*/`, or None when the comment is never closed.
+fn block_comment_len(rest: &str) -> Option<usize> {
+    let bytes = rest.as_bytes();
+    let mut depth = 1;
+    let mut i = 0;
+    
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    
+    None
+}
+
+/// A problem found while splitting source text into tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// 1-based line and column of a byte offset in `source`.
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let line = source[..offset].matches('\n').count() + 1;
+    let line_start = source[..offset].rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+    (line, offset - line_start + 1)
+}
+
 pub fn tokenize(source: &str) -> Vec<(Token, usize, usize)> {
     let mut tokens = Vec::new();
     let mut lexer = Token::lexer(source);
@@ -171,29 +242,30 @@ pub fn tokenize(source: &str) -> Vec<(Token, usize, usize)> {
         match result {
             Ok(token) => {
                 if token != Token::__Skip {
-                    let span = lexer.span();
-                    let token_start = span.start;
-                    
-                    let lines_up_to_token: Vec<&str> = source[..token_start].lines().collect();
-                    let current_line = lines_up_to_token.len();
-                    
-                    let current_line_start = source[..token_start].rfind('\n').map(|pos| pos + 1).unwrap_or(0);
-                    let current_column = token_start - current_line_start + 1;
-                    
+                    let (current_line, current_column) = line_and_column(source, lexer.span().start);
                     tokens.push((token, current_line, current_column));
                 }
             }
             Err(_) => {
-                let span = lexer.span();
                 let slice = lexer.slice();
+                let (error_line, error_column) = line_and_column(source, lexer.span().start);
                 
-                let lines_up_to_error: Vec<&str> = source[..span.start].lines().collect();
-                let error_line = lines_up_to_error.len();
-                let line_start = source[..span.start].rfind('\n').map(|pos| pos + 1).unwrap_or(0);
-                let error_column = span.start - line_start + 1;
-                
-                eprintln!("Lexer error at line {} column {}: unexpected character '{}'", 
-                         error_line, error_column, slice);
+                let message = if slice.starts_with("/*") {
+                    "Unterminated block comment".to_string()
+                } else if slice.starts_with('"') {
+                    "Unterminated string literal".to_string()
+                } else if slice.starts_with("|0") || slice.starts_with("|1") {
+                    format!("Unterminated qubit literal '{}'", slice)
+                } else if slice.starts_with("0x") || slice.starts_with("0X") {
+                    format!("Malformed hexadecimal literal '{}'", slice)
+                } else if slice.starts_with("0b") || slice.starts_with("0B") {
+                    format!("Malformed binary literal '{}'", slice)
+                } else if slice.starts_with(|c: char| c.is_ascii_digit()) {
+                    format!("Malformed numeric literal '{}'", slice)
+                } else {
+                    format!("Unexpected character '{}'", slice)
+                };
+                tokens.push((Token::Error(message), error_line, error_column));
             }
         }
     }
@@ -201,9 +273,170 @@ pub fn tokenize(source: &str) -> Vec<(Token, usize, usize)> {
     tokens
 }
 
-pub fn is_gate_name(name: &str) -> bool {
-    matches!(
-        name.to_lowercase().as_str(),
-        "h" | "x" | "y" | "z" | "cnot" | "rx" | "ry" | "rz" | "t" | "s" | "swap"
-    )
-}
\ No newline at end of file
+/// Like [`tokenize`], but returns the lexer errors separately instead of
+/// embedding them in the stream as `Token::Error`.
+pub fn tokenize_with_errors(source: &str) -> (Vec<(Token, usize, usize)>, Vec<LexError>) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    
+    for (token, line, column) in tokenize(source) {
+        match token {
+            Token::Error(message) => errors.push(LexError { message, line, column }),
+            token => tokens.push((token, line, column)),
+        }
+    }
+    
+    (tokens, errors)
+}
+
+/// A `//` or `/* */` comment, which [`tokenize`] skips.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub line: usize,
+    /// Whether code comes before the comment on its line.
+    pub trailing: bool,
+}
+
+/// The comments in `source`, in order. `qclang format` uses them to keep
+/// comments that are not part of the AST.
+pub fn comments(source: &str) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut lexer = Token::lexer(source);
+    let mut gap_start = 0;
+    
+    loop {
+        let next = lexer.next();
+        let gap_end = if next.is_some() { lexer.span().start } else { source.len() };
+        collect_comments(source, gap_start, gap_end, &mut comments);
+        if next.is_none() {
+            break;
+        }
+        gap_start = lexer.span().end;
+    }
+    
+    comments
+}
+
+// Comments in the whitespace between two tokens, `source[start..end]`
+fn collect_comments(source: &str, start: usize, end: usize, comments: &mut Vec<Comment>) {
+    let mut i = start;
+    
+    while i < end {
+        let rest = &source[i..end];
+        let len = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            block_comment_len(body).map(|len| len + 2).unwrap_or(rest.len())
+        } else {
+            i += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+            continue;
+        };
+        
+        let line_start = source[..i].rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+        comments.push(Comment {
+            text: rest[..len].trim_end().to_string(),
+            line: line_and_column(source, i).0,
+            trailing: !source[line_start..i].trim().is_empty(),
+        });
+        i += len;
+    }
+}
+
+/// Strips digit separators from a numeric literal, rejecting doubled,
+/// trailing or misplaced underscores and more than one decimal point.
+fn normalize_number(slice: &str) -> Option<String> {
+    if slice.matches('.').count() > 1 || slice.contains("__") || slice.ends_with('_') {
+        return None;
+    }
+    
+    let chars: Vec<char> = slice.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        if *c != '_' {
+            continue;
+        }
+        let before = chars[i - 1];
+        let after = chars.get(i + 1).copied().unwrap_or(' ');
+        if !before.is_ascii_digit() || !after.is_ascii_digit() {
+            return None;
+        }
+    }
+    
+    Some(slice.replace('_', ""))
+}
+
+/// Value of a `0x`/`0b` literal. Digits may be separated by single
+/// underscores, as in decimal literals; at least one digit is required.
+fn parse_radix_number(slice: &str, radix: u32) -> Option<i64> {
+    let digits = &slice[2..];
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return None;
+    }
+    i64::from_str_radix(&digits.replace('_', ""), radix).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        tokenize(source).into_iter().map(|(token, _, _)| token).collect()
+    }
+
+    #[test]
+    fn numbers_accept_underscores_and_exponents() {
+        assert_eq!(tokens("1_000"), vec![Token::IntLiteral(1000)]);
+        assert_eq!(tokens("1.5e-3"), vec![Token::FloatLiteral(1.5e-3)]);
+        assert_eq!(tokens("6.022e23"), vec![Token::FloatLiteral(6.022e23)]);
+    }
+
+    #[test]
+    fn malformed_numbers_are_parse_errors() {
+        for literal in ["1__0", "1.2.3"] {
+            let source = format!("fn main() -> int {{ let x: int = {}; return 0; }}", literal);
+            let mut parser = Parser::new(tokenize(&source).into_iter(), source.clone());
+            parser.parse_program();
+            assert!(parser.errors.iter().any(|e| e.message.contains("Malformed numeric literal") && e.line == 1 && e.column == 33),
+                "{}: {:?}", literal, parser.errors);
+        }
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let plain = "fn main() -> int { let q: qubit = |0>; H(q); return 0; }";
+        let commented = "// entry point\nfn main() -> int { /* a /* nested */ comment */ let q: qubit = |0>;\n\
+            H(q); // Hadamard\n return 0; }";
+        assert_eq!(tokens(commented), tokens(plain));
+    }
+
+    #[test]
+    fn lines_are_counted_through_block_comments() {
+        let tokens = tokenize("/* one\ntwo\nthree */\n  return");
+        assert_eq!(tokens, vec![(Token::KwReturn, 4, 3)]);
+
+        let (_, errors) = tokenize_with_errors("let /* never closed\nx");
+        assert_eq!(errors, vec![LexError { message: "Unterminated block comment".to_string(), line: 1, column: 5 }]);
+    }
+
+    #[test]
+    fn hex_and_binary_literals() {
+        assert_eq!(tokens("0xff"), vec![Token::IntLiteral(255)]);
+        assert_eq!(tokens("0b101"), vec![Token::IntLiteral(5)]);
+
+        for (source, message) in [("0x", "Malformed hexadecimal literal '0x'"), ("0xG", "Malformed hexadecimal literal")] {
+            let (_, errors) = tokenize_with_errors(&format!("x = {};", source));
+            assert!(errors.iter().any(|e| e.message.starts_with(message) && e.line == 1 && e.column == 5), "{}: {:?}", source, errors);
+        }
+    }
+
+    #[test]
+    fn unterminated_strings_are_lexer_errors() {
+        let (tokens, errors) = tokenize_with_errors("let s = \"abc");
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(errors, vec![LexError { message: "Unterminated string literal".to_string(), line: 1, column: 9 }]);
+
+        let errors = crate::Compiler::parse_only("fn main() -> int { let s = \"abc").err().unwrap();
+        assert_eq!(errors[0].to_string(), "1:28: [E0001] Unterminated string literal");
+    }
+}
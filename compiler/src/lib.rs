@@ -7,17 +7,27 @@ pub mod qir;
 pub mod codegen;
 pub mod semantics;
 pub mod error;
+pub mod gates;
+pub mod include;
+pub mod targets;
 pub mod simulator; // <--- Added: Simulator Module
 
-use lexer::tokenize;
+use lexer::{tokenize_with_errors, Token};
 use parser::Parser;
 use qir::builder::QirBuilder;
-use qir::optimizer::QirOptimizer;
+use qir::optimizer::{OptimizationReport, QirOptimizer, QirSnapshots};
 use qir::analysis::QirAnalyzer;
 use semantics::SemanticAnalyzer;
 use codegen::QASMGenerator;
-use qir::QirModule;
-use std::time::SystemTime;
+use qir::{QirError, QirGate, QirModule, QirOp, invert_function, route_module};
+use ast::Program;
+use targets::Target;
+use gates::GateRegistry;
+use include::expand_includes;
+pub use error::{CompileError, Diagnostic, Severity};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
 pub const VERSION: &str = "0.6.0";
 
@@ -35,7 +45,7 @@ pub fn git_commit_hash() -> String {
 
 // --- Return Structures ---
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, serde::Serialize)]
 pub struct CompileStats {
     pub qubits: usize,
     pub cbits: usize,
@@ -48,21 +58,159 @@ impl CompileStats {
         Self { qubits: 0, cbits: 0, gates: 0, measurements: 0 }
     }
     
+    /// Counts taken straight from the QIR, matching what QASM generation
+    /// would report without generating any text.
+    pub fn from_module(module: &QirModule, gates: &GateRegistry) -> Self {
+        Self {
+            qubits: module.used_qubit_count(),
+            cbits: module.used_cbit_count(),
+            gates: Self::count_gates(module, gates),
+            measurements: module.measurement_count(),
+        }
+    }
+    
+    /// Gates the QASM output will contain: applied and conditional gates
+    /// plus the `x` gates preparing `|1>` qubits. A custom gate counts as
+    /// the number of statements in its template. Both `gates` in the stats
+    /// and the `--max-gates` limit use this count.
+    pub fn count_gates(module: &QirModule, gates: &GateRegistry) -> usize {
+        let custom_extra: usize = module.functions.iter()
+            .flat_map(|f| f.blocks.values())
+            .flat_map(|b| b.ops.iter())
+            .filter_map(|op| match op {
+                QirOp::ApplyGate { gate: QirGate::Custom { name, .. }, .. }
+                | QirOp::ConditionalGate { gate: QirGate::Custom { name, .. }, .. } => gates.custom(name),
+                _ => None,
+            })
+            .map(|custom| custom.statement_count().saturating_sub(1))
+            .sum();
+        let conditional_gates = module.functions.iter()
+            .flat_map(|f| f.blocks.values())
+            .flat_map(|b| b.ops.iter())
+            .filter(|op| matches!(op, QirOp::ConditionalGate { .. }))
+            .count();
+        
+        module.gate_count() + conditional_gates + module.initialization_gate_count() + custom_extra
+    }
+    
     pub fn total_operations(&self) -> usize {
         self.gates + self.measurements
     }
+    
+    /// Adds the counts of `other` to these stats, e.g. to total several files.
+    pub fn merge(&mut self, other: &CompileStats) {
+        self.qubits += other.qubits;
+        self.cbits += other.cbits;
+        self.gates += other.gates;
+        self.measurements += other.measurements;
+    }
 }
 
 impl Default for CompileStats {
     fn default() -> Self { Self::new() }
 }
 
+/// Settings that control a single compilation.
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    pub optimize: bool,
+    /// Reject circuits deeper than this many layers.
+    pub max_depth: Option<usize>,
+    /// Reject circuits with more gates than this.
+    pub max_gates: Option<usize>,
+    /// Reject programs that allocate more qubits than this.
+    pub max_qubits: Option<usize>,
+    /// Reject gates outside this set of OpenQASM gate names.
+    pub basis_gates: Option<Vec<String>>,
+    /// Reject loops that unroll to more iterations than this
+    /// (defaults to `qir::builder::DEFAULT_UNROLL_THRESHOLD`).
+    pub unroll_threshold: Option<usize>,
+    /// Rewrite every SWAP as three CNOTs.
+    pub decompose_swap: bool,
+    /// Fuse runs of single-qubit gates on the same qubit into one `u3`.
+    pub fuse_single_qubit: bool,
+    /// Record the gates each optimizer pass removed and its running time in
+    /// `CompilationResult::optimization`.
+    pub profile: bool,
+    /// Function compiled as the circuit root (defaults to `main`; a program
+    /// without `main` then keeps every function).
+    pub entry: Option<String>,
+    /// Return how long each compilation phase took.
+    pub time_passes: bool,
+    /// Omit the `OPENQASM`/`include` header and register declarations,
+    /// leaving only the gate and measurement instructions.
+    pub no_header: bool,
+    /// Warn wherever an `int` is implicitly converted to `float`.
+    pub warn_coercion: bool,
+    /// Warn wherever a declaration in a nested block shadows an outer variable.
+    pub warn_shadowing: bool,
+    /// Reject programs that allocate a qubit and never measure, reset,
+    /// return or pass it on.
+    pub deny_quantum_leaks: bool,
+    /// Return the QIR text from before and after optimization.
+    pub dump_qir: bool,
+    /// Reset measured qubits and reuse them for later allocations.
+    pub reuse_ancillas: bool,
+    /// Emit QASM with section comments and aligned operands.
+    pub pretty_qasm: bool,
+    /// Compile the inverse (dagger) of the circuit instead of the circuit.
+    pub emit_inverse: bool,
+    /// Device preset supplying defaults for `max_qubits`, `basis_gates` and
+    /// `coupling_map`; explicitly set limits take precedence.
+    pub target: Option<&'static Target>,
+    /// Reject two-qubit gates on physical qubits that aren't joined by one
+    /// of these edges (in either direction).
+    pub coupling_map: Option<Vec<(usize, usize)>>,
+    /// Insert SWAPs so two-qubit gates respect the coupling map.
+    pub route: bool,
+    /// Custom gates accepted in source and expanded from their QASM templates.
+    pub gates: GateRegistry,
+    /// Compute the statistics from the QIR and skip QASM generation, leaving
+    /// `CompilationResult::qasm` empty.
+    pub count_only: bool,
+    /// File the source was read from; `include` paths are resolved relative
+    /// to its directory, or to the current directory when unset.
+    pub source_path: Option<PathBuf>,
+}
+
+/// Wall-clock time spent in each compilation phase, in pipeline order.
+#[derive(Debug, Clone, Default)]
+pub struct PassTimings {
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl PassTimings {
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, elapsed)| *elapsed).sum()
+    }
+    
+    /// Records the time since `clock` under `phase` and restarts the clock.
+    fn record(&mut self, phase: &'static str, clock: &mut Instant) {
+        self.phases.push((phase, clock.elapsed()));
+        *clock = Instant::now();
+    }
+}
+
 /// The result of a successful compilation.
 /// Contains the QASM code, statistics, and the IR for simulation.
 pub struct CompilationResult {
     pub qasm: String,
     pub stats: CompileStats,
     pub ir: QirModule, // <--- Exposed for Simulator
+    /// Per-phase timings, when `CompileOptions::time_passes` is set.
+    pub timings: Option<PassTimings>,
+    /// QIR before and after optimization, when `CompileOptions::dump_qir` is set.
+    pub qir_snapshots: Option<QirSnapshots>,
+    /// What each optimizer pass did, when `CompileOptions::profile` is set.
+    pub optimization: Option<OptimizationReport>,
+}
+
+/// The result of [`Compiler::compile_file`].
+pub struct CompileOutput {
+    pub result: CompilationResult,
+    /// Where the QASM conventionally goes: the input path with a `.qasm` extension.
+    pub output_path: PathBuf,
+    pub warnings: Vec<Diagnostic>,
 }
 
 // --- Compiler Implementation ---
@@ -92,55 +240,110 @@ impl Compiler {
             "Phase 1.4: Semantic Analyzer",
             "• Quantum ownership rules",
             "• Type Registry & Symbol Table",
-            "Standard gates: H, X, Y, Z, CNOT",
             "Quantum control flow: qif, qfor",
         ]
     }
     
     // UPDATED: Returns CompilationResult instead of tuple
     pub fn compile_with_stats(source: &str, optimize: bool) -> Result<CompilationResult, Vec<String>> {
-        // 1. LEXING
-        let tokens = tokenize(source);
-        
-        // 2. PARSING
+        Self::compile_with_options(source, &CompileOptions { optimize, ..CompileOptions::default() })
+    }
+    
+    /// Compiles without printing anything; warnings are dropped, so use
+    /// `run_pipeline` to report them.
+    pub fn compile_with_options(source: &str, options: &CompileOptions) -> Result<CompilationResult, Vec<String>> {
+        let (result, _warnings) = Self::run_pipeline(source, options);
+        result.map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
+    }
+    
+    /// Lexes and parses `source` without analyzing it, returning the AST
+    /// (with spans) or the syntax errors. Prints nothing, for editor tooling.
+    pub fn parse_only(source: &str) -> Result<Program, Vec<Diagnostic>> {
+        let (tokens, lex_errors) = tokenize_with_errors(source);
+        let result = if lex_errors.is_empty() {
+            Self::parse_tokens(tokens, source, &GateRegistry::default())
+        } else {
+            Err(lex_errors.into_iter().map(CompileError::from).collect())
+        };
+        result.map_err(|errors| errors.iter().map(Diagnostic::from).collect())
+    }
+    
+    fn parse_tokens(tokens: Vec<(Token, usize, usize)>, source: &str, gates: &GateRegistry) -> Result<Program, Vec<CompileError>> {
         let mut parser = Parser::new(tokens.into_iter(), source.to_string());
+        parser.set_gate_registry(gates.clone());
         let program = parser.parse_program();
         
-        if !parser.errors.is_empty() {
-            return Err(parser.errors.iter().map(|e| e.to_string()).collect());
+        if parser.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(parser.errors.into_iter().map(CompileError::from).collect())
         }
+    }
+    
+    /// Runs every stage up to code generation and returns the verified,
+    /// optionally optimized `QirModule` for inspection or a custom backend.
+    pub fn compile_to_qir(source: &str, options: &CompileOptions) -> Result<QirModule, Vec<String>> {
+        let (result, _warnings) = Self::build_qir(source, options, &mut PassTimings::default(), &mut None, &mut None);
+        result.map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
+    }
+    
+    /// Reads and compiles a source file. A read failure is reported as a
+    /// single diagnostic; compile errors carry their source locations.
+    pub fn compile_file(path: &Path, options: &CompileOptions) -> Result<CompileOutput, Vec<Diagnostic>> {
+        let source = fs::read_to_string(path).map_err(|error| {
+            vec![Diagnostic::error(&format!("Cannot read {}: {}", path.display(), error))]
+        })?;
         
-        // 3. SEMANTIC ANALYSIS
-        let mut semantic_analyzer = SemanticAnalyzer::new();
-        match semantic_analyzer.analyze_program(&program) {
-            Ok(_) => {
-                for warning in semantic_analyzer.get_warnings() {
-                    eprintln!("Warning: {}", warning);
-                }
-            }
-            Err(errors) => {
-                return Err(errors.iter().map(|e| e.to_string()).collect());
-            }
+        let options = CompileOptions { source_path: Some(path.to_path_buf()), ..options.clone() };
+        let (result, warnings) = Self::run_pipeline(&source, &options);
+        match result {
+            Ok(result) => Ok(CompileOutput {
+                result,
+                output_path: Self::output_path(path, None, "qasm"),
+                warnings,
+            }),
+            Err(errors) => Err(errors.iter().map(Diagnostic::from).collect()),
         }
-        
-        // 4. QIR GENERATION
-        let mut qir_builder = QirBuilder::new();
-        let mut qir_module = qir_builder.build_from_program(&program);
-        
-        // 5. QIR OPTIMIZATION
-        if optimize {
-            let optimizer = QirOptimizer::new(true);
-            optimizer.optimize_module(&mut qir_module);
+    }
+    
+    /// Output file for `input`: the same file name with `extension`, inside
+    /// `output_dir` when one is given and next to the input otherwise.
+    pub fn output_path(input: &Path, output_dir: Option<&Path>, extension: &str) -> PathBuf {
+        match (output_dir, input.file_name()) {
+            (Some(dir), Some(file_name)) => dir.join(file_name).with_extension(extension),
+            _ => input.with_extension(extension),
         }
+    }
+    
+    /// Runs every compilation stage, returning the result together with any warnings.
+    pub fn run_pipeline(source: &str, options: &CompileOptions) -> (Result<CompilationResult, Vec<CompileError>>, Vec<Diagnostic>) {
+        let mut timings = PassTimings::default();
+        let mut qir_snapshots = None;
+        let mut optimization = None;
+        let (qir_module, warnings) = match Self::build_qir(source, options, &mut timings, &mut qir_snapshots, &mut optimization) {
+            (Ok(qir_module), warnings) => (qir_module, warnings),
+            (Err(errors), warnings) => return (Err(errors), warnings),
+        };
+        let mut clock = Instant::now();
         
-        // 6. QIR ANALYSIS (Safety Check)
-        let mut analyzer = QirAnalyzer::new();
-        if !analyzer.analyze_module(&qir_module) {
-            return Err(analyzer.get_errors().iter().map(|s| s.clone()).collect());
+        if options.count_only {
+            let stats = CompileStats::from_module(&qir_module, &options.gates);
+            timings.record("counting", &mut clock);
+            return (Ok(CompilationResult {
+                qasm: String::new(),
+                stats,
+                ir: qir_module,
+                timings: options.time_passes.then_some(timings),
+                qir_snapshots,
+                optimization,
+            }), warnings);
         }
         
         // 7. CODE GENERATION (OpenQASM)
         let mut qasm_generator = QASMGenerator::new();
+        qasm_generator.emit_header = !options.no_header;
+        qasm_generator.pretty = options.pretty_qasm;
+        qasm_generator.gates = options.gates.clone();
         let qasm_code = qasm_generator.generate(&qir_module);
         
         // 8. STATS GATHERING
@@ -150,12 +353,174 @@ impl Compiler {
             gates: qasm_generator.gate_count(),
             measurements: qasm_generator.measurement_count(),
         };
+        timings.record("code generation", &mut clock);
         
-        Ok(CompilationResult {
+        (Ok(CompilationResult {
             qasm: qasm_code,
             stats,
             ir: qir_module, // Pass the IR out for the simulator
-        })
+            timings: options.time_passes.then_some(timings),
+            qir_snapshots,
+            optimization,
+        }), warnings)
+    }
+    
+    /// Stages 1-6 of the pipeline: everything before code generation.
+    /// Fills `snapshots` when `options.dump_qir` is set, and `report` when
+    /// `options.profile` is.
+    fn build_qir(source: &str, options: &CompileOptions, timings: &mut PassTimings, snapshots: &mut Option<QirSnapshots>,
+                 report: &mut Option<OptimizationReport>) -> (Result<QirModule, Vec<CompileError>>, Vec<Diagnostic>) {
+        let mut warnings = Vec::new();
+        let mut clock = Instant::now();
+        
+        // 1. LEXING
+        let (tokens, lex_errors) = tokenize_with_errors(source);
+        if !lex_errors.is_empty() {
+            return (Err(lex_errors.into_iter().map(CompileError::from).collect()), warnings);
+        }
+        let tokens = match expand_includes(tokens, options.source_path.as_deref()) {
+            Ok(tokens) => tokens,
+            Err(errors) => return (Err(errors), warnings),
+        };
+        timings.record("lexing", &mut clock);
+        
+        // 2. PARSING
+        let program = match Self::parse_tokens(tokens, source, &options.gates) {
+            Ok(program) => program,
+            Err(errors) => return (Err(errors), warnings),
+        };
+        timings.record("parsing", &mut clock);
+        
+        // 3. SEMANTIC ANALYSIS
+        let mut semantic_analyzer = SemanticAnalyzer::new();
+        semantic_analyzer.warn_coercion = options.warn_coercion;
+        semantic_analyzer.warn_shadowing = options.warn_shadowing;
+        semantic_analyzer.deny_quantum_leaks = options.deny_quantum_leaks;
+        let analysis = semantic_analyzer.analyze_program(&program);
+        warnings.extend(semantic_analyzer.get_warnings().iter().cloned());
+        if let Err(errors) = analysis {
+            return (Err(errors.into_iter().map(CompileError::from).collect()), warnings);
+        }
+        timings.record("semantic analysis", &mut clock);
+        
+        // 4. QIR GENERATION
+        let mut qir_builder = QirBuilder::new();
+        if let Some(threshold) = options.unroll_threshold {
+            qir_builder.set_unroll_threshold(threshold);
+        }
+        let mut qir_module = qir_builder.build_from_program(&program);
+        if !qir_builder.get_errors().is_empty() {
+            return (Err(qir_builder.get_errors().iter().map(|e| QirError::new(e).into()).collect()), warnings);
+        }
+        
+        // 4b. ENTRY POINT
+        match &options.entry {
+            Some(entry) => {
+                if !qir_module.retain_entry(entry) {
+                    let message = format!("Entry function '{}' not found (--entry)", entry);
+                    return (Err(vec![QirError::new(&message).into()]), warnings);
+                }
+            }
+            None => {
+                qir_module.retain_entry("main");
+            }
+        }
+        
+        // 4c. INVERSE
+        if options.emit_inverse {
+            for func in &mut qir_module.functions {
+                match invert_function(func) {
+                    Ok(inverted) => *func = inverted,
+                    Err(error) => return (Err(vec![error.into()]), warnings),
+                }
+            }
+        }
+        timings.record("QIR building", &mut clock);
+        
+        // 5. QIR OPTIMIZATION
+        let mut optimizer = QirOptimizer::new(options.optimize);
+        optimizer.enable_swap_decomposition = options.decompose_swap;
+        optimizer.enable_ancilla_reuse = options.reuse_ancillas;
+        optimizer.enable_single_qubit_fusion = options.fuse_single_qubit;
+        let mut optimization = if options.dump_qir {
+            // Snapshot even when no pass is enabled, so both dumps are always shown
+            let (dump, optimization) = optimizer.optimize_module_with_snapshots(&mut qir_module);
+            *snapshots = Some(dump);
+            optimization
+        } else if options.optimize || options.decompose_swap || options.reuse_ancillas || options.fuse_single_qubit {
+            optimizer.optimize_module(&mut qir_module)
+        } else {
+            OptimizationReport::default()
+        };
+        timings.record("optimization", &mut clock);
+        
+        // 5b. ROUTING (after optimization, which may renumber qubits)
+        let coupling_map = options.coupling_map.as_deref()
+            .or(options.target.map(|target| target.coupling_map).filter(|map| !map.is_empty()));
+        if options.route {
+            let Some(coupling_map) = coupling_map else {
+                let message = "--route needs a coupling map (--coupling-map or a --target that has one)";
+                return (Err(vec![QirError::new(message).into()]), warnings);
+            };
+            let layout = QASMGenerator::new().physical_qubits(&qir_module);
+            route_module(&mut qir_module, coupling_map, &layout);
+            
+            if options.decompose_swap {
+                let mut decomposer = QirOptimizer::new(false);
+                decomposer.enable_swap_decomposition = true;
+                optimization.merge(decomposer.optimize_module(&mut qir_module));
+            }
+        }
+        if options.profile {
+            *report = Some(optimization);
+        }
+        
+        // 6. QIR ANALYSIS (Safety Check)
+        let mut analyzer = QirAnalyzer::new();
+        if let Some(target) = options.target {
+            analyzer.set_target(target);
+        }
+        if let Some(max_qubits) = options.max_qubits {
+            analyzer.set_max_qubits(max_qubits);
+        }
+        if let Some(basis_gates) = &options.basis_gates {
+            analyzer.set_basis_gates(basis_gates);
+        }
+        if let Some(coupling_map) = &options.coupling_map {
+            analyzer.set_coupling_map(coupling_map);
+        }
+        if coupling_map.is_some() {
+            // Coupling maps number qubits as they are laid out in the QASM registers
+            analyzer.set_qubit_layout(QASMGenerator::new().physical_qubits(&qir_module));
+        }
+        if !analyzer.analyze_module(&qir_module) {
+            return (Err(analyzer.get_errors().iter().cloned().map(CompileError::from).collect()), warnings);
+        }
+        
+        // 6b. HARDWARE LIMITS
+        if let Some(max_depth) = options.max_depth {
+            let depth = qir_module.circuit_depth();
+            if depth > max_depth {
+                let message = format!(
+                    "Circuit depth {} exceeds the maximum of {} (--max-depth)",
+                    depth, max_depth
+                );
+                return (Err(vec![QirError::new(&message).into()]), warnings);
+            }
+        }
+        if let Some(max_gates) = options.max_gates {
+            let gates = CompileStats::count_gates(&qir_module, &options.gates);
+            if gates > max_gates {
+                let message = format!(
+                    "Circuit has {} gates, exceeding the maximum of {} (--max-gates)",
+                    gates, max_gates
+                );
+                return (Err(vec![QirError::new(&message).into()]), warnings);
+            }
+        }
+        timings.record("verification", &mut clock);
+        
+        (Ok(qir_module), warnings)
     }
     
     // Helper for simple QASM string output
@@ -163,11 +528,191 @@ impl Compiler {
         Self::compile_with_stats(source, true).map(|res| res.qasm)
     }
     
-    // Helper for tests/diagnostics
-    pub fn compile_with_diagnostics(source: &str) -> (Result<String, Vec<String>>, CompileStats) {
-        match Self::compile_with_stats(source, true) {
-            Ok(res) => (Ok(res.qasm), res.stats),
-            Err(e) => (Err(e), CompileStats::default()),
+    /// Compiles with optimizations, keeping errors and warnings structured
+    /// (location, severity, hint) for tools that render their own messages.
+    pub fn compile_with_diagnostics(source: &str) -> (Result<String, Vec<Diagnostic>>, Vec<Diagnostic>, CompileStats) {
+        let options = CompileOptions { optimize: true, ..CompileOptions::default() };
+        match Self::run_pipeline(source, &options) {
+            (Ok(res), warnings) => (Ok(res.qasm), warnings, res.stats),
+            (Err(errors), warnings) => (Err(errors.iter().map(Diagnostic::from).collect()), warnings, CompileStats::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One `|1>` preparation and four applied gates.
+    const FIVE_GATES: &str = "fn main() -> int { let q: qubit = |1>; let p: qubit = |0>; \
+        H(q); X(p); CNOT(q, p); Z(q); let r: cbit = measure(q); return 0; }";
+
+    fn with_max_gates(max_gates: usize) -> Result<CompilationResult, Vec<String>> {
+        Compiler::compile_with_options(FIVE_GATES, &CompileOptions { max_gates: Some(max_gates), ..CompileOptions::default() })
+    }
+
+    #[test]
+    fn max_gates_counts_like_the_stats() {
+        let result = Compiler::compile_with_stats(FIVE_GATES, false).unwrap();
+        assert_eq!(result.stats.gates, 5);
+
+        let errors = with_max_gates(3).err().unwrap();
+        assert!(errors[0].contains("Circuit has 5 gates, exceeding the maximum of 3"), "{:?}", errors);
+        assert!(with_max_gates(4).is_err());
+        assert!(with_max_gates(5).is_ok());
+        assert!(with_max_gates(10).is_ok());
+    }
+
+    #[test]
+    fn max_depth_rejects_deep_circuits() {
+        let source = "fn main() -> int { let q: qubit = |0>; \
+            H(q); X(q); H(q); Z(q); H(q); Y(q); H(q); X(q); H(q); Z(q); let c: cbit = measure(q); return 0; }";
+        let with_max_depth = |max_depth| Compiler::compile_with_options(source, &CompileOptions { max_depth: Some(max_depth), ..CompileOptions::default() });
+
+        let errors = with_max_depth(3).err().unwrap();
+        assert!(errors[0].contains("exceeds the maximum of 3 (--max-depth)"), "{:?}", errors);
+        assert!(with_max_depth(100).is_ok());
+    }
+
+    #[test]
+    fn merged_stats_add_up() {
+        let bell = "fn main() -> int { let a: qubit = |0>; let b: qubit = |0>; H(a); CNOT(a, b); \
+            let x: cbit = measure(a); let y: cbit = measure(b); return 0; }";
+        let stats = Compiler::compile_with_stats(bell, false).unwrap().stats;
+        let mut totals = CompileStats::new();
+        totals.merge(&stats);
+        totals.merge(&stats);
+        assert_eq!((totals.qubits, totals.gates, totals.measurements), (4, 4, 4));
+    }
+
+    #[test]
+    fn diagnostics_carry_a_location() {
+        let (result, _, _) = Compiler::compile_with_diagnostics("fn main() -> int {\n    let x: int = 1\n    return x;\n}\n");
+        let errors = result.err().unwrap();
+        let span = errors[0].span.as_ref().unwrap();
+        assert!(span.line > 0, "{:?}", errors[0]);
+        assert!(errors[0].hint.is_some(), "{:?}", errors[0]);
+        assert_eq!(errors[0].severity, Severity::Error);
+
+        let (result, warnings, _) = Compiler::compile_with_diagnostics("fn main() -> int {\n    let unused: int = 1;\n    return 0;\n}\n");
+        assert!(result.is_ok());
+        let warning = warnings.iter().find(|w| w.message == "Variable 'unused' is never used").unwrap();
+        let span = warning.span.as_ref().unwrap();
+        assert_eq!((span.line, span.column), (2, 5), "{:?}", warning);
+        assert_eq!(warning.severity, Severity::Warning);
+    }
+
+    #[test]
+    fn entry_selects_the_circuit_root() {
+        let source = "fn create_bell_pair() -> int { let a: qubit = |0>; let b: qubit = |0>; H(a); CNOT(a, b); \
+            let x: cbit = measure(a); let y: cbit = measure(b); return 0; }";
+        let with_entry = |entry: &str| Compiler::compile_with_options(source, &CompileOptions { entry: Some(entry.to_string()), ..CompileOptions::default() });
+
+        let result = with_entry("create_bell_pair").unwrap();
+        assert!(result.qasm.contains("h q[0];\n  cx q[0], q[1];"), "{}", result.qasm);
+        assert_eq!((result.stats.qubits, result.stats.gates, result.stats.measurements), (2, 2, 2));
+
+        let errors = with_entry("main").err().unwrap();
+        assert!(errors[0].contains("Entry function 'main' not found (--entry)"), "{:?}", errors);
+    }
+
+    #[test]
+    fn compile_to_qir_returns_the_module() {
+        let bell = "fn main() -> int { let a: qubit = |0>; let b: qubit = |0>; H(a); CNOT(a, b); \
+            let x: cbit = measure(a); let y: cbit = measure(b); return 0; }";
+        let module = Compiler::compile_to_qir(bell, &CompileOptions::default()).unwrap();
+        assert_eq!(module.functions.len(), 1);
+        let allocs = module.functions[0].blocks.values()
+            .flat_map(|block| &block.ops)
+            .filter(|op| matches!(op, QirOp::AllocQubit { .. }))
+            .count();
+        assert!(allocs >= 2, "{}", allocs);
+    }
+
+    #[test]
+    fn parse_only_returns_the_program_or_diagnostics() {
+        let program = Compiler::parse_only("fn helper() -> int { return 1; } fn main() -> int { return 0; }").unwrap();
+        let names: Vec<&str> = program.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["helper", "main"]);
+
+        let errors = Compiler::parse_only("fn main() -> int { return 0;").err().unwrap();
+        assert!(errors[0].message.contains("'}'"), "{:?}", errors);
+        assert_eq!(errors[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn library_compile_prints_nothing() {
+        let source = "fn main() -> int { let q: qubit = |0>; H(q); let c: cbit = measure(q); return 0; }";
+        if std::env::var_os("QCLANG_STDOUT_CHILD").is_some() {
+            // Runs in the child below, whose stdout is not captured by the test harness
+            print!("<begin>");
+            Compiler::compile(source).unwrap();
+            Compiler::compile_with_stats(source, true).unwrap();
+            print!("<end>");
+            return;
         }
+
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "tests::library_compile_prints_nothing", "--nocapture", "--test-threads=1"])
+            .env("QCLANG_STDOUT_CHILD", "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let start = stdout.find("<begin>").unwrap() + "<begin>".len();
+        let end = stdout.find("<end>").unwrap();
+        assert_eq!(&stdout[start..end], "");
+    }
+
+    #[test]
+    fn time_passes_records_every_phase() {
+        let options = CompileOptions { time_passes: true, optimize: true, ..CompileOptions::default() };
+        let timings = Compiler::compile_with_options(FIVE_GATES, &options).unwrap().timings.unwrap();
+        let phases: Vec<&str> = timings.phases.iter().map(|(phase, _)| *phase).collect();
+        assert_eq!(phases, ["lexing", "parsing", "semantic analysis", "QIR building", "optimization", "verification", "code generation"]);
+        assert!(timings.total() > Duration::ZERO);
+
+        assert!(Compiler::compile_with_stats(FIVE_GATES, true).unwrap().timings.is_none());
+    }
+
+    #[test]
+    fn qir_dumps_show_cancelled_gates() {
+        let source = "fn main() -> int { let q: qubit = |0>; H(q); H(q); let c: cbit = measure(q); return 0; }";
+        let options = CompileOptions { optimize: true, dump_qir: true, ..CompileOptions::default() };
+        let snapshots = Compiler::compile_with_options(source, &options).unwrap().qir_snapshots.unwrap();
+        let hadamards = |dump: &str| dump.lines().filter(|line| line.ends_with("= h q0")).count();
+        assert_eq!(hadamards(&snapshots.before), 2, "{}", snapshots.before);
+        assert_eq!(hadamards(&snapshots.after), 0, "{}", snapshots.after);
+        assert!(snapshots.after.contains("measure q0 -> c0"), "{}", snapshots.after);
+
+        let plain = Compiler::compile_with_options(source, &CompileOptions { optimize: true, ..CompileOptions::default() }).unwrap();
+        assert!(plain.qir_snapshots.is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn compile_file_returns_qasm_and_its_output_path() {
+        let dir = std::env::temp_dir().join(format!("qclang-compile-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("flip.qc");
+        fs::write(&input, "fn main() -> int { let q: qubit = |0>; X(q); let c: cbit = measure(q); return 0; }").unwrap();
+
+        let output = Compiler::compile_file(&input, &CompileOptions::default()).unwrap();
+        assert!(output.result.qasm.contains("x q[0];"), "{}", output.result.qasm);
+        assert_eq!(output.output_path, dir.join("flip.qasm"));
+
+        let missing = Compiler::compile_file(&dir.join("missing.qc"), &CompileOptions::default()).err().unwrap();
+        assert!(missing[0].message.starts_with("Cannot read"), "{:?}", missing);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn count_only_skips_qasm_but_keeps_the_stats() {
+        let counted = Compiler::compile_with_options(FIVE_GATES, &CompileOptions { count_only: true, ..CompileOptions::default() }).unwrap();
+        let full = Compiler::compile_with_options(FIVE_GATES, &CompileOptions::default()).unwrap();
+        assert!(counted.qasm.is_empty());
+        assert!(!full.qasm.is_empty());
+        let counts = |stats: &CompileStats| (stats.qubits, stats.cbits, stats.gates, stats.measurements);
+        assert_eq!(counts(&counted.stats), counts(&full.stats));
+        assert_eq!(counts(&counted.stats), (2, 1, 5, 1));
+    }
+}
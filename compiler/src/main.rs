@@ -1,17 +1,7 @@
 // src/main.rs - TEST SUITE FOR PHASE 1.5 (FIXED)
-mod lexer;
-mod ast;
-mod parser;
-mod ir;
-mod qir;
-mod codegen;
-mod semantics;
-
-use lexer::tokenize;
-use parser::Parser;
-use qir::builder::QirBuilder;
-use qir::optimizer::QirOptimizer;
-use qir::analysis::QirAnalyzer;
+use qclang_compiler::lexer::tokenize;
+use qclang_compiler::parser::Parser;
+use qclang_compiler::qir::{self, QirAnalyzer, QirBuilder, QirOptimizer};
 
 fn test_qir_generation(source: &str, name: &str) {
     println!("\n=== TEST: {} ===", name);
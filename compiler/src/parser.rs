@@ -1,7 +1,7 @@
 // parser.rs - COMPLETE FOR PHASE 1.3
 use crate::lexer::Token;
-use crate::lexer::is_gate_name;
 use crate::ast::*;
+use crate::gates::GateRegistry;
 use std::iter::Peekable;
 use std::fmt;
 
@@ -21,6 +21,10 @@ pub struct Parser<I: Iterator<Item = (Token, usize, usize)> + Clone> {
     position: usize,
     type_aliases: std::collections::HashMap<String, Type>,
     struct_defs: std::collections::HashMap<String, StructDef>,
+    // Set while parsing a `qif` condition without parentheses, where `q {`
+    // starts the then-branch rather than a struct literal
+    no_struct_literal: bool,
+    gates: GateRegistry,
 }
 
 impl<I: Iterator<Item = (Token, usize, usize)> + Clone> Parser<I> {
@@ -32,8 +36,15 @@ impl<I: Iterator<Item = (Token, usize, usize)> + Clone> Parser<I> {
             position: 0,
             type_aliases: std::collections::HashMap::new(),
             struct_defs: std::collections::HashMap::new(),
+            no_struct_literal: false,
+            gates: GateRegistry::default(),
         }
     }
+    
+    /// Accepts the gates registered in `gates` alongside the built-in ones.
+    pub fn set_gate_registry(&mut self, gates: GateRegistry) {
+        self.gates = gates;
+    }
 
     pub fn parse_program(&mut self) -> Program {
         let mut functions = Vec::new();
@@ -190,12 +201,12 @@ impl<I: Iterator<Item = (Token, usize, usize)> + Clone> Parser<I> {
         let (token, line, col) = self.next_token()?;
         
         match token {
-            Token::KwInt => Some(Type::Int),
-            Token::KwFloat => Some(Type::Float),
-            Token::KwBool => Some(Type::Bool),
-            Token::KwString => Some(Type::String),
+            Token::KwInt => self.parse_array_type_suffix(Type::Int),
+            Token::KwFloat => self.parse_array_type_suffix(Type::Float),
+            Token::KwBool => self.parse_array_type_suffix(Type::Bool),
+            Token::KwString => self.parse_array_type_suffix(Type::String),
             Token::KwQubit => Some(Type::Qubit),
-            Token::KwCbit => Some(Type::Cbit),
+            Token::KwCbit => self.parse_array_type_suffix(Type::Cbit),
             Token::KwQreg => {
                 self.expect(&Token::BracketOpen, "opening bracket for qreg size")?;
                 let size = match self.parse_int_literal() {
@@ -267,6 +278,18 @@ impl<I: Iterator<Item = (Token, usize, usize)> + Clone> Parser<I> {
         }
     }
 
+    // Parses an optional `[size]` after a classical type, e.g. `cbit[3]`
+    fn parse_array_type_suffix(&mut self, elem_type: Type) -> Option<Type> {
+        if self.peek_token() != Some(&Token::BracketOpen) {
+            return Some(elem_type);
+        }
+        
+        self.next_token();
+        let size = self.parse_int_literal()? as usize;
+        self.expect(&Token::BracketClose, "closing bracket for array size")?;
+        Some(Type::Array(Box::new(elem_type), size))
+    }
+
     fn parse_type_alias_stmt(&mut self) -> Option<Stmt> {
         let start_pos = self.position;
         let (start_line, start_col) = match self.peek_token_with_pos() {
@@ -409,6 +432,8 @@ impl<I: Iterator<Item = (Token, usize, usize)> + Clone> Parser<I> {
                     position: saved_position,
                     type_aliases: self.type_aliases.clone(),
                     struct_defs: self.struct_defs.clone(),
+                    no_struct_literal: self.no_struct_literal,
+                    gates: self.gates.clone(),
                 };
                 
                 if let Some(_) = temp_parser.parse_type() {
@@ -489,12 +514,14 @@ impl<I: Iterator<Item = (Token, usize, usize)> + Clone> Parser<I> {
         if !self.consume_if(&Token::OpAssign) {
             self.expect(&Token::Semicolon, "semicolon after variable declaration")?;
             
-            let default_expr = if let Some(_size) = array_size {
-                let expr_span = Span::new(line, col, self.position, self.position);
-                Expr::LiteralInt(0, expr_span)
-            } else {
+            // Arrays start with every element at the element type's default
+            let default_ty = match (&actual_ty, array_size) {
+                (Type::Array(element_ty, _), Some(_)) => (**element_ty).clone(),
+                _ => actual_ty.clone(),
+            };
+            let default_expr = {
                 let expr_span = Span::new(line, col, self.position, self.position);
-                match actual_ty {
+                match default_ty {
                     Type::Int => Expr::LiteralInt(0, expr_span),
                     Type::Float => Expr::LiteralFloat(0.0, expr_span),
                     Type::Bool => Expr::LiteralBool(false, expr_span),
@@ -686,7 +713,7 @@ impl<I: Iterator<Item = (Token, usize, usize)> + Clone> Parser<I> {
                 format!("Bit string length {} doesn't match qreg size {}", bits.bits.len(), size),
                 bits_line,
                 bits_col,
-                Some(format!("Expected {} bits, got {}", size, bits.bits.len())),
+                Some(format!("Expected |{}> for qreg[{}], got {} bits", "0".repeat(size), size, bits.bits.len())),
             );
             return None;
         }
@@ -921,7 +948,10 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
             self.expect(&Token::ParenClose, "closing parenthesis for condition")?;
             cond
         } else {
-            self.parse_expr()?
+            self.no_struct_literal = true;
+            let cond = self.parse_expr();
+            self.no_struct_literal = false;
+            cond?
         };
         
         let then_branch = Box::new(self.parse_stmt()?);
@@ -970,7 +1000,19 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
             None => return None,
         };
         
-        let expr = self.parse_expr()?;
+        let mut expr = self.parse_expr()?;
+        
+        // `i++;` and `i--;` are statements, not expressions
+        let step = match self.peek_token() {
+            Some(Token::OpIncrement) => Some(UnaryOp::PostIncrement),
+            Some(Token::OpDecrement) => Some(UnaryOp::PostDecrement),
+            _ => None,
+        };
+        if let Some(op) = step {
+            self.next_token();
+            let span = Span::new(line, col, self.position, self.position);
+            expr = Expr::UnaryOp(op, Box::new(expr), span);
+        }
         self.expect(&Token::Semicolon, "semicolon after expression")?;
         
         if let Expr::BinaryOp(ref lhs, BinaryOp::Assign, ref rhs, _) = &expr {
@@ -978,6 +1020,12 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
                 return Some(Stmt::Assign(var_name.clone(), (**rhs).clone(), 
                                        Span::new(line, col, self.position, self.position)));
             }
+            if let Expr::Index(base, index, _) = &**lhs {
+                if let Expr::Variable(var_name, _) = &**base {
+                    return Some(Stmt::AssignIndex(var_name.clone(), index.clone(), (**rhs).clone(),
+                                                  Span::new(line, col, self.position, self.position)));
+                }
+            }
         }
         
         Some(Stmt::Expr(expr, Span::new(line, col, self.position, self.position)))
@@ -1117,10 +1165,10 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
             None => return None,
         };
         
-        let mut expr = self.parse_additive_expr()?;
+        let mut expr = self.parse_xor_expr()?;
         
         while let Some(op) = self.parse_relational_op() {
-            let rhs = self.parse_additive_expr()?;
+            let rhs = self.parse_xor_expr()?;
             let span = Span::new(start_line, start_col, start_pos, self.position);
             expr = Expr::BinaryOp(Box::new(expr), op, Box::new(rhs), span);
         }
@@ -1138,6 +1186,25 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
         }
     }
 
+    fn parse_xor_expr(&mut self) -> Option<Expr> {
+        let start_pos = self.position;
+        let (start_line, start_col) = match self.peek_token_with_pos() {
+            Some((_, line, col)) => (*line, *col),
+            None => return None,
+        };
+        
+        let mut expr = self.parse_additive_expr()?;
+        
+        while self.peek_token() == Some(&Token::OpXor) {
+            self.next_token();
+            let rhs = self.parse_additive_expr()?;
+            let span = Span::new(start_line, start_col, start_pos, self.position);
+            expr = Expr::BinaryOp(Box::new(expr), BinaryOp::Xor, Box::new(rhs), span);
+        }
+        
+        Some(expr)
+    }
+
     fn parse_additive_expr(&mut self) -> Option<Expr> {
         let start_pos = self.position;
         let (start_line, start_col) = match self.peek_token_with_pos() {
@@ -1235,7 +1302,7 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
                 Some(Expr::LiteralQubit(bits, span))
             }
             Token::Ident(name) => {
-                if self.peek_token() == Some(&Token::BraceOpen) {
+                if self.peek_token() == Some(&Token::BraceOpen) && !self.no_struct_literal {
                     self.parse_struct_literal(&name, line, col)
                 } else if self.peek_token() == Some(&Token::ParenOpen) {
                     self.next_token();
@@ -1244,22 +1311,48 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
                     
                     let span = Span::new(line, col, self.position, self.position);
                     
-                    if is_gate_name(&name) {
+                    if self.gates.is_gate(&name) {
                         self.parse_gate_application(&name, args, span)
                     } else if name == "measure" {
+                        match args.len() {
+                            1 => Some(Expr::Measure(Box::new(args[0].clone()), span)),
+                            2 => Some(Expr::MeasureInto(Box::new(args[0].clone()), Box::new(args[1].clone()), span)),
+                            _ => {
+                                self.add_error(
+                                    format!("measure expects 1 or 2 arguments, got {}", args.len()),
+                                    line,
+                                    col,
+                                    Some("Usage: measure(qubit) or measure(qubit, cbit)".to_string()),
+                                );
+                                None
+                            }
+                        }
+                    } else if name == "barrier" {
+                        if args.is_empty() {
+                            self.add_error(
+                                "barrier expects at least 1 argument".to_string(),
+                                line,
+                                col,
+                                Some("Usage: barrier(q1, q2, ...)".to_string()),
+                            );
+                            None
+                        } else {
+                            Some(Expr::Barrier(args, span))
+                        }
+                    } else if name == "reset" {
                         if args.len() == 1 {
-                            Some(Expr::Measure(Box::new(args[0].clone()), span))
+                            Some(Expr::Reset(Box::new(args[0].clone()), span))
                         } else {
                             self.add_error(
-                                format!("measure expects 1 argument, got {}", args.len()),
+                                format!("reset expects 1 argument, got {}", args.len()),
                                 line,
                                 col,
-                                Some("Usage: measure(qubit)".to_string()),
+                                Some("Usage: reset(qubit)".to_string()),
                             );
                             None
                         }
                     } else {
-                        Some(Expr::Call(name, args, span))
+                        self.parse_member_access(Expr::Call(name, args, span), line, col)
                     }
                 } else if self.peek_token() == Some(&Token::BracketOpen) {
                     let array_expr = Expr::Variable(name, Span::new(line, col, self.position, self.position));
@@ -1294,10 +1387,10 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
                     
                     self.expect(&Token::ParenClose, "closing parenthesis for tuple")?;
                     let span = Span::new(line, col, self.position, self.position);
-                    Some(Expr::Tuple(elements, span))
+                    self.parse_member_access(Expr::Tuple(elements, span), line, col)
                 } else {
                     self.expect(&Token::ParenClose, "closing parenthesis")?;
-                    Some(first_expr)
+                    self.parse_member_access(first_expr, line, col)
                 }
             }
             _ => {
@@ -1398,82 +1491,49 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
     }
     
     fn parse_gate_application(&mut self, gate_name: &str, args: Vec<Expr>, span: Span) -> Option<Expr> {
-        let gate_name_lower = gate_name.to_lowercase();
-        let gate = match gate_name_lower.as_str() {
-            "h" => Gate::H,
-            "x" => Gate::X,
-            "y" => Gate::Y,
-            "z" => Gate::Z,
-            "cnot" => Gate::CNOT,
-            "t" => Gate::T,
-            "s" => Gate::S,
-            "swap" => Gate::SWAP,
-            "rx" => {
-                if args.len() == 2 {
-                    let angle = args[0].clone();
-                    Gate::RX(Box::new(angle))
-                } else {
-                    self.add_error(
-                        format!("RX gate expects 2 arguments (angle and qubit), got {}", args.len()),
-                        span.line,
-                        span.column,
-                        Some("Usage: RX(angle, qubit)".to_string()),
-                    );
-                    return None;
-                }
-            }
-            "ry" => {
-                if args.len() == 2 {
-                    let angle = args[0].clone();
-                    Gate::RY(Box::new(angle))
-                } else {
-                    self.add_error(
-                        format!("RY gate expects 2 arguments (angle and qubit), got {}", args.len()),
-                        span.line,
-                        span.column,
-                        Some("Usage: RY(angle, qubit)".to_string()),
-                    );
-                    return None;
-                }
-            }
-            "rz" => {
-                if args.len() == 2 {
-                    let angle = args[0].clone();
-                    Gate::RZ(Box::new(angle))
-                } else {
-                    self.add_error(
-                        format!("RZ gate expects 2 arguments (angle and qubit), got {}", args.len()),
-                        span.line,
-                        span.column,
-                        Some("Usage: RZ(angle, qubit)".to_string()),
-                    );
-                    return None;
-                }
-            }
-            _ => {
-                self.add_error(
-                    format!("Unknown gate: '{}'", gate_name),
-                    span.line,
-                    span.column,
-                    Some("Valid gates: H, X, Y, Z, CNOT, RX, RY, RZ, T, S, SWAP".to_string()),
-                );
-                return None;
-            }
+        let custom = self.gates.custom(gate_name).map(|custom| Gate::Custom(custom.name.clone(), custom.arity));
+        let Some(mut gate) = Gate::from_name(gate_name).or(custom) else {
+            self.add_error(
+                format!("Unknown gate: '{}'", gate_name),
+                span.line,
+                span.column,
+                Some(format!("Valid gates: {}", self.gates.name_list())),
+            );
+            return None;
         };
         
-        let gate_args = match gate {
-            Gate::RX(_) | Gate::RY(_) | Gate::RZ(_) => {
-                if args.len() == 2 {
-                    vec![args[1].clone()]
-                } else {
-                    vec![]
-                }
+        // The angle is stored on the gate; the remaining arguments are qubits
+        let (name, qubits) = (gate.canonical_name().to_string(), gate.arity());
+        let gate_args = match gate.angle_mut() {
+            Some(angle) => {
+                *angle = self.rotation_angle(&name, &args, qubits, &span)?;
+                args[1..].to_vec()
             }
-            _ => args,
+            None => args,
         };
         
         Some(Expr::GateApply(Box::new(gate), gate_args, span))
     }
+    
+    /// Takes the leading angle argument of a rotation gate acting on `qubits` qubits.
+    fn rotation_angle(&mut self, name: &str, args: &[Expr], qubits: usize, span: &Span) -> Option<Box<Expr>> {
+        if args.len() == qubits + 1 {
+            return Some(Box::new(args[0].clone()));
+        }
+        
+        let (expected, usage) = if qubits == 1 {
+            ("angle and qubit", "qubit")
+        } else {
+            ("angle, control and target", "control, target")
+        };
+        self.add_error(
+            format!("{} gate expects {} arguments ({}), got {}", name, qubits + 1, expected, args.len()),
+            span.line,
+            span.column,
+            Some(format!("Usage: {}(angle, {})", name, usage)),
+        );
+        None
+    }
 
     fn parse_int_literal(&mut self) -> Option<i64> {
         let (token, line, col) = self.next_token()?;
@@ -1605,19 +1665,31 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
     }
     
     fn peek_token(&mut self) -> Option<&Token> {
+        self.report_lexer_errors();
         self.tokens.peek().map(|(token, _, _)| token)
     }
     
     fn peek_token_with_pos(&mut self) -> Option<&(Token, usize, usize)> {
+        self.report_lexer_errors();
         self.tokens.peek()
     }
     
     fn next_token(&mut self) -> Option<(Token, usize, usize)> {
+        self.report_lexer_errors();
         let (token, line, col) = self.tokens.next()?;
         self.position += 1;
         Some((token, line, col))
     }
     
+    // Lexer errors arrive as `Token::Error` and are reported as they are reached
+    fn report_lexer_errors(&mut self) {
+        while let Some((Token::Error(_), _, _)) = self.tokens.peek() {
+            if let Some((Token::Error(message), line, col)) = self.tokens.next() {
+                self.add_error(message, line, col, Some("Remove or fix the invalid input".to_string()));
+            }
+        }
+    }
+    
     fn consume_if(&mut self, expected: &Token) -> bool {
         if self.peek_token() == Some(expected) {
             self.next_token();
@@ -1632,6 +1704,7 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
             Stmt::Expr(expr, _) => Stmt::Expr(expr, span),
             Stmt::Let(name, ty, expr, mutable, _) => Stmt::Let(name, ty, expr, mutable, span),
             Stmt::Assign(name, expr, _) => Stmt::Assign(name, expr, span),
+            Stmt::AssignIndex(name, index, expr, _) => Stmt::AssignIndex(name, index, expr, span),
             Stmt::Block(stmts, _) => Stmt::Block(stmts, span),
             Stmt::If(cond, then_stmt, else_stmt, _) => Stmt::If(cond, then_stmt, else_stmt, span),
             Stmt::While(cond, body, _) => Stmt::While(cond, body, span),
@@ -1713,6 +1786,7 @@ fn parse_let_stmt(&mut self) -> Option<Stmt> {
             Token::Semicolon => ";".to_string(),
             Token::Arrow => "->".to_string(),
             Token::Dot => ".".to_string(),
+            Token::Error(message) => message.clone(),
             Token::__Skip => "<skip>".to_string(),
         }
     }
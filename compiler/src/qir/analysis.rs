@@ -1,5 +1,6 @@
 // src/qir/analysis.rs - FIXED WITH ALL PATTERNS
 use super::*;
+use crate::targets::Target;
 use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -17,8 +18,17 @@ pub enum ValueType {
 }
 
 pub struct QirAnalyzer {
-    errors: Vec<String>,
+    errors: Vec<QirError>,
     warnings: Vec<String>,
+    max_qubits: Option<usize>,
+    basis_gates: Option<Vec<String>>,
+    // Option named in limit errors, e.g. `--max-qubits` or `--target ibmq_small`
+    max_qubits_origin: String,
+    basis_gates_origin: String,
+    coupling_map: Option<Vec<(usize, usize)>>,
+    coupling_map_origin: String,
+    // Physical index of each qubit id; ids missing here are their own index
+    qubit_layout: HashMap<usize, usize>,
 }
 
 impl QirAnalyzer {
@@ -26,9 +36,57 @@ impl QirAnalyzer {
         Self {
             errors: Vec::new(),
             warnings: Vec::new(),
+            max_qubits: None,
+            basis_gates: None,
+            max_qubits_origin: "--max-qubits".to_string(),
+            basis_gates_origin: "--basis-gates".to_string(),
+            coupling_map: None,
+            coupling_map_origin: "--coupling-map".to_string(),
+            qubit_layout: HashMap::new(),
         }
     }
     
+    /// Makes modules that allocate more than `limit` qubits an error.
+    pub fn set_max_qubits(&mut self, limit: usize) {
+        self.max_qubits = Some(limit);
+        self.max_qubits_origin = "--max-qubits".to_string();
+    }
+    
+    /// Restricts gates to the given OpenQASM names (e.g. `rz`, `cx`).
+    pub fn set_basis_gates(&mut self, gates: &[String]) {
+        self.basis_gates = Some(gates.iter().map(|g| g.trim().to_lowercase()).collect());
+        self.basis_gates_origin = "--basis-gates".to_string();
+    }
+    
+    /// Applies a device preset's qubit limit and basis gates. Call before
+    /// the individual setters so that explicit limits override the preset.
+    pub fn set_target(&mut self, target: &Target) {
+        let origin = format!("--target {}", target.name);
+        self.max_qubits = Some(target.max_qubits);
+        self.max_qubits_origin = origin.clone();
+        if let Some(gates) = target.basis_gates {
+            self.basis_gates = Some(gates.iter().map(|g| g.to_string()).collect());
+            self.basis_gates_origin = origin.clone();
+        }
+        if !target.coupling_map.is_empty() {
+            self.coupling_map = Some(target.coupling_map.to_vec());
+            self.coupling_map_origin = origin;
+        }
+    }
+    
+    /// Requires every two-qubit gate to act on a pair of physical qubits
+    /// joined by an edge of `edges`, in either direction.
+    pub fn set_coupling_map(&mut self, edges: &[(usize, usize)]) {
+        self.coupling_map = Some(edges.to_vec());
+        self.coupling_map_origin = "--coupling-map".to_string();
+    }
+    
+    /// Maps qubit ids to the physical qubits the coupling map refers to,
+    /// e.g. from [`QASMGenerator::physical_qubits`](crate::codegen::QASMGenerator::physical_qubits).
+    pub fn set_qubit_layout(&mut self, layout: HashMap<usize, usize>) {
+        self.qubit_layout = layout;
+    }
+    
     pub fn analyze_module(&mut self, module: &QirModule) -> bool {
         self.errors.clear();
         self.warnings.clear();
@@ -41,6 +99,12 @@ impl QirAnalyzer {
         // Check global resources
         self.check_global_resources(module);
         
+        // Check the gate set
+        self.check_basis_gates(module);
+        
+        // Check connectivity
+        self.check_coupling_map(module);
+        
         self.errors.is_empty()
     }
     
@@ -59,32 +123,44 @@ impl QirAnalyzer {
         
         // 5. Check control flow
         self.check_control_flow(func);
+        
+        // 6. Check gate operand counts
+        self.check_gate_operands(func);
     }
     
     fn check_block_structure(&mut self, func: &QirFunction) {
         // All blocks should be reachable from entry block
-        let reachable = self.compute_reachable_blocks(func);
-        
-        for &block_id in func.blocks.keys() {
-            if !reachable.contains(&block_id) && block_id != func.entry_block {
-                self.warnings.push(format!(
-                    "Unreachable block {} in function {}",
-                    block_id.id(), func.name
-                ));
-            }
-        }
+        let unreachable = self.unreachable_blocks(func);
+        self.warnings.extend(unreachable.iter().map(|error| {
+            format!("{}{}", error, at_line(error.span()))
+        }));
         
         // Each block should end with a terminator
-        for (block_id, block) in &func.blocks {
+        for block_id in func.block_ids() {
+            let block = &func.blocks[&block_id];
             if !block.is_terminated() && !block.ops.is_empty() {
-                self.errors.push(format!(
-                    "Block {} in function {} doesn't end with a terminator",
-                    block_id.id(), func.name
-                ));
+                self.errors.push(QirError::MissingTerminator {
+                    function: func.name.clone(),
+                    block: block_id,
+                });
             }
         }
     }
     
+    /// An `UnreachableBlock` error for every block the entry block can't reach.
+    pub fn unreachable_blocks(&self, func: &QirFunction) -> Vec<QirError> {
+        let reachable = self.compute_reachable_blocks(func);
+        
+        func.block_ids().into_iter()
+            .filter(|block_id| !reachable.contains(block_id) && *block_id != func.entry_block)
+            .map(|block| QirError::UnreachableBlock {
+                function: func.name.clone(),
+                block,
+                span: func.blocks[&block].span.clone(),
+            })
+            .collect()
+    }
+    
     fn compute_reachable_blocks(&self, func: &QirFunction) -> HashSet<BlockId> {
         let mut visited = HashSet::new();
         let mut queue = VecDeque::new();
@@ -110,34 +186,44 @@ impl QirAnalyzer {
     
     fn check_ssa_properties(&mut self, func: &QirFunction) {
         let mut definitions = HashMap::new();
-        let mut uses = HashMap::new();
+        // Block of each temp's first use
+        let mut uses: HashMap<TempId, BlockId> = HashMap::new();
         
-        for (block_id, block) in &func.blocks {
+        for block_id in func.block_ids() {
+            let block = &func.blocks[&block_id];
             for (op_index, op) in block.ops.iter().enumerate() {
                 // Check which temps are defined
                 if let Some(temp_id) = self.get_result_temp(op) {
                     if definitions.contains_key(&temp_id) {
-                        self.errors.push(format!(
-                            "Temp {} redefined in block {} (SSA violation)",
-                            temp_id.id(), block_id.id()
-                        ));
+                        self.errors.push(QirError::SsaViolation {
+                            function: func.name.clone(),
+                            block: block_id,
+                            temp: temp_id,
+                        });
                     }
                     definitions.insert(temp_id, (block_id, op_index));
                 }
                 
                 // Check which temps are used
-                self.collect_temp_uses(op, &mut uses);
+                let mut op_uses = Vec::new();
+                self.collect_temp_uses(op, &mut op_uses);
+                for temp_id in op_uses {
+                    uses.entry(temp_id).or_insert(block_id);
+                }
             }
         }
         
-        // Check for undefined temps
-        for (temp_id, _) in uses {
-            if !definitions.contains_key(&temp_id) {
-                self.errors.push(format!(
-                    "Temp {} used before definition",
-                    temp_id.id()
-                ));
-            }
+        // Check for undefined temps, in temp order so the report is stable
+        let mut undefined: Vec<(TempId, BlockId)> = uses.into_iter()
+            .filter(|(temp_id, _)| !definitions.contains_key(temp_id))
+            .collect();
+        undefined.sort_by_key(|(temp_id, _)| temp_id.id());
+        for (temp_id, block_id) in undefined {
+            self.errors.push(QirError::UndefinedTemp {
+                function: func.name.clone(),
+                block: block_id,
+                temp: temp_id,
+            });
         }
     }
     
@@ -162,9 +248,11 @@ impl QirAnalyzer {
         }
     }
     
-    fn collect_temp_uses(&self, op: &QirOp, uses: &mut HashMap<TempId, usize>) {
-        fn add_temp_use(temp_id: TempId, uses: &mut HashMap<TempId, usize>) {
-            *uses.entry(temp_id).or_insert(0) += 1;
+    /// Appends every temp `op` reads to `uses`, including branch conditions,
+    /// return values and phi inputs.
+    pub fn collect_temp_uses(&self, op: &QirOp, uses: &mut Vec<TempId>) {
+        fn add_temp_use(temp_id: TempId, uses: &mut Vec<TempId>) {
+            uses.push(temp_id);
         }
         
         fn extract_temp(value: &QirValue) -> Option<TempId> {
@@ -233,6 +321,36 @@ impl QirAnalyzer {
                     }
                 }
             }
+            QirOp::ClassicalAssign { value, .. } => {
+                if let Some(temp_id) = extract_temp(value) {
+                    add_temp_use(temp_id, uses);
+                }
+            }
+            QirOp::MakeStruct { field_values: values, .. } |
+            QirOp::MakeArray { elements: values, .. } => {
+                for value in values {
+                    if let Some(temp_id) = extract_temp(value) {
+                        add_temp_use(temp_id, uses);
+                    }
+                }
+            }
+            QirOp::Branch { cond, .. } => {
+                if let Some(temp_id) = extract_temp(cond) {
+                    add_temp_use(temp_id, uses);
+                }
+            }
+            QirOp::Return { value: Some(value) } => {
+                if let Some(temp_id) = extract_temp(value) {
+                    add_temp_use(temp_id, uses);
+                }
+            }
+            QirOp::Phi { incoming, .. } => {
+                for (_, value) in incoming {
+                    if let Some(temp_id) = extract_temp(value) {
+                        add_temp_use(temp_id, uses);
+                    }
+                }
+            }
             _ => {}
         }
     }
@@ -240,7 +358,8 @@ impl QirAnalyzer {
     fn check_qubit_linearity(&mut self, func: &QirFunction) {
         let mut allocated_qubits = HashSet::new();
         
-        for (block_id, block) in &func.blocks {
+        for block_id in func.block_ids() {
+            let block = &func.blocks[&block_id];
             for op in &block.ops {
                 match op {
                     QirOp::AllocQubit { result: _, init_state: _ } => {
@@ -273,17 +392,18 @@ impl QirAnalyzer {
     }
     
     fn check_type_consistency(&mut self, func: &QirFunction) {
-        for (block_id, block) in &func.blocks {
+        for block_id in func.block_ids() {
+            let block = &func.blocks[&block_id];
             for op in &block.ops {
                 match op {
-                    QirOp::BinaryOp { lhs, rhs, .. } => {
+                    QirOp::BinaryOp { lhs, rhs, result, .. } => {
                         let lhs_type = self.infer_value_type(lhs);
                         let rhs_type = self.infer_value_type(rhs);
                         
                         if lhs_type != rhs_type {
                             self.warnings.push(format!(
-                                "Type mismatch in binary operation in block {}",
-                                block_id.id()
+                                "Type mismatch in binary operation in block {}{}",
+                                block_id.id(), at_line(func.temp_spans.get(result))
                             ));
                         }
                     }
@@ -317,8 +437,29 @@ impl QirAnalyzer {
         }
     }
     
+    /// Every gate needs exactly as many operands as its arity, since the
+    /// backend tells controls from targets by position.
+    fn check_gate_operands(&mut self, func: &QirFunction) {
+        for block_id in func.block_ids() {
+            for op in &func.blocks[&block_id].ops {
+                let (gate, operands) = match op {
+                    QirOp::ApplyGate { gate, args, .. } => (gate, args.len()),
+                    QirOp::ConditionalGate { gate, qubits, .. } => (gate, qubits.len()),
+                    _ => continue,
+                };
+                if operands != gate.arity() {
+                    self.errors.push(QirError::Other(format!(
+                        "Gate '{}' in block {} of function {} takes {} operand(s), got {}",
+                        gate.base_name(), block_id.id(), func.name, gate.arity(), operands
+                    )));
+                }
+            }
+        }
+    }
+    
     fn check_control_flow(&mut self, func: &QirFunction) {
-        for (block_id, block) in &func.blocks {
+        for block_id in func.block_ids() {
+            let block = &func.blocks[&block_id];
             if block.successors.len() > 1 {
                 for &succ in &block.successors {
                     if let Some(succ_block) = func.blocks.get(&succ) {
@@ -335,6 +476,16 @@ impl QirAnalyzer {
     }
     
     fn check_global_resources(&mut self, module: &QirModule) {
+        if let Some(limit) = self.max_qubits {
+            let count = module.qubit_count();
+            if count > limit {
+                self.errors.push(QirError::Other(format!(
+                    "Program uses {} qubits, exceeding the maximum of {} ({})",
+                    count, limit, self.max_qubits_origin
+                )));
+            }
+        }
+        
         if module.global_qubits.len() > 100 {
             self.warnings.push(format!(
                 "Large number of global qubits: {}",
@@ -350,11 +501,180 @@ impl QirAnalyzer {
         }
     }
     
-    pub fn get_errors(&self) -> &[String] {
+    /// Reports the first two-qubit gate, in program order, whose qubits
+    /// aren't adjacent in the coupling map.
+    fn check_coupling_map(&mut self, module: &QirModule) {
+        let Some(edges) = &self.coupling_map else {
+            return;
+        };
+        
+        for func in &module.functions {
+            for block_id in func.block_ids() {
+                for op in &func.blocks[&block_id].ops {
+                    let (gate, qubits): (_, Vec<usize>) = match op {
+                        QirOp::ApplyGate { gate, args, .. } => (gate, args.iter().filter_map(|arg| match arg {
+                            QirValue::Qubit(qubit) => Some(qubit.id()),
+                            _ => None,
+                        }).collect()),
+                        QirOp::ConditionalGate { gate, qubits, .. } => (gate, qubits.iter().map(|q| q.id()).collect()),
+                        _ => continue,
+                    };
+                    if qubits.len() > 2 {
+                        self.errors.push(QirError::Other(format!(
+                            "Gate '{}' in function {} acts on {} qubits, but the coupling map only connects pairs ({})",
+                            gate.base_name(), func.name, qubits.len(), self.coupling_map_origin
+                        )));
+                        return;
+                    }
+                    let &[a, b] = qubits.as_slice() else {
+                        continue;
+                    };
+                    
+                    let physical = |id: usize| self.qubit_layout.get(&id).copied().unwrap_or(id);
+                    let (a, b) = (physical(a), physical(b));
+                    if !edges.iter().any(|&edge| edge == (a, b) || edge == (b, a)) {
+                        self.errors.push(QirError::Other(format!(
+                            "Gate '{}' in function {} acts on qubits {} and {}, which are not adjacent in the coupling map ({})",
+                            gate.base_name(), func.name, a, b, self.coupling_map_origin
+                        )));
+                        return;
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Reports the first gate, in program order, outside the basis set.
+    fn check_basis_gates(&mut self, module: &QirModule) {
+        let Some(basis) = &self.basis_gates else {
+            return;
+        };
+        
+        for func in &module.functions {
+            let block_ids = func.block_ids();
+            
+            for block_id in block_ids {
+                for op in &func.blocks[&block_id].ops {
+                    let gate = match op {
+                        QirOp::ApplyGate { gate, .. } | QirOp::ConditionalGate { gate, .. } => gate,
+                        _ => continue,
+                    };
+                    let name = gate.base_name();
+                    if !basis.contains(&name) {
+                        self.errors.push(QirError::Other(format!(
+                            "Gate '{}' in function {} is not in the basis gate set {{{}}} ({})",
+                            name, func.name, basis.join(", "), self.basis_gates_origin
+                        )));
+                        return;
+                    }
+                }
+            }
+        }
+    }
+    
+    pub fn get_errors(&self) -> &[QirError] {
         &self.errors
     }
     
     pub fn get_warnings(&self) -> &[String] {
         &self.warnings
     }
-}
\ No newline at end of file
+}
+
+/// ` at line N` for messages about an op or block with a known source span.
+fn at_line(span: Option<&Span>) -> String {
+    match span {
+        Some(span) => format!(" at line {}", span.line),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QirAnalyzer;
+    use crate::ast::BinaryOp;
+    use crate::qir::{QirError, QirFunction, QirModule, QirOp, QirType, QirValue, TempId};
+    use crate::targets;
+    use crate::{CompilationResult, CompileOptions, Compiler};
+
+    fn compile(body: &str, options: CompileOptions) -> Result<CompilationResult, Vec<String>> {
+        let source = format!("fn main() -> int {{ {} return 0; }}", body);
+        Compiler::compile_with_options(&source, &options)
+    }
+
+    #[test]
+    fn max_qubits_limits_the_program() {
+        let body = "qreg r[5] = |00000>; H(r[0]);";
+        let errors = compile(body, CompileOptions { max_qubits: Some(3), ..CompileOptions::default() }).err().unwrap();
+        assert!(errors[0].contains("Program uses 5 qubits, exceeding the maximum of 3 (--max-qubits)"), "{:?}", errors);
+        assert!(compile(body, CompileOptions { max_qubits: Some(5), ..CompileOptions::default() }).is_ok());
+    }
+
+    #[test]
+    fn basis_gates_reject_other_gates() {
+        let body = "let a: qubit = |0>; let b: qubit = |0>; H(a); CNOT(a, b); RZ(pi / 2, b);";
+        let basis = |gates: &[&str]| CompileOptions {
+            basis_gates: Some(gates.iter().map(|g| g.to_string()).collect()),
+            ..CompileOptions::default()
+        };
+
+        let errors = compile(body, basis(&["rz", "cx"])).err().unwrap();
+        assert!(errors[0].contains("Gate 'h' in function main is not in the basis gate set {rz, cx} (--basis-gates)"), "{:?}", errors);
+        assert!(compile(body, basis(&["rz", "cx", "h"])).is_ok());
+    }
+
+    #[test]
+    fn validate_reports_a_redefined_temp() {
+        let mut func = QirFunction::new("main", Vec::new(), QirType::Int);
+        let result = TempId::new(0);
+        func.add_op(QirOp::BinaryOp { op: BinaryOp::Add, lhs: QirValue::Int(1), rhs: QirValue::Int(2), result });
+        func.add_op(QirOp::BinaryOp { op: BinaryOp::Mul, lhs: QirValue::Int(3), rhs: QirValue::Int(4), result });
+        func.add_op(QirOp::Return { value: Some(QirValue::Temp(result)) });
+        let entry = func.entry_block;
+        let mut module = QirModule::new("main");
+        module.add_function(func);
+
+        let errors = module.validate().unwrap_err();
+        assert!(errors.iter().any(|error| matches!(error,
+            QirError::SsaViolation { function, block, temp } if function == "main" && *block == entry && *temp == result)), "{:?}", errors);
+
+        let compiled = Compiler::compile_to_qir("fn main() -> int { let q: qubit = |0>; H(q); return 0; }", &CompileOptions::default()).unwrap();
+        assert_eq!(compiled.validate(), Ok(()));
+    }
+
+    #[test]
+    fn qir_warnings_cite_the_source_line() {
+        let source = "fn main() -> int {
+            let q: qubit = |0>;
+            let mut n: int = 2;
+            let x: float = n * 1.5;
+            return 0;
+        }";
+        let module = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        let mut analyzer = QirAnalyzer::new();
+        analyzer.analyze_module(&module);
+        let warnings = analyzer.get_warnings().join("\n");
+        assert!(warnings.contains("Type mismatch in binary operation in block 0 at line 4"), "{}", warnings);
+    }
+
+    #[test]
+    fn target_presets_check_their_basis_gates() {
+        let target = |name: &str| CompileOptions { target: targets::lookup(name), ..CompileOptions::default() };
+
+        let errors = compile("let q: qubit = |0>; H(q);", target("ibmq_small")).err().unwrap();
+        assert!(errors[0].contains("Gate 'h' in function main is not in the basis gate set {rz, sx, x, cx} (--target ibmq_small)"), "{:?}", errors);
+        assert!(compile("let q: qubit = |0>; X(q); RZ(pi / 2, q);", target("ibmq_small")).is_ok());
+        assert!(compile("let q: qubit = |0>; H(q);", target("simulator")).is_ok());
+    }
+
+    #[test]
+    fn coupling_map_rejects_non_adjacent_pairs() {
+        let line = CompileOptions { coupling_map: Some(vec![(0, 1), (1, 2)]), ..CompileOptions::default() };
+        let body = |control: usize, target: usize| format!("qreg r[3] = |000>; H(r[{}]); CNOT(r[{}], r[{}]);", control, control, target);
+
+        let errors = compile(&body(0, 2), line.clone()).err().unwrap();
+        assert!(errors[0].contains("acts on qubits 0 and 2, which are not adjacent in the coupling map"), "{:?}", errors);
+        assert!(compile(&body(0, 1), line.clone()).is_ok());
+        assert!(compile(&body(2, 1), line).is_ok());
+    }
+}
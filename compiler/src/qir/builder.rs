@@ -1,15 +1,46 @@
 // src/qir/builder.rs - FIXED LET STATEMENT HANDLER
-use crate::ast::{Program, Function, Stmt, Expr, Type, BinaryOp, UnaryOp, Gate as AstGate};
+use crate::ast::{Program, Function, Stmt, Expr, Type, BinaryOp, UnaryOp, StructDef, Gate as AstGate};
 // Removed: use crate::semantics::{SemanticAnalyzer, TypeRegistry}; -- We trust the caller!
 use super::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// Loops that would unroll to more iterations than this are rejected.
+pub const DEFAULT_UNROLL_THRESHOLD: usize = 1000;
+
+/// Source variable bindings: each name's type and current value.
+type Bindings = HashMap<String, (QirType, QirValue)>;
 
 pub struct QirBuilder {
     module: QirModule,
     current_function: Option<String>,
     // type_registry: TypeRegistry, // Removed dependency on TypeRegistry for now to simplify
-    symbol_table: HashMap<String, (QirType, QirValue)>,
+    symbol_table: Bindings,
+    // Mutable bindings keep their initial value in `symbol_table`, so they must not be folded
+    mutable_vars: HashSet<String>,
     loop_stack: Vec<BlockId>,
+    // Depth of `while` loops currently being unrolled
+    unrolling: usize,
+    // Depth of runtime `if` branches currently being built
+    branching: usize,
+    unroll_threshold: usize,
+    // User-defined functions, inlined at each call site
+    functions: HashMap<String, Function>,
+    // Struct layouts and type aliases, for binding struct fields as `var.field`
+    struct_defs: HashMap<String, StructDef>,
+    type_aliases: HashMap<String, Type>,
+    // Functions whose bodies are currently being inlined, innermost last
+    inline_stack: Vec<String>,
+    // Value of the `return` reached in the function being inlined
+    inline_return: Option<QirValue>,
+    // Line of an `if` decided at runtime or on a measured cbit whose branch
+    // returns, and the number of quantum ops the function had then; none may
+    // follow it
+    conditional_return: Option<(usize, usize)>,
+    // The function being built takes parameters. Its own body is dropped in
+    // favour of the copies inlined at each call, where the parameters are
+    // known, so only those copies are checked against what OpenQASM can do
+    template: bool,
+    errors: Vec<String>,
     qubit_counter: usize,
     cbit_counter: usize,
     temp_counter: usize,
@@ -22,17 +53,47 @@ impl QirBuilder {
             current_function: None,
             // type_registry: TypeRegistry::new(),
             symbol_table: HashMap::new(),
+            mutable_vars: HashSet::new(),
             loop_stack: Vec::new(),
+            unrolling: 0,
+            branching: 0,
+            unroll_threshold: DEFAULT_UNROLL_THRESHOLD,
+            functions: HashMap::new(),
+            struct_defs: HashMap::new(),
+            type_aliases: HashMap::new(),
+            inline_stack: Vec::new(),
+            inline_return: None,
+            conditional_return: None,
+            template: false,
+            errors: Vec::new(),
             qubit_counter: 0,
             cbit_counter: 0,
             temp_counter: 0,
         }
     }
     
+    pub fn set_unroll_threshold(&mut self, threshold: usize) {
+        self.unroll_threshold = threshold;
+    }
+    
+    pub fn get_errors(&self) -> &[String] {
+        &self.errors
+    }
+    
     pub fn build_from_program(&mut self, program: &Program) -> QirModule {
         // FIX: Removed redundant SemanticAnalyzer check. 
         // We assume lib.rs has already validated the AST.
         
+        for func in &program.functions {
+            self.functions.insert(func.name.clone(), func.clone());
+        }
+        for def in &program.struct_defs {
+            self.struct_defs.insert(def.name.clone(), def.clone());
+        }
+        for alias in &program.type_aliases {
+            self.type_aliases.insert(alias.name.clone(), alias.target.clone());
+        }
+        
         for func in &program.functions {
             self.build_function(func);
         }
@@ -55,7 +116,9 @@ impl QirBuilder {
         let mut qir_func = QirFunction::new(&ast_func.name, params, return_type);
         
         self.current_function = Some(ast_func.name.clone());
+        self.template = !ast_func.params.is_empty();
         self.symbol_table.clear();
+        self.mutable_vars.clear();
         self.qubit_counter = 0;
         self.cbit_counter = 0;
         self.temp_counter = 0;
@@ -64,17 +127,40 @@ impl QirBuilder {
             self.build_statement(stmt, &mut qir_func);
         }
         
+        if let Some((line, quantum_ops)) = self.conditional_return.take() {
+            if Self::quantum_op_count(&qir_func) > quantum_ops && !self.template {
+                self.errors.push(format!(
+                    "Quantum operations after the if at line {} only run when its branch does not return, which OpenQASM 2.0 cannot express",
+                    line
+                ));
+            }
+        }
+        
         // Ensure the function ends with a return if not present (implicit void return)
         let current_blk = qir_func.get_current_block_mut();
         if !current_blk.is_terminated() {
             current_blk.add_op(QirOp::Return { value: None });
         }
         
+        // Keep the function's resource counts in step with what was allocated
+        qir_func.next_qubit_id = self.qubit_counter;
+        qir_func.next_cbit_id = self.cbit_counter;
+        qir_func.next_temp_id = self.temp_counter;
+        
         self.module.add_function(qir_func);
         self.current_function = None;
     }
     
     fn build_statement(&mut self, stmt: &Stmt, qir_func: &mut QirFunction) {
+        let first_temp = self.temp_counter;
+        let first_block = qir_func.next_block_id;
+        
+        self.build_statement_ops(stmt, qir_func);
+        
+        qir_func.record_span(first_temp..self.temp_counter, first_block..qir_func.next_block_id, stmt.span());
+    }
+    
+    fn build_statement_ops(&mut self, stmt: &Stmt, qir_func: &mut QirFunction) {
         match stmt {
             Stmt::Let(name, ty, expr, mutable, _) => {
                 self.build_let_stmt(name, ty, expr, *mutable, qir_func);
@@ -82,24 +168,41 @@ impl QirBuilder {
             Stmt::Assign(name, expr, _) => {
                 self.build_assign_stmt(name, expr, qir_func);
             }
-            Stmt::Expr(expr, _) => {
-                self.build_expr(expr, qir_func);
+            Stmt::AssignIndex(name, index, expr, span) => {
+                self.build_assign_index_stmt(name, index, expr, span.line, qir_func);
             }
+            Stmt::Expr(expr, _) => match stmt.step_assignment() {
+                Some((name, value)) => self.build_assign_stmt(name, &value, qir_func),
+                None => {
+                    self.build_expr(expr, qir_func);
+                }
+            },
             Stmt::Return(expr, _) => {
                 self.build_return_stmt(expr, qir_func);
             }
             Stmt::Block(stmts, _) => {
                 self.build_block(stmts, qir_func);
             }
-            Stmt::If(condition, then_branch, else_branch, _) => {
-                self.build_if_stmt(condition, then_branch, else_branch.as_deref(), qir_func);
+            Stmt::If(condition, then_branch, else_branch, span) => {
+                self.build_if_stmt(condition, then_branch, else_branch.as_deref(), span.line, qir_func);
             }
-            Stmt::While(condition, body, _) => {
-                self.build_while_stmt(condition, body, qir_func);
+            Stmt::While(condition, body, span) => {
+                self.build_while_stmt(condition, body, span.line, qir_func);
             }
             Stmt::ForRange(var_name, start, end, step, body, _) => {
                 self.build_for_range_stmt(var_name, start, end, step, body, qir_func);
             }
+            Stmt::QIf(condition, then_branch, else_branch, span) => {
+                self.build_qif_stmt(condition, then_branch, else_branch.as_deref(), span.line, qir_func);
+            }
+            Stmt::QForRange(var_name, start, end, step, body, span) => {
+                // Unrolled like `for`, but recorded so later passes can tell the iterations apart
+                self.module.metadata.insert(
+                    format!("qfor.{}.{}", qir_func.name, span.line),
+                    var_name.clone(),
+                );
+                self.build_for_range_stmt(var_name, start, end, step, body, qir_func);
+            }
             Stmt::Break(_) => {
                 self.build_break_stmt(qir_func);
             }
@@ -110,7 +213,13 @@ impl QirBuilder {
         }
     }
     
-    fn build_let_stmt(&mut self, name: &str, ty: &Type, expr: &Expr, _mutable: bool, qir_func: &mut QirFunction) {
+    fn build_let_stmt(&mut self, name: &str, ty: &Type, expr: &Expr, mutable: bool, qir_func: &mut QirFunction) {
+        if mutable {
+            self.mutable_vars.insert(name.to_string());
+        } else {
+            self.mutable_vars.remove(name);
+        }
+        
         match ty {
             Type::Qreg(size) => {
                 let mut qubit_values = Vec::new();
@@ -120,6 +229,12 @@ impl QirBuilder {
                     None
                 };
                 
+                // Keep the register name so the QASM output can declare it as written
+                self.module.metadata.insert(
+                    format!("qreg.{}", name),
+                    format!("{},{}", self.qubit_counter, size),
+                );
+                
                 for i in 0..*size {
                     let qubit_id = QubitId::new(self.qubit_counter);
                     self.qubit_counter += 1;
@@ -149,7 +264,16 @@ impl QirBuilder {
                 self.symbol_table.insert(name.to_string(), (qir_type, QirValue::Array(qubit_values)));
             }
             Type::Array(elem_type, size) => {
-                if let Type::Cbit = **elem_type {
+                let measured = match expr {
+                    Expr::Measure(..) => true,
+                    Expr::Call(name, _, _) => name == "measure_x" || name == "measure_y",
+                    _ => false,
+                };
+                if let (Type::Cbit, true) = (&**elem_type, measured) {
+                    let value = self.build_expr_value(expr, qir_func);
+                    let qir_type = self.convert_type(ty);
+                    self.symbol_table.insert(name.to_string(), (qir_type, value));
+                } else if let Type::Cbit = **elem_type {
                     let mut cbit_values = Vec::new();
                     
                     for _ in 0..*size {
@@ -170,7 +294,11 @@ impl QirBuilder {
                     let qir_type = self.convert_type(ty);
                     self.symbol_table.insert(name.to_string(), (qir_type, QirValue::Array(cbit_values)));
                 } else {
-                    let value = self.build_expr_value(expr, qir_func);
+                    let value = match self.build_expr_value(expr, qir_func) {
+                        value @ QirValue::Array(_) => value,
+                        // A scalar initializer fills every element, e.g. `mut int counts[3];`
+                        value => QirValue::Array(vec![value; *size]),
+                    };
                     let qir_type = self.convert_type(ty);
                     self.symbol_table.insert(name.to_string(), (qir_type, value));
                 }
@@ -178,7 +306,9 @@ impl QirBuilder {
             _ => {
                 let value = self.build_expr_value(expr, qir_func);
                 let qir_type = self.convert_type(ty);
+                let fields = self.field_bindings(name, ty, &value);
                 self.symbol_table.insert(name.to_string(), (qir_type, value));
+                self.symbol_table.extend(fields);
             }
         }
     }
@@ -201,9 +331,122 @@ impl QirBuilder {
                     }
                 }
             }
+        } else if self.unrolling > 0 {
+            let value = self.eval_const(expr);
+            self.track_assignment(name, value);
+        } else if self.branching > 0 {
+            let value = self.build_expr_value(expr, qir_func);
+            self.track_assignment(name, Some(value));
+        } else {
+            // Keeps later compile-time reads (indices, angles, loop bounds)
+            // in step with straight-line updates
+            let value = self.eval_const(expr).unwrap_or_else(|| self.build_expr_value(expr, qir_func));
+            self.track_assignment(name, Some(value));
+        }
+    }
+    
+    /// `name[index] = expr;`: a gate on a register element updates the qubit in
+    /// place, while a value stored into a classical array replaces the element
+    /// so later reads of `name[index]` see it.
+    fn build_assign_index_stmt(&mut self, name: &str, index: &Expr, expr: &Expr, line: usize, qir_func: &mut QirFunction) {
+        let classical = matches!(self.symbol_table.get(name),
+            Some((_, QirValue::Array(elements))) if !elements.iter().any(|e| matches!(e, QirValue::Qubit(_))));
+        if !classical {
+            self.build_expr(expr, qir_func);
+            return;
+        }
+        
+        let value = match self.eval_const(expr) {
+            Some(value) => value,
+            None if self.unrolling > 0 => QirValue::Null,
+            None => self.build_expr_value(expr, qir_func),
+        };
+        let Some(QirValue::Int(index)) = self.eval_const(index) else {
+            self.errors.push(format!(
+                "Index into '{}' must be a compile-time constant at line {}",
+                name, line
+            ));
+            return;
+        };
+        if let Some((_, QirValue::Array(elements))) = self.symbol_table.get_mut(name) {
+            match usize::try_from(index).ok().and_then(|i| elements.get_mut(i)) {
+                Some(element) => *element = value,
+                None => {
+                    let size = elements.len();
+                    self.errors.push(format!(
+                        "Index {} out of bounds for '{}' of size {} at line {}",
+                        index, name, size, line
+                    ));
+                }
+            }
         }
     }
     
+    /// Records the new value of a `mut` variable assigned inside an unrolled
+    /// `while` or a runtime `if` branch, or marks it unknown when there is none.
+    fn track_assignment(&mut self, name: &str, value: Option<QirValue>) {
+        if !self.mutable_vars.contains(name) {
+            return;
+        }
+        if let Some(entry) = self.symbol_table.get_mut(name) {
+            entry.1 = value.unwrap_or(QirValue::Null);
+        }
+    }
+    
+    /// Evaluates a classical expression from literals and the current values
+    /// of variables without emitting any ops.
+    fn eval_const(&self, expr: &Expr) -> Option<QirValue> {
+        match expr {
+            Expr::LiteralInt(value, _) => Some(QirValue::Int(*value)),
+            Expr::LiteralFloat(value, _) => Some(QirValue::Float(*value)),
+            Expr::LiteralBool(value, _) => Some(QirValue::Bool(*value)),
+            Expr::Variable(name, _) => match self.symbol_table.get(name) {
+                Some((_, value @ (QirValue::Int(_) | QirValue::Float(_) | QirValue::Bool(_)))) => Some(value.clone()),
+                _ => None,
+            },
+            Expr::BinaryOp(lhs, op, rhs, _) => {
+                let (lhs, rhs) = (self.eval_const(lhs)?, self.eval_const(rhs)?);
+                Self::fold_const_binary(op, &lhs, &rhs)
+            }
+            Expr::Index(array, index, _) => {
+                let Expr::Variable(name, _) = &**array else { return None };
+                let (Some((_, QirValue::Array(elements))), QirValue::Int(index)) = (self.symbol_table.get(name), self.eval_const(index)?) else {
+                    return None;
+                };
+                match elements.get(usize::try_from(index).ok()?)? {
+                    value @ (QirValue::Int(_) | QirValue::Float(_) | QirValue::Bool(_)) => Some(value.clone()),
+                    _ => None,
+                }
+            }
+            Expr::UnaryOp(op, operand, _) => match (op, self.eval_const(operand)?) {
+                (UnaryOp::Neg, QirValue::Int(v)) => v.checked_neg().map(QirValue::Int),
+                (UnaryOp::Neg, QirValue::Float(v)) => Some(QirValue::Float(-v)),
+                (UnaryOp::Not, QirValue::Bool(v)) => Some(QirValue::Bool(!v)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    
+    /// The value stored by a plain or compound assignment, if it is constant.
+    fn eval_assignment(&self, target: &Expr, op: &BinaryOp, value: &Expr) -> Option<QirValue> {
+        let arith = match op {
+            BinaryOp::AddAssign => BinaryOp::Add,
+            BinaryOp::SubAssign => BinaryOp::Sub,
+            BinaryOp::MulAssign => BinaryOp::Mul,
+            BinaryOp::DivAssign => BinaryOp::Div,
+            _ => return self.eval_const(value),
+        };
+        let (lhs, rhs) = (self.eval_const(target)?, self.eval_const(value)?);
+        Self::fold_const_binary(&arith, &lhs, &rhs)
+    }
+    
+    fn fold_const_binary(op: &BinaryOp, lhs: &QirValue, rhs: &QirValue) -> Option<QirValue> {
+        Self::fold_float_binary(op, lhs, rhs)
+            .map(QirValue::Float)
+            .or_else(|| Self::fold_classical_binary(op, lhs, rhs))
+    }
+    
     fn build_expr(&mut self, expr: &Expr, qir_func: &mut QirFunction) -> QirValue {
         self.build_expr_value(expr, qir_func)
     }
@@ -236,22 +479,33 @@ impl QirBuilder {
             Expr::Variable(name, _) => {
                 if let Some((_ty, value)) = self.symbol_table.get(name) {
                     value.clone()
+                } else if name == "pi" {
+                    QirValue::Float(std::f64::consts::PI)
                 } else {
                     QirValue::Variable(name.clone())
                 }
             }
-            Expr::BinaryOp(left, op, right, _) => {
-                self.build_binary_expr(left, op, right, qir_func)
+            Expr::BinaryOp(left, op, right, span) => {
+                self.build_binary_expr(left, op, right, span.line, qir_func)
             }
             Expr::UnaryOp(op, operand, _) => {
                 self.build_unary_expr(op, operand, qir_func)
             }
-            Expr::Call(name, args, _) => {
-                self.build_call_expr(name, args, qir_func)
+            Expr::Call(name, args, span) => {
+                self.build_call_expr(name, args, span.line, qir_func)
             }
             Expr::Measure(qubit_expr, _) => {
                 self.build_measure_expr(qubit_expr, qir_func)
             }
+            Expr::MeasureInto(qubit_expr, target_expr, _) => {
+                self.build_measure_into_expr(qubit_expr, target_expr, qir_func)
+            }
+            Expr::Reset(qubit_expr, _) => {
+                self.build_reset_expr(qubit_expr, qir_func)
+            }
+            Expr::Barrier(args, _) => {
+                self.build_barrier_expr(args, qir_func)
+            }
             Expr::GateApply(gate, args, _) => {
                 self.build_gate_apply_expr(gate, args, qir_func)
             }
@@ -264,13 +518,54 @@ impl QirBuilder {
                     .collect();
                 QirValue::Tuple(values)
             }
-            _ => QirValue::Null,
+            Expr::StructLiteral(name, fields, _) => {
+                self.build_struct_literal(name, fields, qir_func)
+            }
+            Expr::MemberAccess(base_expr, field, _) => {
+                self.build_member_access_expr(base_expr, field, qir_func)
+            }
         }
     }
     
-    fn build_binary_expr(&mut self, left: &Expr, op: &BinaryOp, right: &Expr, qir_func: &mut QirFunction) -> QirValue {
+    fn build_binary_expr(&mut self, left: &Expr, op: &BinaryOp, right: &Expr, line: usize, qir_func: &mut QirFunction) -> QirValue {
+        // `x += 1` inside an unrolled `while` updates the loop state directly
+        if self.unrolling > 0 {
+            if let (Expr::Variable(name, _), BinaryOp::Assign | BinaryOp::AddAssign | BinaryOp::SubAssign
+                | BinaryOp::MulAssign | BinaryOp::DivAssign) = (left, op) {
+                let value = self.eval_assignment(left, op, right);
+                self.track_assignment(name, value.clone());
+                if let Some(value) = value {
+                    return value;
+                }
+            }
+        }
+        
         let lhs = self.build_expr_value(left, qir_func);
         let rhs = self.build_expr_value(right, qir_func);
+        
+        if !self.reads_mutable(left) && !self.reads_mutable(right) {
+            if Self::divides_by_zero(op, &lhs, &rhs) {
+                self.errors.push(format!(
+                    "Division by zero in constant expression at line {}",
+                    line
+                ));
+                return QirValue::Null;
+            }
+            if Self::overflows_int(op, &lhs, &rhs) {
+                self.errors.push(format!(
+                    "Integer overflow in constant expression at line {}",
+                    line
+                ));
+                return QirValue::Null;
+            }
+            // Fold float arithmetic on constants (e.g. `pi / 2`)
+            if let Some(value) = Self::fold_float_binary(op, &lhs, &rhs) {
+                return QirValue::Float(value);
+            }
+            if let Some(value) = Self::fold_classical_binary(op, &lhs, &rhs) {
+                return value;
+            }
+        }
         let result_temp = TempId::new(self.temp_counter);
         self.temp_counter += 1;
         
@@ -280,11 +575,130 @@ impl QirBuilder {
             rhs,
             result: result_temp,
         });
+        
+        // `x += 1` inside a runtime `if` branch leaves `x` holding the result
+        if self.branching > 0 {
+            if let (Expr::Variable(name, _), BinaryOp::Assign | BinaryOp::AddAssign | BinaryOp::SubAssign
+                | BinaryOp::MulAssign | BinaryOp::DivAssign) = (left, op) {
+                self.track_assignment(name, Some(QirValue::Temp(result_temp)));
+            }
+        }
         QirValue::Temp(result_temp)
     }
     
+    /// Whether `lhs op rhs` on two integer constants falls outside `i64`.
+    fn overflows_int(op: &BinaryOp, lhs: &QirValue, rhs: &QirValue) -> bool {
+        let (QirValue::Int(l), QirValue::Int(r)) = (lhs, rhs) else {
+            return false;
+        };
+        match op {
+            BinaryOp::Add => l.checked_add(*r).is_none(),
+            BinaryOp::Sub => l.checked_sub(*r).is_none(),
+            BinaryOp::Mul => l.checked_mul(*r).is_none(),
+            BinaryOp::Div => l.checked_div(*r).is_none(),
+            _ => false,
+        }
+    }
+    
+    fn divides_by_zero(op: &BinaryOp, lhs: &QirValue, rhs: &QirValue) -> bool {
+        let zero = match rhs {
+            QirValue::Int(r) => *r == 0,
+            QirValue::Float(r) => *r == 0.0,
+            _ => false,
+        };
+        *op == BinaryOp::Div && zero && matches!(lhs, QirValue::Int(_) | QirValue::Float(_))
+    }
+    
+    fn fold_float_binary(op: &BinaryOp, lhs: &QirValue, rhs: &QirValue) -> Option<f64> {
+        let (l, r) = match (lhs, rhs) {
+            (QirValue::Float(l), QirValue::Float(r)) => (*l, *r),
+            (QirValue::Float(l), QirValue::Int(r)) => (*l, *r as f64),
+            (QirValue::Int(l), QirValue::Float(r)) => (*l as f64, *r),
+            _ => return None,
+        };
+        
+        match op {
+            BinaryOp::Add => Some(l + r),
+            BinaryOp::Sub => Some(l - r),
+            BinaryOp::Mul => Some(l * r),
+            BinaryOp::Div if r != 0.0 => Some(l / r),
+            _ => None,
+        }
+    }
+    
+    /// Folds integer arithmetic and int/float/bool comparisons on constants.
+    fn fold_classical_binary(op: &BinaryOp, lhs: &QirValue, rhs: &QirValue) -> Option<QirValue> {
+        match (lhs, rhs) {
+            (QirValue::Int(l), QirValue::Int(r)) => {
+                let (l, r) = (*l, *r);
+                match op {
+                    BinaryOp::Add => l.checked_add(r).map(QirValue::Int),
+                    BinaryOp::Sub => l.checked_sub(r).map(QirValue::Int),
+                    BinaryOp::Mul => l.checked_mul(r).map(QirValue::Int),
+                    BinaryOp::Div => l.checked_div(r).map(QirValue::Int),
+                    BinaryOp::Xor => Some(QirValue::Int(l ^ r)),
+                    BinaryOp::Eq => Some(QirValue::Bool(l == r)),
+                    BinaryOp::Neq => Some(QirValue::Bool(l != r)),
+                    BinaryOp::Lt => Some(QirValue::Bool(l < r)),
+                    BinaryOp::Gt => Some(QirValue::Bool(l > r)),
+                    BinaryOp::Le => Some(QirValue::Bool(l <= r)),
+                    BinaryOp::Ge => Some(QirValue::Bool(l >= r)),
+                    _ => None,
+                }
+            }
+            (QirValue::Bool(l), QirValue::Bool(r)) => match op {
+                BinaryOp::And => Some(QirValue::Bool(*l && *r)),
+                BinaryOp::Or => Some(QirValue::Bool(*l || *r)),
+                BinaryOp::Xor | BinaryOp::Neq => Some(QirValue::Bool(l != r)),
+                BinaryOp::Eq => Some(QirValue::Bool(l == r)),
+                _ => None,
+            },
+            (QirValue::Int(_) | QirValue::Float(_), QirValue::Int(_) | QirValue::Float(_)) => {
+                let as_float = |value: &QirValue| match value {
+                    QirValue::Int(v) => *v as f64,
+                    QirValue::Float(v) => *v,
+                    _ => unreachable!(),
+                };
+                let (l, r) = (as_float(lhs), as_float(rhs));
+                match op {
+                    BinaryOp::Eq => Some(QirValue::Bool(l == r)),
+                    BinaryOp::Neq => Some(QirValue::Bool(l != r)),
+                    BinaryOp::Lt => Some(QirValue::Bool(l < r)),
+                    BinaryOp::Gt => Some(QirValue::Bool(l > r)),
+                    BinaryOp::Le => Some(QirValue::Bool(l <= r)),
+                    BinaryOp::Ge => Some(QirValue::Bool(l >= r)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+    
+    // True if `expr` reads a `mut` binding, whose recorded value may be stale
+    fn reads_mutable(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Variable(name, _) => self.mutable_vars.contains(name),
+            Expr::BinaryOp(lhs, _, rhs, _) | Expr::Index(lhs, rhs, _) => {
+                self.reads_mutable(lhs) || self.reads_mutable(rhs)
+            }
+            Expr::UnaryOp(_, operand, _) | Expr::MemberAccess(operand, _, _) => self.reads_mutable(operand),
+            Expr::Call(_, args, _) | Expr::Tuple(args, _) => args.iter().any(|arg| self.reads_mutable(arg)),
+            _ => false,
+        }
+    }
+    
     fn build_unary_expr(&mut self, op: &UnaryOp, operand: &Expr, qir_func: &mut QirFunction) -> QirValue {
         let operand_val = self.build_expr_value(operand, qir_func);
+        
+        if !self.reads_mutable(operand) {
+            match (op, &operand_val) {
+                (UnaryOp::Neg, QirValue::Int(v)) if *v != i64::MIN => return QirValue::Int(-v),
+                (UnaryOp::Neg, QirValue::Float(v)) => return QirValue::Float(-v),
+                (UnaryOp::Not, QirValue::Bool(v)) => return QirValue::Bool(!v),
+                _ => {}
+            }
+        }
+        
         let result_temp = TempId::new(self.temp_counter);
         self.temp_counter += 1;
         
@@ -296,7 +710,7 @@ impl QirBuilder {
         QirValue::Temp(result_temp)
     }
     
-    fn build_call_expr(&mut self, name: &str, args: &[Expr], qir_func: &mut QirFunction) -> QirValue {
+    fn build_call_expr(&mut self, name: &str, args: &[Expr], line: usize, qir_func: &mut QirFunction) -> QirValue {
         match name {
             "range" => {
                 if args.len() >= 2 {
@@ -314,23 +728,158 @@ impl QirBuilder {
                     QirValue::Null
                 }
             }
+            // Checked by the semantic analyzer; nothing to emit
+            "assert" if !self.functions.contains_key(name) => QirValue::Null,
+            "measure_x" | "measure_y" if !self.functions.contains_key(name) => {
+                // Rotate the measured basis onto Z: H for X, S†·H for Y
+                let basis_change = if name == "measure_x" {
+                    vec![QirGate::H]
+                } else {
+                    vec![QirGate::Sdg, QirGate::H]
+                };
+                match args.first() {
+                    Some(arg) => self.build_basis_measure_expr(&basis_change, arg, qir_func),
+                    None => QirValue::Null,
+                }
+            }
+            "sqrt" | "sin" | "cos" => {
+                let arg = match args.first().map(|a| self.build_expr_value(a, qir_func)) {
+                    Some(QirValue::Int(v)) => v as f64,
+                    Some(QirValue::Float(v)) => v,
+                    _ => return QirValue::Null,
+                };
+                match name {
+                    "sqrt" => QirValue::Float(arg.sqrt()),
+                    "sin" => QirValue::Float(arg.sin()),
+                    _ => QirValue::Float(arg.cos()),
+                }
+            }
+            _ => match self.functions.get(name).cloned() {
+                Some(callee) => self.build_inline_call(&callee, args, line, qir_func),
+                None => QirValue::Null,
+            },
+        }
+    }
+    
+    /// Emits the body of `callee` into the caller with its parameters bound
+    /// to the argument values, and returns the value it returns.
+    fn build_inline_call(&mut self, callee: &Function, args: &[Expr], line: usize, qir_func: &mut QirFunction) -> QirValue {
+        let recursive = self.current_function.as_deref() == Some(callee.name.as_str())
+            || self.inline_stack.contains(&callee.name);
+        if recursive {
+            let message = format!(
+                "Recursive call to '{}' at line {} cannot be inlined",
+                callee.name, line
+            );
+            if !self.errors.contains(&message) {
+                self.errors.push(message);
+            }
+            return QirValue::Null;
+        }
+        
+        let mut bindings = Bindings::new();
+        let mut mutable_params = HashSet::new();
+        for (param, arg) in callee.params.iter().zip(args) {
+            let value = self.build_expr_value(arg, qir_func);
+            bindings.extend(self.field_bindings(&param.name, &param.ty, &value));
+            bindings.insert(param.name.clone(), (self.convert_type(&param.ty), value));
+            if param.mutable {
+                mutable_params.insert(param.name.clone());
+            }
+        }
+        
+        // The callee only sees its own parameters and locals
+        let caller_symbols = std::mem::replace(&mut self.symbol_table, bindings);
+        let caller_mutable = std::mem::replace(&mut self.mutable_vars, mutable_params);
+        let caller_return = self.inline_return.take();
+        self.inline_stack.push(callee.name.clone());
+        
+        for stmt in &callee.body {
+            self.build_statement(stmt, qir_func);
+            if self.inline_return.is_some() {
+                break;
+            }
+        }
+        
+        self.inline_stack.pop();
+        let value = std::mem::replace(&mut self.inline_return, caller_return);
+        self.symbol_table = caller_symbols;
+        self.mutable_vars = caller_mutable;
+        value.unwrap_or(QirValue::Null)
+    }
+    
+    fn build_reset_expr(&mut self, qubit_expr: &Expr, qir_func: &mut QirFunction) -> QirValue {
+        // Resetting a register resets every qubit in it
+        for qubit in self.build_qubit_operands(qubit_expr, qir_func) {
+            qir_func.add_op(QirOp::Reset { qubit });
+        }
+        
+        QirValue::Null
+    }
+    
+    fn build_barrier_expr(&mut self, args: &[Expr], qir_func: &mut QirFunction) -> QirValue {
+        let mut qubits = Vec::new();
+        for arg in args {
+            for qubit in self.build_qubit_operands(arg, qir_func) {
+                if !qubits.contains(&qubit) {
+                    qubits.push(qubit);
+                }
+            }
+        }
+        
+        if !qubits.is_empty() {
+            qir_func.add_op(QirOp::Barrier { qubits });
+        }
+        
+        QirValue::Null
+    }
+    
+    // Resolves a qubit or register operand to the qubits it names
+    fn build_qubit_operands(&mut self, expr: &Expr, qir_func: &mut QirFunction) -> Vec<QubitId> {
+        let value = match self.build_expr_value(expr, qir_func) {
+            QirValue::Null => match expr {
+                Expr::Index(array_expr, index_expr, _) => self.build_index_expr(array_expr, index_expr, qir_func),
+                _ => QirValue::Null,
+            },
+            value => value,
+        };
+        
+        match &value {
+            QirValue::Qubit(qubit_id) => vec![*qubit_id],
+            QirValue::Array(elements) => elements.iter()
+                .filter_map(|element| match element {
+                    QirValue::Qubit(qubit_id) => Some(*qubit_id),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    
+    /// `measure(q, c[i])` writes the outcome to an existing cbit instead of a fresh one.
+    fn build_measure_into_expr(&mut self, qubit_expr: &Expr, target_expr: &Expr, qir_func: &mut QirFunction) -> QirValue {
+        let qubits = self.build_qubit_operands(qubit_expr, qir_func);
+        let target = match self.build_expr_value(target_expr, qir_func) {
+            QirValue::Null => match target_expr {
+                Expr::Index(array_expr, index_expr, _) => self.build_index_expr(array_expr, index_expr, qir_func),
+                _ => QirValue::Null,
+            },
+            value => value,
+        };
+        
+        match (qubits.as_slice(), target) {
+            (&[qubit], QirValue::Cbit(cbit)) => {
+                qir_func.add_op(QirOp::Measure { qubit, cbit });
+                QirValue::Cbit(cbit)
+            }
             _ => QirValue::Null,
         }
     }
     
     fn build_measure_expr(&mut self, qubit_expr: &Expr, qir_func: &mut QirFunction) -> QirValue {
         let value = self.build_expr_value(qubit_expr, qir_func);
-
-        if let QirValue::Qubit(qubit_id) = value {
-            let cbit_id = CbitId::new(self.cbit_counter);
-            self.cbit_counter += 1;
-            
-            qir_func.add_op(QirOp::Measure {
-                qubit: qubit_id,
-                cbit: cbit_id,
-            });
-            
-            return QirValue::Cbit(cbit_id);
+        if let Some(result) = self.measure_value(&value, qir_func) {
+            return result;
         }
 
         // Fallback for manual array resolution
@@ -371,6 +920,74 @@ impl QirBuilder {
         QirValue::Null
     }
     
+    /// Measures a qubit, or each qubit of a register, into fresh cbits.
+    /// Returns `None` when `value` isn't a resolved qubit or register.
+    fn measure_value(&mut self, value: &QirValue, qir_func: &mut QirFunction) -> Option<QirValue> {
+        if let QirValue::Qubit(qubit_id) = value {
+            let cbit_id = CbitId::new(self.cbit_counter);
+            self.cbit_counter += 1;
+            
+            qir_func.add_op(QirOp::Measure {
+                qubit: *qubit_id,
+                cbit: cbit_id,
+            });
+            
+            return Some(QirValue::Cbit(cbit_id));
+        }
+        
+        // Whole-register measurement: one cbit per qubit, in register order
+        if let QirValue::Array(elements) = value {
+            let mut cbit_values = Vec::new();
+            for element in elements {
+                if let QirValue::Qubit(qubit_id) = element {
+                    let cbit_id = CbitId::new(self.cbit_counter);
+                    self.cbit_counter += 1;
+                    
+                    qir_func.add_op(QirOp::Measure {
+                        qubit: *qubit_id,
+                        cbit: cbit_id,
+                    });
+                    
+                    cbit_values.push(QirValue::Cbit(cbit_id));
+                }
+            }
+            return Some(QirValue::Array(cbit_values));
+        }
+        
+        None
+    }
+    
+    /// `measure_x`/`measure_y`: applies `basis_change` to each measured
+    /// qubit, then measures in the computational basis.
+    fn build_basis_measure_expr(&mut self, basis_change: &[QirGate], qubit_expr: &Expr, qir_func: &mut QirFunction) -> QirValue {
+        let value = self.build_expr_value(qubit_expr, qir_func);
+        let qubits: Vec<QubitId> = match &value {
+            QirValue::Qubit(qubit_id) => vec![*qubit_id],
+            QirValue::Array(elements) => elements.iter()
+                .filter_map(|element| match element {
+                    QirValue::Qubit(qubit_id) => Some(*qubit_id),
+                    _ => None,
+                })
+                .collect(),
+            _ => return QirValue::Null,
+        };
+        
+        for qubit_id in qubits {
+            for gate in basis_change {
+                let result_temp = TempId::new(self.temp_counter);
+                self.temp_counter += 1;
+                
+                qir_func.add_op(QirOp::ApplyGate {
+                    gate: gate.clone(),
+                    args: vec![QirValue::Qubit(qubit_id)],
+                    result: Some(result_temp),
+                });
+            }
+        }
+        
+        self.measure_value(&value, qir_func).unwrap_or(QirValue::Null)
+    }
+    
     fn build_gate_apply_expr(&mut self, gate: &AstGate, args: &[Expr], qir_func: &mut QirFunction) -> QirValue {
         let mut arg_values = Vec::new();
         let mut first_qubit = None;
@@ -395,7 +1012,28 @@ impl QirBuilder {
             arg_values.push(actual_value);
         }
         
-        if let Some(qir_gate) = QirGate::from_ast_gate(gate) {
+        // A register operand broadcasts the gate over its qubits, pairing
+        // elements when several operands are registers
+        let broadcast = arg_values.iter().find_map(|value| match value {
+            QirValue::Array(elements) => Some(elements.len()),
+            _ => None,
+        });
+        if let (Some(width), Some(qir_gate)) = (broadcast, self.convert_gate(gate)) {
+            for k in 0..width {
+                let args = arg_values.iter()
+                    .map(|value| match value {
+                        QirValue::Array(elements) => elements.get(k).cloned().unwrap_or(QirValue::Null),
+                        other => other.clone(),
+                    })
+                    .collect();
+                let result_temp = TempId::new(self.temp_counter);
+                self.temp_counter += 1;
+                qir_func.add_op(QirOp::ApplyGate { gate: qir_gate.clone(), args, result: Some(result_temp) });
+            }
+            return arg_values.swap_remove(0);
+        }
+        
+        if let Some(qir_gate) = self.convert_gate(gate) {
             let result_temp = TempId::new(self.temp_counter);
             self.temp_counter += 1;
             
@@ -413,9 +1051,44 @@ impl QirBuilder {
         QirValue::Null
     }
     
+    fn convert_gate(&self, gate: &AstGate) -> Option<QirGate> {
+        // Rotation angles may refer to constants bound in the symbol table,
+        // or to elements of classical arrays spelled `name[i]`
+        let lookup = |name: &str| {
+            let value = match name.split_once('[') {
+                Some((array, index)) => match self.symbol_table.get(array) {
+                    Some((_, QirValue::Array(elements))) => index.trim_end_matches(']').parse::<usize>().ok()
+                        .and_then(|index| elements.get(index)),
+                    _ => None,
+                },
+                None => self.symbol_table.get(name).map(|(_, value)| value),
+            };
+            match value {
+                Some(QirValue::Int(v)) => Some(*v as f64),
+                Some(QirValue::Float(v)) => Some(*v),
+                _ => None,
+            }
+        };
+        
+        match gate {
+            AstGate::RX(expr) => Some(QirGate::RX(QirAngle::from_expr(expr, &lookup))),
+            AstGate::RY(expr) => Some(QirGate::RY(QirAngle::from_expr(expr, &lookup))),
+            AstGate::RZ(expr) => Some(QirGate::RZ(QirAngle::from_expr(expr, &lookup))),
+            AstGate::CRX(expr) => Some(QirGate::CRX(QirAngle::from_expr(expr, &lookup))),
+            AstGate::CRY(expr) => Some(QirGate::CRY(QirAngle::from_expr(expr, &lookup))),
+            AstGate::CRZ(expr) => Some(QirGate::CRZ(QirAngle::from_expr(expr, &lookup))),
+            _ => QirGate::from_ast_gate(gate),
+        }
+    }
+    
     fn build_index_expr(&mut self, array_expr: &Expr, index_expr: &Expr, qir_func: &mut QirFunction) -> QirValue {
         let array_val = self.build_expr_value(array_expr, qir_func);
-        let index_val = self.build_expr_value(index_expr, qir_func);
+        // Resolve indices such as `i - 1` from the current values of loop and
+        // `mut` variables, so `results[i - 1]` finds its cbit
+        let index_val = match self.eval_const(index_expr) {
+            Some(index @ QirValue::Int(_)) => index,
+            _ => self.build_expr_value(index_expr, qir_func),
+        };
         
         if let (QirValue::Array(elements), QirValue::Int(index)) = (array_val.clone(), index_val.clone()) {
             let idx = index as usize;
@@ -438,8 +1111,81 @@ impl QirBuilder {
         QirValue::Null
     }
     
-    fn build_member_access_expr(&mut self, _base_expr: &Expr, _field: &str, _qir_func: &mut QirFunction) -> QirValue {
-        QirValue::Null
+    fn build_member_access_expr(&mut self, base_expr: &Expr, field: &str, qir_func: &mut QirFunction) -> QirValue {
+        let bound = Self::member_path(base_expr)
+            .and_then(|path| self.symbol_table.get(&format!("{}.{}", path, field)));
+        if let Some((_, value)) = bound {
+            return value.clone();
+        }
+        
+        // `.0`, `.1`, ... on any other tuple value, e.g. `make_pair().1`
+        match (self.build_expr_value(base_expr, qir_func), field.parse::<usize>()) {
+            (QirValue::Tuple(elements), Ok(index)) => elements.get(index).cloned().unwrap_or(QirValue::Null),
+            _ => QirValue::Null,
+        }
+    }
+    
+    /// `state.qubits` for a chain of member accesses on a variable.
+    fn member_path(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Variable(name, _) => Some(name.clone()),
+            Expr::MemberAccess(base, field, _) => Some(format!("{}.{}", Self::member_path(base)?, field)),
+            _ => None,
+        }
+    }
+    
+    /// A struct value is a tuple of its field values in declaration order.
+    /// Fields are still evaluated in the order they are written.
+    fn build_struct_literal(&mut self, name: &str, fields: &[(String, Expr)], qir_func: &mut QirFunction) -> QirValue {
+        let mut written = Vec::new();
+        for (field, expr) in fields {
+            let value = self.build_expr_value(expr, qir_func);
+            written.push((field.clone(), value));
+        }
+        
+        let Some(def) = self.struct_defs.get(name) else {
+            return QirValue::Tuple(written.into_iter().map(|(_, value)| value).collect());
+        };
+        QirValue::Tuple(def.fields.iter()
+            .map(|field| written.iter()
+                .find(|(name, _)| *name == field.name)
+                .map(|(_, value)| value.clone())
+                .unwrap_or(QirValue::Null))
+            .collect())
+    }
+    
+    /// Symbol-table entries `name.field` for a struct value of type `ty`, and
+    /// `name.0`, `name.1`, ... for a tuple, recursing into nested fields.
+    fn field_bindings(&self, name: &str, ty: &Type, value: &QirValue) -> Vec<(String, (QirType, QirValue))> {
+        let QirValue::Tuple(values) = value else {
+            return Vec::new();
+        };
+        let fields: Vec<(String, Type)> = match self.resolve_alias(ty) {
+            Type::Named(struct_name) => match self.struct_defs.get(struct_name) {
+                Some(def) => def.fields.iter().map(|f| (f.name.clone(), f.ty.clone())).collect(),
+                None => return Vec::new(),
+            },
+            Type::Tuple(types) => types.iter().cloned().enumerate().map(|(i, t)| (i.to_string(), t)).collect(),
+            _ => return Vec::new(),
+        };
+        
+        let mut entries = Vec::new();
+        for ((field, field_ty), field_value) in fields.iter().zip(values) {
+            let path = format!("{}.{}", name, field);
+            entries.extend(self.field_bindings(&path, field_ty, field_value));
+            entries.push((path, (self.convert_type(field_ty), field_value.clone())));
+        }
+        entries
+    }
+    
+    fn resolve_alias<'a>(&'a self, ty: &'a Type) -> &'a Type {
+        match ty {
+            Type::Named(name) => match self.type_aliases.get(name) {
+                Some(target) => self.resolve_alias(target),
+                None => ty,
+            },
+            _ => ty,
+        }
     }
     
     fn build_return_stmt(&mut self, expr: &Option<Expr>, qir_func: &mut QirFunction) {
@@ -447,6 +1193,12 @@ impl QirBuilder {
             .map(|e| self.build_expr_value(e, qir_func))
             .unwrap_or(QirValue::Null);
         
+        // Returning from an inlined call resumes the caller instead
+        if !self.inline_stack.is_empty() {
+            self.inline_return = Some(value);
+            return;
+        }
+        
         qir_func.add_op(QirOp::Return {
             value: if value == QirValue::Null { None } else { Some(value) },
         });
@@ -458,29 +1210,531 @@ impl QirBuilder {
         }
     }
     
-    fn build_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>, qir_func: &mut QirFunction) {
-        let _cond_val = self.build_expr_value(condition, qir_func);
-        self.build_statement(then_branch, qir_func);
+    fn build_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>, line: usize, qir_func: &mut QirFunction) {
+        // `if (c == 1)` on a measured cbit becomes classically controlled gates,
+        // unless the branches are purely classical, which a runtime `if` keeps
+        let classical = self.is_classical_stmt(then_branch)
+            && else_branch.is_none_or(|branch| self.is_classical_stmt(branch));
+        if let Some((cbit, value)) = self.cbit_condition(condition, qir_func).filter(|_| !classical) {
+            let errors = self.errors.len();
+            self.build_conditional_branch(cbit, value, then_branch, line, qir_func);
+            if let Some(else_branch) = else_branch.filter(|_| self.errors.len() == errors) {
+                self.build_conditional_branch(cbit, 1 - value, else_branch, line, qir_func);
+            }
+            return;
+        }
+        
+        let cond_val = self.build_expr_value(condition, qir_func);
+        
+        // A condition known at compile time only builds the branch it takes
+        let taken = match cond_val {
+            QirValue::Bool(value) => Some(value),
+            QirValue::Int(value) if !self.reads_mutable(condition) => Some(value != 0),
+            _ => None,
+        };
+        match taken {
+            Some(true) => self.build_statement(then_branch, qir_func),
+            Some(false) => {
+                if let Some(else_branch) = else_branch {
+                    self.build_statement(else_branch, qir_func);
+                }
+            }
+            None => self.build_runtime_if(cond_val, then_branch, else_branch, line, qir_func),
+        }
+    }
+    
+    /// Builds an `if` decided at runtime as then/else blocks that jump to a
+    /// merge block. `mut` classical variables that leave the two paths with
+    /// different values get a `Phi` in the merge block. Blocks are created in
+    /// source order so code generation still visits them in program order.
+    /// OpenQASM 2.0 cannot branch, so the branches may only compute classical
+    /// values; quantum operations in them are an error.
+    fn build_runtime_if(&mut self, cond: QirValue, then_branch: &Stmt, else_branch: Option<&Stmt>, line: usize, qir_func: &mut QirFunction) {
+        let entry_block = qir_func.current_block;
+        let before = self.symbol_table.clone();
+        let mutable_before = self.mutable_vars.clone();
+        let quantum_ops = Self::quantum_op_count(qir_func);
+        
+        self.branching += 1;
+        
+        let then_block = qir_func.create_block();
+        let then_exit = self.build_branch_block(then_block, then_branch, line, qir_func);
+        
+        self.symbol_table = before.clone();
+        self.mutable_vars = mutable_before.clone();
+        
+        let (else_block, else_exit) = match else_branch {
+            Some(else_branch) => {
+                let else_block = qir_func.create_block();
+                (Some(else_block), self.build_branch_block(else_block, else_branch, line, qir_func))
+            }
+            None => (None, Some((entry_block, before.clone()))),
+        };
+        
+        self.branching -= 1;
+        
+        if Self::quantum_op_count(qir_func) > quantum_ops && !self.building_template() {
+            self.errors.push(format!(
+                "Branch of the if at line {} applies quantum operations, but its condition is only known at runtime and OpenQASM 2.0 cannot branch",
+                line
+            ));
+        }
+        if (then_exit.is_none() || else_exit.is_none()) && self.conditional_return.is_none() {
+            self.conditional_return = Some((line, quantum_ops));
+        }
+        self.mutable_vars = mutable_before;
+        
+        let merge_block = qir_func.create_block();
+        qir_func.switch_to_block(entry_block);
+        qir_func.add_branch(cond, then_block, else_block.unwrap_or(merge_block));
+        for (exit_block, _) in [&then_exit, &else_exit].into_iter().flatten() {
+            if *exit_block != entry_block {
+                qir_func.switch_to_block(*exit_block);
+                qir_func.add_jump(merge_block);
+            }
+        }
+        qir_func.switch_to_block(merge_block);
+        
+        self.symbol_table = match (then_exit, else_exit) {
+            (Some(then_exit), Some(else_exit)) => self.merge_branch_values(before, then_exit, else_exit, qir_func),
+            (Some((_, symbols)), None) | (None, Some((_, symbols))) => symbols,
+            (None, None) => before,
+        };
+    }
+    
+    /// Builds `branch` starting in `block`. Returns the block control leaves
+    /// from together with the bindings at that point, or `None` if the branch
+    /// returned.
+    fn build_branch_block(&mut self, block: BlockId, branch: &Stmt, line: usize, qir_func: &mut QirFunction)
+        -> Option<(BlockId, Bindings)> {
+        qir_func.switch_to_block(block);
+        self.build_statement(branch, qir_func);
+        
+        // The inlined callee would stop here whichever way the `if` went
+        if self.inline_return.take().is_some() {
+            self.errors.push(format!(
+                "Branch of the if at line {} returns from an inlined function, but its condition is only known at runtime",
+                line
+            ));
+        }
+        
+        if qir_func.get_current_block_mut().is_terminated() {
+            None
+        } else {
+            Some((qir_func.current_block, self.symbol_table.clone()))
+        }
+    }
+    
+    /// Bindings after both paths of an `if` meet. Variables declared inside a
+    /// branch go out of scope; classical `mut` variables whose values differ
+    /// are bound to a `Phi` of the two incoming values.
+    fn merge_branch_values(
+        &mut self,
+        before: Bindings,
+        (then_block, then_symbols): (BlockId, Bindings),
+        (else_block, else_symbols): (BlockId, Bindings),
+        qir_func: &mut QirFunction,
+    ) -> Bindings {
+        let mut names: Vec<&String> = before.keys().collect();
+        names.sort();
+        
+        let mut merged = HashMap::new();
+        for name in names {
+            let (ty, then_value) = &then_symbols[name];
+            let else_value = &else_symbols[name].1;
+            
+            let value = if then_value == else_value {
+                then_value.clone()
+            } else if self.mutable_vars.contains(name)
+                && matches!(ty, QirType::Int | QirType::Float | QirType::Bool) {
+                let result = TempId::new(self.temp_counter);
+                self.temp_counter += 1;
+                qir_func.add_op(QirOp::Phi {
+                    incoming: vec![(then_block, then_value.clone()), (else_block, else_value.clone())],
+                    result,
+                });
+                QirValue::Temp(result)
+            } else {
+                else_value.clone()
+            };
+            merged.insert(name.clone(), (ty.clone(), value));
+        }
+        merged
+    }
+    
+    /// Matches `cbit == 0|1` / `cbit != 0|1` (either operand order) and returns
+    /// the cbit together with the value that makes the condition true.
+    fn cbit_condition(&mut self, condition: &Expr, qir_func: &mut QirFunction) -> Option<(CbitId, i64)> {
+        let (lhs, op, rhs) = match condition {
+            Expr::BinaryOp(lhs, op @ (BinaryOp::Eq | BinaryOp::Neq), rhs, _) => (lhs, op, rhs),
+            _ => return None,
+        };
+        
+        // Only plain lookups, so probing the operands never emits ops
+        let is_lookup = |expr: &Expr| matches!(expr, Expr::Variable(..) | Expr::Index(..) | Expr::MemberAccess(..) | Expr::LiteralInt(..));
+        if !is_lookup(lhs) || !is_lookup(rhs) {
+            return None;
+        }
+        
+        let (cbit, value) = match (self.build_expr_value(lhs, qir_func), self.build_expr_value(rhs, qir_func)) {
+            (QirValue::Cbit(cbit), QirValue::Int(value)) | (QirValue::Int(value), QirValue::Cbit(cbit)) => (cbit, value),
+            _ => return None,
+        };
+        if value != 0 && value != 1 {
+            return None;
+        }
+        
+        Some((cbit, if *op == BinaryOp::Neq { 1 - value } else { value }))
+    }
+    
+    /// Whether `stmt` neither touches qubits nor calls a user function, so it
+    /// only computes classical values.
+    fn is_classical_stmt(&self, stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Expr(expr, _) | Stmt::Let(_, _, expr, _, _) | Stmt::Assign(_, expr, _) => self.is_classical_expr(expr),
+            Stmt::AssignIndex(_, index, expr, _) => self.is_classical_expr(index) && self.is_classical_expr(expr),
+            Stmt::Block(stmts, _) => stmts.iter().all(|stmt| self.is_classical_stmt(stmt)),
+            Stmt::If(condition, then_branch, else_branch, _) => {
+                self.is_classical_expr(condition)
+                    && self.is_classical_stmt(then_branch)
+                    && else_branch.as_deref().is_none_or(|branch| self.is_classical_stmt(branch))
+            }
+            Stmt::While(condition, body, _) => self.is_classical_expr(condition) && self.is_classical_stmt(body),
+            Stmt::ForRange(_, start, end, step, body, _) => {
+                self.is_classical_expr(start)
+                    && self.is_classical_expr(end)
+                    && step.as_deref().is_none_or(|step| self.is_classical_expr(step))
+                    && self.is_classical_stmt(body)
+            }
+            Stmt::Return(expr, _) => expr.as_ref().is_none_or(|expr| self.is_classical_expr(expr)),
+            Stmt::Break(_) | Stmt::Continue(_) | Stmt::TypeAlias(..) | Stmt::StructDef(..) => true,
+            Stmt::QIf(..) | Stmt::QForRange(..) => false,
+        }
+    }
+    
+    fn is_classical_expr(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::LiteralInt(..) | Expr::LiteralFloat(..) | Expr::LiteralBool(..)
+            | Expr::LiteralString(..) | Expr::Variable(..) => true,
+            Expr::BinaryOp(lhs, _, rhs, _) | Expr::Index(lhs, rhs, _) => {
+                self.is_classical_expr(lhs) && self.is_classical_expr(rhs)
+            }
+            Expr::UnaryOp(_, operand, _) | Expr::MemberAccess(operand, _, _) => self.is_classical_expr(operand),
+            Expr::Call(name, args, _) => {
+                matches!(name.as_str(), "sqrt" | "sin" | "cos" | "assert" | "range")
+                    && !self.functions.contains_key(name)
+                    && args.iter().all(|arg| self.is_classical_expr(arg))
+            }
+            Expr::Tuple(elements, _) => elements.iter().all(|element| self.is_classical_expr(element)),
+            Expr::StructLiteral(_, fields, _) => fields.iter().all(|(_, value)| self.is_classical_expr(value)),
+            Expr::LiteralQubit(..) | Expr::Measure(..) | Expr::MeasureInto(..) | Expr::Reset(..)
+            | Expr::Barrier(..) | Expr::GateApply(..) => false,
+        }
+    }
+    
+    /// Builds `branch` and turns its gates into conditional gates. OpenQASM 2.0
+    /// can only make single gates conditional, so a branch that does anything
+    /// else, such as measuring, resetting or assigning a variable, is an error.
+    fn build_conditional_branch(&mut self, cbit: CbitId, value: i64, branch: &Stmt, line: usize, qir_func: &mut QirFunction) {
+        let block_id = qir_func.current_block;
+        let start = qir_func.get_current_block_mut().ops.len();
+        let before = self.symbol_table.clone();
+        
+        // Track assignments so they can be reported below
+        self.branching += 1;
+        self.build_statement(branch, qir_func);
+        self.branching -= 1;
+        
+        let unsupported = |what: &str| format!(
+            "Branch of the if on a measured cbit at line {} {}, but OpenQASM 2.0 can only make gates conditional",
+            line, what
+        );
+        
+        let mut assigned: Vec<&String> = before.iter()
+            .filter(|(name, entry)| self.mutable_vars.contains(*name) && self.symbol_table.get(*name) != Some(*entry))
+            .map(|(name, _)| name)
+            .collect();
+        assigned.sort();
+        if let Some(name) = assigned.first() {
+            self.errors.push(unsupported(&format!("assigns '{}'", name)));
+            self.symbol_table = before;
+            return;
+        }
+        if qir_func.current_block != block_id {
+            self.errors.push(unsupported("branches at runtime"));
+            return;
+        }
+        if self.inline_return.is_some() {
+            self.errors.push(unsupported("returns from an inlined function"));
+            return;
+        }
+        
+        let block = qir_func.get_current_block_mut();
+        let last = block.ops.len().saturating_sub(1);
+        let mut returns = false;
+        let mut conditional = Vec::new();
+        for (index, op) in block.ops.iter().enumerate().skip(start) {
+            let what = match op {
+                QirOp::ApplyGate { gate, args, .. } => {
+                    let qubits: Vec<QubitId> = args.iter()
+                        .filter_map(|arg| match arg {
+                            QirValue::Qubit(qubit_id) => Some(*qubit_id),
+                            _ => None,
+                        })
+                        .collect();
+                    if !qubits.is_empty() && qubits.len() == args.len() {
+                        conditional.push(QirOp::ConditionalGate { cbit, value, gate: gate.clone(), qubits });
+                        continue;
+                    }
+                    "applies a gate to a qubit not known at compile time"
+                }
+                // A nested `if`/`else if` on the same cbit either agrees with
+                // this branch or can never run inside it
+                QirOp::ConditionalGate { cbit: inner, value: inner_value, .. } if *inner == cbit => {
+                    if *inner_value == value {
+                        conditional.push(op.clone());
+                    }
+                    continue;
+                }
+                QirOp::ConditionalGate { .. } => {
+                    self.errors.push(format!(
+                        "Condition at line {} depends on more than one measured cbit, which OpenQASM 2.0 cannot express",
+                        line
+                    ));
+                    return;
+                }
+                QirOp::Comment(_) => {
+                    conditional.push(op.clone());
+                    continue;
+                }
+                // The return value is not part of the circuit, so a final
+                // `return` only has to be the last thing the function does
+                QirOp::Return { .. } if index == last => {
+                    returns = true;
+                    continue;
+                }
+                QirOp::Measure { .. } => "measures a qubit",
+                QirOp::Reset { .. } => "resets a qubit",
+                QirOp::Barrier { .. } => "places a barrier",
+                QirOp::AllocQubit { .. } | QirOp::AllocCbit { .. } => "allocates a variable",
+                QirOp::Return { .. } => "returns",
+                _ => "computes a classical value",
+            };
+            self.errors.push(unsupported(what));
+            return;
+        }
+        
+        block.ops.truncate(start);
+        block.ops.extend(conditional);
+        
+        if returns && self.conditional_return.is_none() {
+            self.conditional_return = Some((line, Self::quantum_op_count(qir_func)));
+        }
+    }
+    
+    fn building_template(&self) -> bool {
+        self.template && self.inline_stack.is_empty()
+    }
+    
+    fn quantum_op_count(qir_func: &QirFunction) -> usize {
+        qir_func.blocks.values()
+            .flat_map(|block| &block.ops)
+            .filter(|op| matches!(op,
+                QirOp::ApplyGate { .. } | QirOp::ConditionalGate { .. } | QirOp::Measure { .. }
+                | QirOp::Reset { .. } | QirOp::Barrier { .. }))
+            .count()
+    }
+    
+    /// `qif q { ... } qelse { ... }` applies the then-branch gates controlled
+    /// on `q`, and the else-branch gates controlled on `q` being |0> by
+    /// wrapping them in `X(q)`.
+    fn build_qif_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>, line: usize, qir_func: &mut QirFunction) {
+        // A parameter is only resolved to a qubit where the function is inlined
+        let QirValue::Qubit(control) = self.build_expr_value(condition, qir_func) else {
+            return;
+        };
+        
+        self.build_controlled_branch(control, then_branch, line, qir_func);
         if let Some(else_branch) = else_branch {
-            self.build_statement(else_branch, qir_func);
+            let flip = QirOp::ApplyGate {
+                gate: QirGate::X,
+                args: vec![QirValue::Qubit(control)],
+                result: None,
+            };
+            qir_func.add_op(flip.clone());
+            self.build_controlled_branch(control, else_branch, line, qir_func);
+            qir_func.add_op(flip);
+        }
+    }
+    
+    fn build_controlled_branch(&mut self, control: QubitId, branch: &Stmt, line: usize, qir_func: &mut QirFunction) {
+        let block_id = qir_func.current_block;
+        let start = qir_func.get_current_block_mut().ops.len();
+        
+        self.build_statement(branch, qir_func);
+        
+        if qir_func.current_block != block_id {
+            self.errors.push(format!(
+                "qif at line {} can only contain gate applications",
+                line
+            ));
+            return;
+        }
+        
+        let block = qir_func.get_current_block_mut();
+        let mut controlled = Vec::new();
+        for op in &block.ops[start..] {
+            let QirOp::ApplyGate { gate, args, .. } = op else {
+                self.errors.push(format!(
+                    "qif at line {} can only contain gate applications",
+                    line
+                ));
+                return;
+            };
+            let mut targets = Vec::new();
+            for arg in args {
+                match arg {
+                    QirValue::Qubit(qubit_id) => targets.push(*qubit_id),
+                    // Unresolved parameters: emitted where the function is inlined
+                    _ => {
+                        block.ops.truncate(start);
+                        return;
+                    }
+                }
+            }
+            if targets.contains(&control) {
+                self.errors.push(format!(
+                    "qif at line {} applies a gate to its own control qubit",
+                    line
+                ));
+                return;
+            }
+            match Self::controlled_gates(gate, control, &targets) {
+                Some(gates) => {
+                    controlled.extend(gates.into_iter().map(|(gate, qubits)| QirOp::ApplyGate {
+                        gate,
+                        args: qubits.into_iter().map(QirValue::Qubit).collect(),
+                        result: None,
+                    }));
+                }
+                None => {
+                    self.errors.push(format!(
+                        "Gate '{}' inside qif at line {} has no controlled form",
+                        gate.base_name(), line
+                    ));
+                    return;
+                }
+            }
         }
+        
+        block.ops.truncate(start);
+        block.ops.extend(controlled);
     }
     
-    fn build_while_stmt(&mut self, condition: &Expr, body: &Stmt, qir_func: &mut QirFunction) {
-        let _cond_val = self.build_expr_value(condition, qir_func);
-        self.build_statement(body, qir_func);
+    /// The gates that apply `gate` to `targets` only when `control` is |1>.
+    /// `CNOT` and `SWAP` gain a second control as `ccx` and `cswap`; other
+    /// two-qubit gates, and `S`/`T` and their inverses, have no controlled
+    /// form in qelib1 and return `None`.
+    fn controlled_gates(gate: &QirGate, control: QubitId, targets: &[QubitId]) -> Option<Vec<(QirGate, Vec<QubitId>)>> {
+        let gates = match (gate, targets) {
+            (QirGate::X, &[target]) => vec![(QirGate::CNOT, vec![control, target])],
+            (QirGate::Y, &[target]) => vec![(QirGate::CY, vec![control, target])],
+            (QirGate::H, &[target]) => vec![(QirGate::CH, vec![control, target])],
+            // CZ = (I ⊗ H) CNOT (I ⊗ H)
+            (QirGate::Z, &[target]) => vec![
+                (QirGate::H, vec![target]),
+                (QirGate::CNOT, vec![control, target]),
+                (QirGate::H, vec![target]),
+            ],
+            (QirGate::RX(angle), &[target]) => vec![(QirGate::CRX(angle.clone()), vec![control, target])],
+            (QirGate::RY(angle), &[target]) => vec![(QirGate::CRY(angle.clone()), vec![control, target])],
+            (QirGate::RZ(angle), &[target]) => vec![(QirGate::CRZ(angle.clone()), vec![control, target])],
+            (QirGate::CNOT, &[inner, target]) => vec![(QirGate::Toffoli, vec![control, inner, target])],
+            (QirGate::SWAP, &[first, second]) => vec![(QirGate::Fredkin, vec![control, first, second])],
+            _ => return None,
+        };
+        Some(gates)
+    }
+    
+    /// OpenQASM 2.0 has no loops, so `while` is unrolled by re-evaluating the
+    /// condition after every pass over the body. The condition has to be a
+    /// compile-time constant on each iteration.
+    fn build_while_stmt(&mut self, condition: &Expr, body: &Stmt, line: usize, qir_func: &mut QirFunction) {
+        self.unrolling += 1;
+        
+        let mut iterations = 0;
+        loop {
+            match self.eval_const(condition) {
+                Some(QirValue::Bool(false)) | Some(QirValue::Int(0)) => break,
+                Some(QirValue::Bool(true)) | Some(QirValue::Int(_)) => {}
+                _ => {
+                    self.errors.push(format!(
+                        "while loop at line {} has a condition that cannot be statically bounded",
+                        line
+                    ));
+                    break;
+                }
+            }
+            if iterations == self.unroll_threshold {
+                self.errors.push(format!(
+                    "while loop at line {} exceeds the unroll threshold of {} iterations",
+                    line, self.unroll_threshold
+                ));
+                break;
+            }
+            
+            self.build_statement(body, qir_func);
+            iterations += 1;
+        }
+        
+        self.unrolling -= 1;
     }
     
     fn build_for_range_stmt(&mut self, var_name: &str, start: &Expr, end: &Expr, 
-                           _step: &Option<Box<Expr>>, body: &Stmt, qir_func: &mut QirFunction) {
+                           step: &Option<Box<Expr>>, body: &Stmt, qir_func: &mut QirFunction) {
         let start_val = self.build_expr_value(start, qir_func);
         let end_val = self.build_expr_value(end, qir_func);
+        let step_val = match step {
+            Some(step) => self.build_expr_value(step, qir_func),
+            None => QirValue::Int(1),
+        };
         
         if let (QirValue::Int(start_int), QirValue::Int(end_int)) = (start_val, end_val) {
-            for i in start_int..end_int {
+            let step_int = match step_val {
+                QirValue::Int(0) => {
+                    self.errors.push(format!("for loop at line {} has a step of zero", start.span().line));
+                    return;
+                }
+                QirValue::Int(step_int) => step_int,
+                _ => {
+                    self.errors.push(format!(
+                        "for loop at line {} needs a constant integer step",
+                        start.span().line
+                    ));
+                    return;
+                }
+            };
+            
+            // Ranges stepping away from `end` run zero times, like Python's `range`
+            let span = if step_int > 0 { end_int as i128 - start_int as i128 } else { start_int as i128 - end_int as i128 };
+            let iterations = if span > 0 { (span + step_int.unsigned_abs() as i128 - 1) / step_int.unsigned_abs() as i128 } else { 0 };
+            if iterations > self.unroll_threshold as i128 {
+                self.errors.push(format!(
+                    "for loop at line {} exceeds the unroll threshold of {} iterations",
+                    start.span().line, self.unroll_threshold
+                ));
+                return;
+            }
+            for n in 0..iterations as i64 {
+                let i = start_int + n * step_int;
                 self.symbol_table.insert(var_name.to_string(), (QirType::Int, QirValue::Int(i)));
                 self.build_statement(body, qir_func);
             }
+        } else if !self.building_template() {
+            self.errors.push(format!(
+                "for loop at line {} needs constant integer bounds",
+                start.span().line
+            ));
         }
     }
     
@@ -510,4 +1764,316 @@ impl QirBuilder {
             _ => QirType::Unit,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::qir::{QirGate, QirOp, QirValue};
+    use crate::{CompileOptions, Compiler};
+
+    fn compile(body: &str) -> Result<String, Vec<String>> {
+        let source = format!("fn main() -> int {{\n{}\n}}\n", body);
+        Compiler::compile_with_stats(&source, false).map(|result| result.qasm)
+    }
+
+    fn compile_errors(body: &str) -> String {
+        compile(body).err().unwrap_or_default().join("\n")
+    }
+
+    const MEASURED: &str = "qreg r[3] = |000>; let p: qubit = |0>; let a: cbit = measure(r[0]);";
+
+    #[test]
+    fn measured_cbit_branch_gates_are_conditional() {
+        let qasm = compile(&format!("{} if (a == 1) {{ X(r[2]); }} return 0;", MEASURED)).unwrap();
+        assert!(qasm.contains("if (c==1) x r[2];"), "{}", qasm);
+    }
+
+    #[test]
+    fn measured_cbit_branch_rejects_measurement() {
+        let errors = compile_errors(&format!("{} if (a == 1) {{ X(r[1]); let z: cbit = measure(p); }} return 0;", MEASURED));
+        assert!(errors.contains("measures a qubit"), "{}", errors);
+    }
+
+    #[test]
+    fn measured_cbit_branch_rejects_assignment() {
+        let errors = compile_errors(&format!("{} let mut x: int = 0; if (a == 1) {{ X(r[1]); x = 1; }} else {{ x = 2; }} return x;", MEASURED));
+        assert!(errors.contains("assigns 'x'"), "{}", errors);
+        assert_eq!(errors.lines().count(), 1, "{}", errors);
+    }
+
+    #[test]
+    fn classical_branches_on_a_measured_cbit_merge_with_phi() {
+        let source = "fn main() -> int {
+            let q: qubit = |0>; H(q); let c: cbit = measure(q);
+            let mut x: int = 0;
+            if (c == 1) { x = 1; } else { x = 2; }
+            return x;
+        }";
+        let module = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        let ops: Vec<&QirOp> = module.functions[0].blocks.values().flat_map(|block| &block.ops).collect();
+        assert!(ops.iter().any(|op| matches!(op, QirOp::Phi { incoming, .. } if incoming.len() == 2)), "{:?}", ops);
+        assert!(ops.iter().any(|op| matches!(op, QirOp::Return { value: Some(QirValue::Temp(_)) })));
+
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        assert!(!qasm.contains("// Block 1"), "{}", qasm);
+    }
+
+    #[test]
+    fn runtime_branches_reject_quantum_operations() {
+        let errors = compile_errors("let q: qubit = |0>; let mut x: int = 1; if (x == 1) { X(q); } else { Y(q); } return 0;");
+        assert!(errors.contains("condition is only known at runtime"), "{}", errors);
+
+        let errors = compile_errors(&format!("{} if (a == 1) {{ return 1; }} X(r[1]); return 0;", MEASURED));
+        assert!(errors.contains("only run when its branch does not return"), "{}", errors);
+    }
+
+    #[test]
+    fn qif_controls_gates_on_the_condition_qubit() {
+        let qasm = compile("qreg q[3] = |000>; H(q[0]); qif (q[0]) { X(q[1]); } return 0;").unwrap();
+        assert!(qasm.contains("cx q[0], q[1];"), "{}", qasm);
+
+        let qasm = compile("qreg q[3] = |000>; H(q[0]); qif (q[0]) { CNOT(q[1], q[2]); SWAP(q[1], q[2]); } return 0;").unwrap();
+        assert!(qasm.contains("ccx q[0], q[1], q[2];"), "{}", qasm);
+        assert!(qasm.contains("cswap q[0], q[1], q[2];"), "{}", qasm);
+    }
+
+    #[test]
+    fn qif_rejects_gates_without_a_controlled_form() {
+        let errors = compile_errors("qreg q[3] = |000>; qif (q[0]) { CH(q[1], q[2]); } return 0;");
+        assert!(errors.contains("has no controlled form"), "{}", errors);
+    }
+
+    #[test]
+    fn overflowing_loop_bound_is_a_diagnostic() {
+        let errors = compile_errors("let q: qubit = |0>; for i in range(0, 1000000 * 10000000000000) { X(q); } return 0;");
+        assert!(errors.contains("Integer overflow"), "{}", errors);
+    }
+
+    #[test]
+    fn constant_division_is_checked() {
+        let errors = compile_errors("let a: int = 5 / 0; return 0;");
+        assert!(errors.contains("Division by zero"), "{}", errors);
+
+        let errors = compile_errors("let b: int = -9223372036854775807 - 1; let c: int = b / -1; return 0;");
+        assert!(errors.contains("Integer overflow"), "{}", errors);
+    }
+
+    #[test]
+    fn for_loop_bounds_must_be_constant() {
+        let errors = compile_errors("let q: qubit = |0>; let m: cbit = measure(q); for i in range(0, m) { reset(q); } return 0;");
+        assert!(errors.contains("needs constant integer bounds"), "{}", errors);
+    }
+
+    #[test]
+    fn measured_cbit_branch_may_end_in_return() {
+        assert!(compile(&format!("{} if (a == 1) {{ X(r[1]); return 1; }} return 0;", MEASURED)).is_ok());
+
+        let errors = compile_errors(&format!("{} if (a == 1) {{ X(r[1]); return 1; }} X(r[2]); return 0;", MEASURED));
+        assert!(errors.contains("only run when its branch does not return"), "{}", errors);
+    }
+
+    #[test]
+    fn constant_conditions_drop_the_untaken_branch() {
+        let qasm = compile("let q: qubit = |0>; if (2 > 1) { X(q); } else { Y(q); } let c: cbit = measure(q); return 0;").unwrap();
+        assert!(qasm.contains("x q[0];"), "{}", qasm);
+        assert!(!qasm.contains("y q[0];"), "{}", qasm);
+    }
+
+    #[test]
+    fn bounded_while_loops_unroll() {
+        let qasm = compile("let q: qubit = |0>; let mut i: int = 0; while (i < 3) { X(q); i += 1; } let c: cbit = measure(q); return 0;").unwrap();
+        assert_eq!(qasm.matches("x q[0];").count(), 3, "{}", qasm);
+
+        let errors = compile_errors(&format!("{} while (a == 0) {{ X(p); }} return 0;", MEASURED));
+        assert!(errors.contains("has a condition that cannot be statically bounded"), "{}", errors);
+    }
+
+    #[test]
+    fn element_updates_in_loops_apply_gates() {
+        let source = "fn main() -> int { qreg qs[15] = |000000000000000>; \
+            for i in range(0, 15) { qs[i] = H(qs[i]); } let r: cbit[15] = measure(qs); return 0; }";
+        let result = Compiler::compile_with_stats(source, false).unwrap();
+        assert_eq!(result.stats.gates, 15);
+        // A gate on every element of a register is emitted as one broadcast statement
+        assert!(result.qasm.contains("h qs;"), "{}", result.qasm);
+    }
+
+    #[test]
+    fn helper_calls_are_inlined() {
+        let source = "fn entangle(a: qubit, b: qubit) -> int { H(a); CNOT(a, b); return 0; }
+            fn main() -> int {
+                let x: qubit = |0>; let y: qubit = |0>;
+                entangle(x, y);
+                let c: cbit = measure(x); let d: cbit = measure(y);
+                return 0;
+            }";
+        let module = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        let main = module.functions.iter().find(|f| f.name == "main").unwrap();
+        let gates: Vec<String> = main.blocks.values()
+            .flat_map(|block| &block.ops)
+            .filter_map(|op| match op {
+                QirOp::ApplyGate { gate, .. } => Some(gate.base_name()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(gates, ["h", "cx"]);
+
+        let errors = Compiler::compile_with_stats("fn again(a: qubit) -> int { H(a); again(a); return 0; }
+            fn main() -> int { let x: qubit = |0>; again(x); return 0; }", false).err().unwrap();
+        assert!(errors[0].contains("Function 'again' used before definition"), "{:?}", errors);
+    }
+
+    #[test]
+    fn qfor_loops_unroll_their_body() {
+        let source = "fn main() -> int {
+            qfor i in range(0, 3) { let q: qubit = |0>; H(q); let r: cbit = measure(q); }
+            return 0;
+        }";
+        let result = Compiler::compile_with_stats(source, false).unwrap();
+        assert_eq!((result.stats.gates, result.stats.measurements), (3, 3));
+        assert_eq!(result.qasm.matches("h q[").count(), 3, "{}", result.qasm);
+
+        let module = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        assert!(module.metadata.contains_key("qfor.main.2"), "{:?}", module.metadata);
+    }
+
+    #[test]
+    fn cbit_array_elements_read_the_measured_bit() {
+        let qasm = compile("qreg qs[2] = |00>; let p: qubit = |0>; H(qs[0]); let results: cbit[2] = measure(qs); \
+            for i in range(0, 2) { if (results[i] == 1) { X(p); } } return 0;").unwrap();
+        for line in ["measure qs[0] -> c0[0];", "measure qs[1] -> c1[0];", "if (c0==1) x q[0];", "if (c1==1) x q[0];"] {
+            assert!(qasm.contains(line), "missing `{}` in\n{}", line, qasm);
+        }
+    }
+
+    #[test]
+    fn descending_ranges_count_down() {
+        let qasm = compile("qreg r[4] = |0000>; for i in range(3, 0, -1) { X(r[i]); } return 0;").unwrap();
+        let gates: Vec<&str> = qasm.lines().map(str::trim).filter(|line| line.starts_with("x ")).collect();
+        assert_eq!(gates, ["x r[3];", "x r[2];", "x r[1];"], "{}", qasm);
+    }
+
+    #[test]
+    fn ranges_stepping_away_from_the_end_are_empty() {
+        let qasm = compile("qreg r[4] = |0000>; for i in range(0, 3, -1) { X(r[i]); } for j in range(3, 0) { H(r[j]); } return 0;").unwrap();
+        assert!(!qasm.lines().map(str::trim).any(|line| line.starts_with("x ") || line.starts_with("h ")), "{}", qasm);
+    }
+
+    #[test]
+    fn else_if_chain_takes_the_constant_true_middle_branch() {
+        let qasm = compile("qreg r[3] = |000>; let x: int = 2; \
+            if (x == 1) { X(r[0]); } else if (x == 2) { X(r[1]); } else { X(r[2]); } return 0;").unwrap();
+        let gates: Vec<&str> = qasm.lines().map(str::trim).filter(|line| line.starts_with("x ")).collect();
+        assert_eq!(gates, ["x r[1];"], "{}", qasm);
+    }
+
+    #[test]
+    fn else_if_chain_on_one_measured_cbit_keeps_each_condition() {
+        let qasm = compile(&format!("{} if (a == 1) {{ X(r[1]); }} else if (a == 0) {{ X(r[2]); }} else {{ Z(r[2]); }} return 0;", MEASURED)).unwrap();
+        assert!(qasm.contains("if (c==1) x r[1];"), "{}", qasm);
+        assert!(qasm.contains("if (c==0) x r[2];"), "{}", qasm);
+        assert!(!qasm.contains("z r[2]"), "{}", qasm);
+    }
+
+    #[test]
+    fn else_if_chain_on_two_measured_cbits_is_rejected() {
+        let errors = compile_errors(&format!("{} let b: cbit = measure(r[1]); \
+            if (a == 1) {{ X(r[2]); }} else if (b == 1) {{ Z(r[2]); }} return 0;", MEASURED));
+        assert!(errors.contains("more than one measured cbit"), "{}", errors);
+    }
+
+    #[test]
+    fn struct_fields_resolve_to_their_qubits_and_cbits() {
+        let source = "struct QuantumState { qubits: (qubit, qubit), measurement: cbit, };
+        fn main() -> int {
+            let a: qubit = |0>; let t: qubit = |0>;
+            let pair: (qubit, qubit) = (|0>, |1>);
+            X(a);
+            let state: QuantumState = QuantumState { qubits: pair, measurement: measure(a) };
+            H(state.qubits.0);
+            let m: cbit = measure(state.qubits.0);
+            let n: cbit = measure(state.qubits.1);
+            if (state.measurement == 1) { X(t); }
+            return 0;
+        }";
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        for line in ["h q[2];", "measure q[2] -> c[1];", "measure q[3] -> c[2];", "measure q[0] -> c0[0];", "if (c0==1) x q[1];"] {
+            assert!(qasm.contains(line), "missing `{}` in\n{}", line, qasm);
+        }
+    }
+
+    #[test]
+    fn tuple_members_of_a_returned_pair_are_measured() {
+        let source = "fn bell() -> (qubit, qubit) {
+            let a: qubit = |0>; let b: qubit = |0>;
+            H(a); CNOT(a, b);
+            return (a, b);
+        }
+        fn main() -> int {
+            let pair: (qubit, qubit) = bell();
+            let r0: cbit = measure(pair.0);
+            let r1: cbit = measure(pair.1);
+            return 0;
+        }";
+        let result = Compiler::compile_with_stats(source, false).unwrap();
+        assert_eq!(result.stats.measurements, 2);
+        for line in ["h q[0];", "cx q[0], q[1];", "measure q -> c;"] {
+            assert!(result.qasm.contains(line), "missing `{}` in\n{}", line, result.qasm);
+        }
+    }
+
+    #[test]
+    fn gates_on_a_whole_register_broadcast() {
+        let source = "fn main() -> int { qreg qs[3] = |000>; H(qs); let r: cbit[3] = measure(qs); return 0; }";
+        let module = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        let mut targets: Vec<usize> = module.functions[0].blocks.values()
+            .flat_map(|block| &block.ops)
+            .filter_map(|op| match op {
+                QirOp::ApplyGate { gate: QirGate::H, args, .. } => match args[..] {
+                    [QirValue::Qubit(qubit)] => Some(qubit.0),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        targets.sort();
+        assert_eq!(targets, [0, 1, 2]);
+
+        let qasm = Compiler::compile_with_stats(source, false).unwrap().qasm;
+        assert!(qasm.contains("h qs;"), "{}", qasm);
+    }
+
+    #[test]
+    fn indices_and_angles_see_the_current_value_of_mut_variables() {
+        let qasm = compile("qreg r[8] = |00000000>; let mut n: int = 2; n = n + 3; X(r[n]); RZ(n * pi / 4, r[0]); return 0;").unwrap();
+        assert!(qasm.contains("x r[5];"), "{}", qasm);
+        assert!(qasm.contains("rz(5*pi/4) r[0];"), "{}", qasm);
+    }
+
+    #[test]
+    fn mut_array_elements_hold_assigned_values() {
+        let qasm = compile("qreg r[8] = |00000000>; mut int counts[2]; counts[0] = 7; counts[1] = counts[0] - 5; \
+            X(r[counts[0]]); for i in range(0, counts[1]) { H(r[i]); } return 0;").unwrap();
+        let gates: Vec<&str> = qasm.lines().map(str::trim).filter(|line| line.starts_with("x ") || line.starts_with("h ")).collect();
+        assert_eq!(gates, ["x r[7];", "h r[0];", "h r[1];"], "{}", qasm);
+
+        let errors = compile_errors("int fixed[2]; fixed[0] = 1; return 0;");
+        assert!(errors.contains("Cannot assign to element of immutable array 'fixed'"), "{}", errors);
+    }
+
+    #[test]
+    fn increments_update_the_index_of_later_gates() {
+        let qasm = compile("let mut i: int = 0;\ni++;\ni++;\ni--;\ni++;\nqreg qs[3] = |000>;\nX(qs[i]);\nlet c: cbit = measure(qs[2]);\nreturn 0;").unwrap();
+        assert!(qasm.lines().map(str::trim).any(|line| line == "x qs[2];"), "{}", qasm);
+    }
+
+    #[test]
+    fn mut_variables_keep_runtime_values_assigned_in_straight_line_code() {
+        let body = "let q: qubit = |0>;\nlet r: qubit = |0>;\nlet p: qubit = |0>;\nH(q);\nH(r);\nlet a: cbit = measure(q);\nlet b: cbit = measure(r);\nlet mut k: cbit = a;\nk = b;\nif (k == 1) {\nX(p);\n}\nlet d: cbit = measure(p);\nreturn 0;";
+        let source = format!("fn main() -> int {{\n{}\n}}\n", body);
+        let module = Compiler::compile_to_qir(&source, &CompileOptions::default()).unwrap();
+        assert!(!format!("{:?}", module).contains("Null"), "{:?}", module);
+        let qasm = compile(body).unwrap();
+        assert!(qasm.lines().map(str::trim).any(|line| line == "if (c1==1) x q[2];"), "{}", qasm);
+    }
+}
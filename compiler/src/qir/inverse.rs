@@ -0,0 +1,84 @@
+// src/qir/inverse.rs - Adjoint (dagger) of straight-line quantum functions
+use super::*;
+
+/// Returns `func` run backwards with every gate replaced by its adjoint, so
+/// that applying it after `func` gives the identity (uncomputation).
+///
+/// Allocations and classical ops stay in front in their original order and
+/// the final `return` stays last; barriers are reversed along with the gates.
+/// Fails on control flow and on ops without an inverse: measurements,
+/// resets, conditional gates and custom gates.
+pub fn invert_function(func: &QirFunction) -> Result<QirFunction, QirError> {
+    let mut prelude = Vec::new();
+    let mut body = Vec::new();
+    let mut ret = None;
+    
+    for block_id in func.block_ids() {
+        for op in &func.blocks[&block_id].ops {
+            match op {
+                QirOp::ApplyGate { gate, args, result } => {
+                    let adjoint = gate.adjoint().ok_or_else(|| not_invertible(func, op))?;
+                    body.push(QirOp::ApplyGate { gate: adjoint, args: args.clone(), result: *result });
+                }
+                QirOp::Barrier { .. } => body.push(op.clone()),
+                QirOp::Return { .. } => ret = Some(op.clone()),
+                QirOp::Measure { .. } | QirOp::Reset { .. } | QirOp::ConditionalGate { .. } => {
+                    return Err(not_invertible(func, op));
+                }
+                QirOp::Jump { .. } | QirOp::Branch { .. } | QirOp::Phi { .. } => {
+                    return Err(QirError::Other(format!(
+                        "Cannot invert function '{}': it contains control flow ({})", func.name, op
+                    )));
+                }
+                _ => prelude.push(op.clone()),
+            }
+        }
+    }
+    
+    let mut inverted = func.clone();
+    for block in inverted.blocks.values_mut() {
+        block.ops.clear();
+    }
+    
+    let entry = inverted.blocks.get_mut(&func.entry_block)
+        .ok_or_else(|| QirError::Other(format!("Function '{}' has no entry block", func.name)))?;
+    entry.ops = prelude;
+    entry.ops.extend(body.into_iter().rev());
+    entry.ops.extend(ret);
+    
+    Ok(inverted)
+}
+
+fn not_invertible(func: &QirFunction, op: &QirOp) -> QirError {
+    QirError::Other(format!("Cannot invert function '{}': '{}' has no inverse", func.name, op))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, Compiler};
+
+    fn main_function(body: &str) -> QirFunction {
+        let source = format!("fn main() -> int {{ let q: qubit = |0>; {} return 0; }}", body);
+        Compiler::compile_to_qir(&source, &CompileOptions::default()).unwrap().functions.remove(0)
+    }
+
+    #[test]
+    fn gates_are_reversed_and_replaced_by_their_adjoints() {
+        let inverted = invert_function(&main_function("H(q); S(q);")).unwrap();
+        let gates: Vec<&QirGate> = inverted.blocks[&inverted.entry_block].ops.iter()
+            .filter_map(|op| match op {
+                QirOp::ApplyGate { gate, .. } => Some(gate),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(gates, [&QirGate::Sdg, &QirGate::H]);
+        assert!(matches!(inverted.blocks[&inverted.entry_block].ops.last(), Some(QirOp::Return { .. })));
+    }
+
+    #[test]
+    fn measurements_cannot_be_inverted() {
+        let error = invert_function(&main_function("H(q); let c: cbit = measure(q);")).unwrap_err();
+        assert!(error.to_string().contains("has no inverse"), "{}", error);
+    }
+}
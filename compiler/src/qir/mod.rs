@@ -4,18 +4,81 @@ pub mod operations;
 pub mod builder;
 pub mod optimizer;
 pub mod analysis;
+pub mod printer;
+pub mod inverse;
+pub mod routing;
+pub mod unitary;
 
 // Re-export public types
 pub use types::{
     QubitId, CbitId, BlockId, TempId, QirType, QirParam, 
     QirValue, BitState
 };
-pub use operations::{QirGate, QirOp};
+pub use operations::{QirGate, QirOp, QirAngle};
 pub use builder::QirBuilder;
-pub use optimizer::QirOptimizer;
+pub use optimizer::{QirOptimizer, InverseRules, QirSnapshots, OptimizationReport, PassReport};
 pub use analysis::QirAnalyzer;
+pub use inverse::invert_function;
+pub use routing::route_module;
 
+use crate::ast::Span;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// An error from building or verifying QIR. These have no source location;
+/// structural problems name the function and block instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QirError {
+    /// A temp is assigned by more than one op.
+    SsaViolation { function: String, block: BlockId, temp: TempId },
+    /// A non-empty block doesn't end with a jump, branch or return.
+    MissingTerminator { function: String, block: BlockId },
+    /// A temp is used but never defined; `block` is where it is first used.
+    UndefinedTemp { function: String, block: BlockId, temp: TempId },
+    /// No path from the entry block leads to this block; `span` is the
+    /// statement that created it, when known.
+    UnreachableBlock { function: String, block: BlockId, span: Option<Span> },
+    /// Any other failure, such as a builder error or an exceeded limit.
+    Other(String),
+}
+
+impl QirError {
+    pub fn new(message: &str) -> Self {
+        QirError::Other(message.to_string())
+    }
+    
+    /// Source location of the error, for the variants that have one.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            QirError::UnreachableBlock { span, .. } => span.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for QirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QirError::SsaViolation { function, block, temp } => write!(
+                f, "Temp {} redefined in block {} of function {} (SSA violation)",
+                temp.id(), block.id(), function
+            ),
+            QirError::MissingTerminator { function, block } => write!(
+                f, "Block {} in function {} doesn't end with a terminator",
+                block.id(), function
+            ),
+            QirError::UndefinedTemp { function, block, temp } => write!(
+                f, "Temp {} used before definition in block {} of function {}",
+                temp.id(), block.id(), function
+            ),
+            QirError::UnreachableBlock { function, block, .. } => write!(
+                f, "Unreachable block {} in function {}",
+                block.id(), function
+            ),
+            QirError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct QirModule {
@@ -39,6 +102,9 @@ pub struct QirFunction {
     pub next_qubit_id: usize,
     pub next_cbit_id: usize,
     pub next_temp_id: usize,
+    /// Source span of the statement that defined each temp. Keyed by temp
+    /// rather than op position so optimizer rewrites don't invalidate it.
+    pub temp_spans: HashMap<TempId, Span>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -49,6 +115,8 @@ pub struct QirBlock {
     pub successors: Vec<BlockId>,
     pub live_qubits: HashSet<QubitId>,
     pub live_cbits: HashSet<CbitId>,
+    /// Source span of the statement that created the block.
+    pub span: Option<Span>,
 }
 
 impl QirModule {
@@ -81,6 +149,36 @@ impl QirModule {
         cbit_id
     }
 
+    /// Keeps only the function `name` as the circuit root, dropping the
+    /// others (their bodies were inlined at each call). Returns `false`
+    /// and leaves the module unchanged when there is no such function.
+    pub fn retain_entry(&mut self, name: &str) -> bool {
+        if !self.functions.iter().any(|f| f.name == name) {
+            return false;
+        }
+        self.functions.retain(|f| f.name == name);
+        true
+    }
+
+    /// Runs the QIR analyzer, returning every error it finds. Stricter than
+    /// the compiler pipeline: unreachable blocks, only a warning there, are
+    /// reported as errors here.
+    pub fn validate(&self) -> Result<(), Vec<QirError>> {
+        let mut analyzer = QirAnalyzer::new();
+        analyzer.analyze_module(self);
+        
+        let mut errors = analyzer.get_errors().to_vec();
+        for func in &self.functions {
+            errors.extend(analyzer.unreachable_blocks(func));
+        }
+        
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     // --- Added Statistics Methods ---
 
     pub fn qubit_count(&self) -> usize {
@@ -96,6 +194,61 @@ impl QirModule {
             .count()
     }
 
+    /// Qubits the circuit touches: global ones, those some operation acts
+    /// on, and those prepared in `|1>`. Counted as the QASM generator does,
+    /// so allocated but unused qubits are left out.
+    pub fn used_qubit_count(&self) -> usize {
+        let mut used: HashSet<usize> = self.global_qubits.iter().map(|q| q.id()).collect();
+        let mut allocated = 0;
+        for op in self.functions.iter().flat_map(|f| f.blocks.values()).flat_map(|b| b.ops.iter()) {
+            match op {
+                QirOp::AllocQubit { init_state, .. } => {
+                    if let Some(BitState::One) = init_state {
+                        used.insert(allocated);
+                    }
+                    allocated += 1;
+                }
+                QirOp::ApplyGate { args, .. } => {
+                    used.extend(args.iter().filter_map(|arg| match arg {
+                        QirValue::Qubit(q) => Some(q.id()),
+                        _ => None,
+                    }));
+                }
+                QirOp::Measure { qubit, .. } | QirOp::Reset { qubit } => {
+                    used.insert(qubit.id());
+                }
+                QirOp::Barrier { qubits } | QirOp::ConditionalGate { qubits, .. } => {
+                    used.extend(qubits.iter().map(|q| q.id()));
+                }
+                _ => {}
+            }
+        }
+        used.len()
+    }
+
+    /// Cbits that are global, measured into or tested by a conditional gate.
+    pub fn used_cbit_count(&self) -> usize {
+        let mut used: HashSet<usize> = self.global_cbits.iter().map(|c| c.id()).collect();
+        for op in self.functions.iter().flat_map(|f| f.blocks.values()).flat_map(|b| b.ops.iter()) {
+            match op {
+                QirOp::Measure { cbit, .. } | QirOp::ConditionalGate { cbit, .. } => {
+                    used.insert(cbit.id());
+                }
+                _ => {}
+            }
+        }
+        used.len()
+    }
+
+    /// Qubits allocated in `|1>`, each prepared with an `x` gate.
+    pub fn initialization_gate_count(&self) -> usize {
+        self.functions.iter()
+            .flat_map(|f| f.blocks.values())
+            .flat_map(|b| b.ops.iter())
+            .filter(|op| matches!(op, QirOp::AllocQubit { init_state: Some(BitState::One), .. }))
+            .count()
+    }
+
     pub fn measurement_count(&self) -> usize {
         self.functions.iter()
             .flat_map(|f| f.blocks.values())
@@ -103,6 +256,55 @@ impl QirModule {
             .filter(|op| matches!(op, QirOp::Measure { .. }))
             .count()
     }
+
+    /// Number of layers in the circuit: each gate, measurement or reset sits
+    /// one layer after the latest operation on any of its qubits. A barrier
+    /// adds no layer but lines its qubits up at their deepest one.
+    pub fn circuit_depth(&self) -> usize {
+        let mut qubit_depth: HashMap<usize, usize> = HashMap::new();
+        let mut depth = 0;
+
+        for func in &self.functions {
+            let block_ids = func.block_ids();
+
+            for block_id in block_ids {
+                for op in &func.blocks[&block_id].ops {
+                    let qubits: Vec<usize> = match op {
+                        QirOp::ApplyGate { args, .. } => args.iter()
+                            .filter_map(|arg| match arg {
+                                QirValue::Qubit(q) => Some(q.id()),
+                                _ => None,
+                            })
+                            .collect(),
+                        QirOp::Measure { qubit, .. } | QirOp::Reset { qubit } => vec![qubit.id()],
+                        QirOp::ConditionalGate { qubits, .. } => qubits.iter().map(|q| q.id()).collect(),
+                        QirOp::Barrier { qubits } => {
+                            let level = qubits.iter()
+                                .map(|q| qubit_depth.get(&q.id()).copied().unwrap_or(0))
+                                .max()
+                                .unwrap_or(0);
+                            for q in qubits {
+                                qubit_depth.insert(q.id(), level);
+                            }
+                            continue;
+                        }
+                        _ => continue,
+                    };
+
+                    let layer = qubits.iter()
+                        .map(|q| qubit_depth.get(q).copied().unwrap_or(0))
+                        .max()
+                        .unwrap_or(0) + 1;
+                    for q in qubits {
+                        qubit_depth.insert(q, layer);
+                    }
+                    depth = depth.max(layer);
+                }
+            }
+        }
+
+        depth
+    }
 }
 
 impl QirFunction {
@@ -117,6 +319,7 @@ impl QirFunction {
             successors: Vec::new(),
             live_qubits: HashSet::new(),
             live_cbits: HashSet::new(),
+            span: None,
         });
         
         Self {
@@ -130,6 +333,7 @@ impl QirFunction {
             next_qubit_id: 0,
             next_cbit_id: 0,
             next_temp_id: 0,
+            temp_spans: HashMap::new(),
         }
     }
     
@@ -144,11 +348,33 @@ impl QirFunction {
             successors: Vec::new(),
             live_qubits: HashSet::new(),
             live_cbits: HashSet::new(),
+            span: None,
         });
         
         id
     }
     
+    /// Block ids in creation order. `blocks` is a `HashMap`, so anything
+    /// whose output depends on block order should iterate these instead.
+    pub fn block_ids(&self) -> Vec<BlockId> {
+        let mut block_ids: Vec<BlockId> = self.blocks.keys().copied().collect();
+        block_ids.sort_by_key(|id| id.id());
+        block_ids
+    }
+    
+    /// Attributes temps and blocks that don't have a span yet to `span`.
+    /// Called innermost statement first, so nested statements keep their own.
+    pub fn record_span(&mut self, temps: std::ops::Range<usize>, blocks: std::ops::Range<usize>, span: &Span) {
+        for temp in temps {
+            self.temp_spans.entry(TempId::new(temp)).or_insert_with(|| span.clone());
+        }
+        for block in blocks {
+            if let Some(block) = self.blocks.get_mut(&BlockId::new(block)) {
+                block.span.get_or_insert_with(|| span.clone());
+            }
+        }
+    }
+    
     pub fn switch_to_block(&mut self, block_id: BlockId) {
         self.current_block = block_id;
     }
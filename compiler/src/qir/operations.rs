@@ -1,6 +1,160 @@
 // src/qir/operations.rs - COMPLETE FIXED VERSION
-use crate::ast::{BinaryOp as AstBinaryOp, UnaryOp as AstUnaryOp, Gate as AstGate};
+use crate::ast::{BinaryOp as AstBinaryOp, UnaryOp as AstUnaryOp, Gate as AstGate, Expr};
 use super::types::{QubitId, CbitId, TempId, BlockId, QirValue, BitState};
+use std::fmt;
+
+/// A rotation angle: folded to a number when possible, otherwise kept as an
+/// OpenQASM expression such as `theta/2`. Folded multiples of π are printed
+/// symbolically (`pi/2`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum QirAngle {
+    Value(f64),
+    Symbolic(String),
+}
+
+impl QirAngle {
+    /// Builds an angle from an AST expression. `lookup` resolves variables
+    /// that have a known numeric value (e.g. unrolled loop counters).
+    pub fn from_expr(expr: &Expr, lookup: &dyn Fn(&str) -> Option<f64>) -> Self {
+        match Self::fold(expr, lookup) {
+            Some(value) => QirAngle::Value(value),
+            None => QirAngle::Symbolic(Self::render(expr, lookup, false)),
+        }
+    }
+    
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            QirAngle::Value(v) => Some(*v),
+            QirAngle::Symbolic(_) => None,
+        }
+    }
+    
+    fn fold(expr: &Expr, lookup: &dyn Fn(&str) -> Option<f64>) -> Option<f64> {
+        match expr {
+            Expr::LiteralInt(v, _) => Some(*v as f64),
+            Expr::LiteralFloat(v, _) => Some(*v),
+            Expr::Variable(name, _) => lookup(name)
+                .or_else(|| (name == "pi").then_some(std::f64::consts::PI)),
+            // Array elements are looked up by their `name[i]` spelling
+            Expr::Index(array, index, _) => match &**array {
+                Expr::Variable(name, _) => lookup(&format!("{}[{}]", name, Self::fold(index, lookup)? as i64)),
+                _ => None,
+            },
+            Expr::UnaryOp(AstUnaryOp::Neg, operand, _) => Self::fold(operand, lookup).map(|v| -v),
+            Expr::Call(name, args, _) if args.len() == 1 => {
+                let arg = Self::fold(&args[0], lookup)?;
+                match name.as_str() {
+                    "sqrt" => Some(arg.sqrt()),
+                    "sin" => Some(arg.sin()),
+                    "cos" => Some(arg.cos()),
+                    _ => None,
+                }
+            }
+            Expr::BinaryOp(left, op, right, _) => {
+                let l = Self::fold(left, lookup)?;
+                let r = Self::fold(right, lookup)?;
+                match op {
+                    AstBinaryOp::Add => Some(l + r),
+                    AstBinaryOp::Sub => Some(l - r),
+                    AstBinaryOp::Mul => Some(l * r),
+                    AstBinaryOp::Div if r != 0.0 => Some(l / r),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+    
+    fn render(expr: &Expr, lookup: &dyn Fn(&str) -> Option<f64>, nested: bool) -> String {
+        if let Some(value) = Self::fold(expr, lookup) {
+            return QirAngle::Value(value).to_string();
+        }
+        
+        match expr {
+            Expr::Variable(name, _) => name.clone(),
+            Expr::UnaryOp(AstUnaryOp::Neg, operand, _) => format!("-{}", Self::render(operand, lookup, true)),
+            Expr::Call(name, args, _) if args.len() == 1 => {
+                format!("{}({})", name, Self::render(&args[0], lookup, false))
+            }
+            Expr::BinaryOp(left, op, right, _) => {
+                let symbol = match op {
+                    AstBinaryOp::Add => "+",
+                    AstBinaryOp::Sub => "-",
+                    AstBinaryOp::Mul => "*",
+                    AstBinaryOp::Div => "/",
+                    _ => "?",
+                };
+                let text = format!(
+                    "{}{}{}",
+                    Self::render(left, lookup, true),
+                    symbol,
+                    Self::render(right, lookup, true)
+                );
+                if nested { format!("({})", text) } else { text }
+            }
+            _ => "0".to_string(),
+        }
+    }
+    
+    /// Sum of two angles, used when merging consecutive rotations.
+    pub fn combined_with(&self, other: &QirAngle) -> QirAngle {
+        match (self, other) {
+            (QirAngle::Value(a), QirAngle::Value(b)) => QirAngle::Value(a + b),
+            _ => QirAngle::Symbolic(format!("{}+{}", self, other)),
+        }
+    }
+    
+    /// The opposite angle, used for the adjoint of a rotation.
+    pub fn negated(&self) -> QirAngle {
+        match self {
+            QirAngle::Value(v) => QirAngle::Value(-v),
+            QirAngle::Symbolic(text) => QirAngle::Symbolic(format!("-({})", text)),
+        }
+    }
+    
+    /// True when the angle is a known multiple of 2π, i.e. the rotation is
+    /// the identity up to global phase.
+    pub fn is_full_turn(&self) -> bool {
+        match self {
+            QirAngle::Value(v) => {
+                let turns = v / (2.0 * std::f64::consts::PI);
+                (turns - turns.round()).abs() < 1e-9
+            }
+            QirAngle::Symbolic(_) => false,
+        }
+    }
+    
+    /// Formats `value` as a small rational multiple of π (`pi/2`, `3*pi/4`)
+    /// when it is one, so the QASM output stays exact.
+    fn pi_fraction(value: f64) -> Option<String> {
+        let ratio = value / std::f64::consts::PI;
+        for den in 1..=16i64 {
+            let num = (ratio * den as f64).round() as i64;
+            if num == 0 || (ratio * den as f64 - num as f64).abs() > 1e-9 {
+                continue;
+            }
+            let numerator = match num {
+                1 => "pi".to_string(),
+                -1 => "-pi".to_string(),
+                _ => format!("{}*pi", num),
+            };
+            return Some(if den == 1 { numerator } else { format!("{}/{}", numerator, den) });
+        }
+        None
+    }
+}
+
+impl fmt::Display for QirAngle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QirAngle::Value(v) => match Self::pi_fraction(*v) {
+                Some(text) => write!(f, "{}", text),
+                None => write!(f, "{}", v),
+            },
+            QirAngle::Symbolic(text) => write!(f, "{}", text),
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum QirGate {
@@ -11,6 +165,8 @@ pub enum QirGate {
     Z,
     CNOT,
     SWAP,
+    CH,
+    CY,
     
     // Phase gates
     T,
@@ -19,17 +175,23 @@ pub enum QirGate {
     Sdg,
     
     // Rotation gates
-    RX(f64),
-    RY(f64),
-    RZ(f64),
+    RX(QirAngle),
+    RY(QirAngle),
+    RZ(QirAngle),
     U3(f64, f64, f64),
     
+    // Controlled rotations (control, target)
+    CRX(QirAngle),
+    CRY(QirAngle),
+    CRZ(QirAngle),
+    
     // Multi-qubit gates
     Toffoli,
     Fredkin,
     
-    // Custom gates
-    Custom { name: String, matrix: Vec<Vec<f64>> },
+    // Custom gates; `matrix` is empty when only a QASM template is known,
+    // as for gates loaded from a gate set file
+    Custom { name: String, qubits: usize, matrix: Vec<Vec<f64>> },
 }
 
 impl QirGate {
@@ -38,12 +200,53 @@ impl QirGate {
             QirGate::H | QirGate::X | QirGate::Y | QirGate::Z |
             QirGate::T | QirGate::Tdg | QirGate::S | QirGate::Sdg |
             QirGate::RX(_) | QirGate::RY(_) | QirGate::RZ(_) | QirGate::U3(_, _, _) => 1,
-            QirGate::CNOT | QirGate::SWAP => 2,
+            QirGate::CNOT | QirGate::SWAP | QirGate::CH | QirGate::CY |
+            QirGate::CRX(_) | QirGate::CRY(_) | QirGate::CRZ(_) => 2,
             QirGate::Toffoli | QirGate::Fredkin => 3,
-            QirGate::Custom { matrix, .. } => {
-                let size = matrix.len();
-                (size as f64).log2().round() as usize
-            }
+            QirGate::Custom { qubits, .. } => *qubits,
+        }
+    }
+    
+    /// Number of leading control qubits. The operands of every gate are
+    /// ordered controls first, then targets, as in OpenQASM (`cx control, target;`).
+    pub fn num_controls(&self) -> usize {
+        match self {
+            QirGate::CNOT | QirGate::CH | QirGate::CY |
+            QirGate::CRX(_) | QirGate::CRY(_) | QirGate::CRZ(_) | QirGate::Fredkin => 1,
+            QirGate::Toffoli => 2,
+            _ => 0,
+        }
+    }
+    
+    /// Splits `operands` into `(controls, targets)` by the ordering contract
+    /// of [`num_controls`](Self::num_controls), or returns `None` when their
+    /// number doesn't match the gate's arity.
+    pub fn split_operands<'a, T>(&self, operands: &'a [T]) -> Option<(&'a [T], &'a [T])> {
+        if operands.len() != self.arity() {
+            return None;
+        }
+        Some(operands.split_at(self.num_controls()))
+    }
+    
+    /// The inverse gate: self-inverse gates map to themselves, S/T to their
+    /// daggers and rotations to the opposite angle. `None` for custom gates,
+    /// whose matrix we don't invert.
+    pub fn adjoint(&self) -> Option<QirGate> {
+        match self {
+            QirGate::S => Some(QirGate::Sdg),
+            QirGate::Sdg => Some(QirGate::S),
+            QirGate::T => Some(QirGate::Tdg),
+            QirGate::Tdg => Some(QirGate::T),
+            QirGate::RX(angle) => Some(QirGate::RX(angle.negated())),
+            QirGate::RY(angle) => Some(QirGate::RY(angle.negated())),
+            QirGate::RZ(angle) => Some(QirGate::RZ(angle.negated())),
+            QirGate::CRX(angle) => Some(QirGate::CRX(angle.negated())),
+            QirGate::CRY(angle) => Some(QirGate::CRY(angle.negated())),
+            QirGate::CRZ(angle) => Some(QirGate::CRZ(angle.negated())),
+            // U3(θ,φ,λ)† = U3(-θ,-λ,-φ)
+            QirGate::U3(theta, phi, lambda) => Some(QirGate::U3(-theta, -lambda, -phi)),
+            QirGate::Custom { .. } => None,
+            _ => Some(self.clone()),
         }
     }
     
@@ -66,16 +269,50 @@ impl QirGate {
             AstGate::Y => Some(QirGate::Y),
             AstGate::Z => Some(QirGate::Z),
             AstGate::CNOT => Some(QirGate::CNOT),
-            AstGate::RX(expr) => {
-                // For now, use placeholder
-                Some(QirGate::RX(0.0))
-            }
-            AstGate::RY(expr) => Some(QirGate::RY(0.0)),
-            AstGate::RZ(expr) => Some(QirGate::RZ(0.0)),
+            AstGate::RX(expr) => Some(QirGate::RX(QirAngle::from_expr(expr, &|_| None))),
+            AstGate::RY(expr) => Some(QirGate::RY(QirAngle::from_expr(expr, &|_| None))),
+            AstGate::RZ(expr) => Some(QirGate::RZ(QirAngle::from_expr(expr, &|_| None))),
+            AstGate::CRX(expr) => Some(QirGate::CRX(QirAngle::from_expr(expr, &|_| None))),
+            AstGate::CRY(expr) => Some(QirGate::CRY(QirAngle::from_expr(expr, &|_| None))),
+            AstGate::CRZ(expr) => Some(QirGate::CRZ(QirAngle::from_expr(expr, &|_| None))),
             AstGate::T => Some(QirGate::T),
             AstGate::S => Some(QirGate::S),
             AstGate::SWAP => Some(QirGate::SWAP),
-            _ => None,
+            AstGate::CH => Some(QirGate::CH),
+            AstGate::CY => Some(QirGate::CY),
+            AstGate::Custom(name, arity) => Some(QirGate::Custom {
+                name: name.clone(),
+                qubits: *arity,
+                matrix: Vec::new(),
+            }),
+        }
+    }
+    
+    /// Lowercase OpenQASM name without parameters, e.g. `rz` for any `RZ`.
+    pub fn base_name(&self) -> String {
+        match self {
+            QirGate::H => "h".to_string(),
+            QirGate::X => "x".to_string(),
+            QirGate::Y => "y".to_string(),
+            QirGate::Z => "z".to_string(),
+            QirGate::CNOT => "cx".to_string(),
+            QirGate::SWAP => "swap".to_string(),
+            QirGate::CH => "ch".to_string(),
+            QirGate::CY => "cy".to_string(),
+            QirGate::T => "t".to_string(),
+            QirGate::Tdg => "tdg".to_string(),
+            QirGate::S => "s".to_string(),
+            QirGate::Sdg => "sdg".to_string(),
+            QirGate::RX(_) => "rx".to_string(),
+            QirGate::RY(_) => "ry".to_string(),
+            QirGate::RZ(_) => "rz".to_string(),
+            QirGate::CRX(_) => "crx".to_string(),
+            QirGate::CRY(_) => "cry".to_string(),
+            QirGate::CRZ(_) => "crz".to_string(),
+            QirGate::U3(_, _, _) => "u3".to_string(),
+            QirGate::Toffoli => "ccx".to_string(),
+            QirGate::Fredkin => "cswap".to_string(),
+            QirGate::Custom { name, .. } => name.to_lowercase(),
         }
     }
     
@@ -87,11 +324,18 @@ impl QirGate {
             QirGate::Z => "z".to_string(),
             QirGate::CNOT => "cx".to_string(),
             QirGate::SWAP => "swap".to_string(),
+            QirGate::CH => "ch".to_string(),
+            QirGate::CY => "cy".to_string(),
             QirGate::T => "t".to_string(),
+            QirGate::Tdg => "tdg".to_string(),
             QirGate::S => "s".to_string(),
+            QirGate::Sdg => "sdg".to_string(),
             QirGate::RX(angle) => format!("rx({})", angle),
             QirGate::RY(angle) => format!("ry({})", angle),
             QirGate::RZ(angle) => format!("rz({})", angle),
+            QirGate::CRX(angle) => format!("crx({})", angle),
+            QirGate::CRY(angle) => format!("cry({})", angle),
+            QirGate::CRZ(angle) => format!("crz({})", angle),
             QirGate::U3(theta, phi, lambda) => format!("u3({}, {}, {})", theta, phi, lambda),
             QirGate::Toffoli => "ccx".to_string(),
             _ => format!("// {:?}", self),
@@ -103,9 +347,14 @@ impl QirGate {
 pub enum QirOp {
     // Quantum operations
     AllocQubit { result: TempId, init_state: Option<BitState> },
+    /// `args` are ordered controls first, then targets (see [`QirGate::split_operands`]).
     ApplyGate { gate: QirGate, args: Vec<QirValue>, result: Option<TempId> },
     Measure { qubit: QubitId, cbit: CbitId },
     Reset { qubit: QubitId },
+    Barrier { qubits: Vec<QubitId> },
+    /// Gate applied only when a measured cbit equals `value` (`if (c==1) x q;`).
+    /// `qubits` follow the same controls-then-targets order as `ApplyGate`.
+    ConditionalGate { cbit: CbitId, value: i64, gate: QirGate, qubits: Vec<QubitId> },
     
     // Classical operations
     AllocCbit { result: TempId, init_value: Option<u8> },
@@ -1,12 +1,142 @@
 // src/qir/optimizer.rs - COMPLETE OPTIMIZER IMPLEMENTATION
 use super::*;
+use super::unitary::{self, Matrix2};
+use crate::ast::{BinaryOp, UnaryOp};
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Which gates undo which, keyed by [`QirGate::base_name`]. Gate
+/// cancellation removes an adjacent pair `g; h` on the same qubits when
+/// `h` is listed as the inverse of `g`. Rotations are not listed here; a
+/// pair of the same rotation cancels when the angles sum to zero.
+#[derive(Debug, Clone)]
+pub struct InverseRules {
+    inverses: HashMap<String, String>,
+}
+
+impl InverseRules {
+    /// A table with no rules, so only rotation pairs cancel.
+    pub fn empty() -> Self {
+        Self { inverses: HashMap::new() }
+    }
+    
+    /// Records `gate` and `inverse` as undoing each other, in either order.
+    pub fn add(&mut self, gate: &str, inverse: &str) -> &mut Self {
+        self.inverses.insert(gate.to_string(), inverse.to_string());
+        self.inverses.insert(inverse.to_string(), gate.to_string());
+        self
+    }
+    
+    /// Records a gate that is its own inverse.
+    pub fn add_self_inverse(&mut self, gate: &str) -> &mut Self {
+        self.add(gate, gate)
+    }
+    
+    pub fn inverse_of(&self, gate: &str) -> Option<&str> {
+        self.inverses.get(gate).map(String::as_str)
+    }
+}
+
+impl Default for InverseRules {
+    fn default() -> Self {
+        let mut rules = Self::empty();
+        for gate in ["h", "x", "y", "z", "cx", "swap", "ch", "cy", "ccx", "cswap"] {
+            rules.add_self_inverse(gate);
+        }
+        rules.add("s", "sdg").add("t", "tdg");
+        rules
+    }
+}
+
+/// Text dumps of a module taken around optimization, for comparing what
+/// the passes changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QirSnapshots {
+    pub before: String,
+    pub after: String,
+}
+
+/// What one optimizer pass did, summed over every function it ran on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassReport {
+    /// The pass's method name, e.g. `gate_cancellation`.
+    pub name: &'static str,
+    /// Net gates removed per run; merging two rotations into one removes one.
+    pub gates_removed: usize,
+    /// Net gates added per run, e.g. the extra CNOTs of a decomposed SWAP.
+    pub gates_added: usize,
+    pub elapsed: Duration,
+}
+
+/// Per-pass results of [`QirOptimizer::optimize_module`], in the order the
+/// passes first ran.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptimizationReport {
+    pub passes: Vec<PassReport>,
+}
+
+impl OptimizationReport {
+    pub fn get(&self, name: &str) -> Option<&PassReport> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+    
+    /// The passes ordered by time spent, slowest first.
+    pub fn hottest(&self) -> Vec<&PassReport> {
+        let mut passes: Vec<&PassReport> = self.passes.iter().collect();
+        passes.sort_by_key(|pass| std::cmp::Reverse(pass.elapsed));
+        passes
+    }
+    
+    /// Adds the results of `other`, combining entries for the same pass.
+    pub fn merge(&mut self, other: OptimizationReport) {
+        for pass in other.passes {
+            let entry = self.entry(pass.name);
+            entry.gates_removed += pass.gates_removed;
+            entry.gates_added += pass.gates_added;
+            entry.elapsed += pass.elapsed;
+        }
+    }
+    
+    fn record(&mut self, name: &'static str, gates_before: usize, gates_after: usize, elapsed: Duration) {
+        let entry = self.entry(name);
+        entry.gates_removed += gates_before.saturating_sub(gates_after);
+        entry.gates_added += gates_after.saturating_sub(gates_before);
+        entry.elapsed += elapsed;
+    }
+    
+    fn entry(&mut self, name: &'static str) -> &mut PassReport {
+        let index = match self.passes.iter().position(|pass| pass.name == name) {
+            Some(index) => index,
+            None => {
+                self.passes.push(PassReport { name, gates_removed: 0, gates_added: 0, elapsed: Duration::ZERO });
+                self.passes.len() - 1
+            }
+        };
+        &mut self.passes[index]
+    }
+}
 
 pub struct QirOptimizer {
     pub enable_gate_cancellation: bool,
     pub enable_dead_qubit_elimination: bool,
     pub enable_constant_folding: bool,
     pub enable_common_subexpression_elimination: bool,
+    pub enable_rotation_merging: bool,
+    /// Drop classical ops whose result temp is never read.
+    pub enable_dead_temp_elimination: bool,
+    /// Reorder commuting gates so separated inverse pairs can cancel.
+    pub enable_commutation: bool,
+    /// Rewrite SWAP as three CNOTs for backends without a native SWAP.
+    /// Independent of `-O`, so it is off unless requested.
+    pub enable_swap_decomposition: bool,
+    /// Reset and reuse measured qubits for later allocations. Changes the
+    /// qubit layout, so it is off unless requested.
+    pub enable_ancilla_reuse: bool,
+    /// Fuse runs of single-qubit gates into one U3. Changes the gate set,
+    /// so it is off unless requested.
+    pub enable_single_qubit_fusion: bool,
+    /// Inverse pairs removed by gate cancellation.
+    pub inverse_rules: InverseRules,
 }
 
 impl QirOptimizer {
@@ -16,46 +146,168 @@ impl QirOptimizer {
             enable_dead_qubit_elimination: enabled,
             enable_constant_folding: enabled,
             enable_common_subexpression_elimination: enabled,
+            enable_rotation_merging: enabled,
+            enable_dead_temp_elimination: enabled,
+            enable_commutation: enabled,
+            enable_swap_decomposition: false,
+            enable_ancilla_reuse: false,
+            enable_single_qubit_fusion: false,
+            inverse_rules: InverseRules::default(),
         }
     }
     
-    pub fn optimize_module(&self, module: &mut QirModule) {
-        if !self.enable_gate_cancellation && !self.enable_dead_qubit_elimination {
-            return;
+    pub fn optimize_module(&self, module: &mut QirModule) -> OptimizationReport {
+        let mut report = OptimizationReport::default();
+        if !self.enable_gate_cancellation && !self.enable_dead_qubit_elimination
+            && !self.enable_swap_decomposition && !self.enable_ancilla_reuse
+            && !self.enable_single_qubit_fusion {
+            return report;
         }
 
         for func in &mut module.functions {
-            self.optimize_function(func);
+            self.run_function_passes(func, &mut report);
+        }
+        
+        // Needs the module's register layout, so it runs after the per-function passes
+        if self.enable_dead_qubit_elimination {
+            Self::timed_module(&mut report, "remove_unused_qubits", module, |module| self.remove_unused_qubits(module));
         }
+        
+        // Last, so it sees the final use of every qubit
+        if self.enable_ancilla_reuse {
+            Self::timed_module(&mut report, "reuse_ancillas", module, |module| self.reuse_ancillas(module));
+        }
+        
+        report
     }
     
-    pub fn optimize_function(&self, func: &mut QirFunction) {
+    /// Runs [`optimize_module`](Self::optimize_module), also returning the QIR
+    /// text from before and after the passes.
+    pub fn optimize_module_with_snapshots(&self, module: &mut QirModule) -> (QirSnapshots, OptimizationReport) {
+        let before = module.to_string();
+        let report = self.optimize_module(module);
+        (QirSnapshots { before, after: module.to_string() }, report)
+    }
+    
+    pub fn optimize_function(&self, func: &mut QirFunction) -> OptimizationReport {
+        let mut report = OptimizationReport::default();
+        self.run_function_passes(func, &mut report);
+        report
+    }
+    
+    fn run_function_passes(&self, func: &mut QirFunction, report: &mut OptimizationReport) {
         // Run optimizations in sequence
         
+        // 0. SWAP decomposition, first so later passes see the CNOTs
+        if self.enable_swap_decomposition {
+            Self::timed(report, "decompose_swap", func, |func| self.decompose_swap(func));
+        }
+        
         // 1. Constant folding (simplified for now)
         if self.enable_constant_folding {
-            self.constant_folding(func);
+            Self::timed(report, "constant_folding", func, |func| self.constant_folding(func));
         }
         
         // 2. Dead qubit elimination
         if self.enable_dead_qubit_elimination {
-            self.dead_qubit_elimination(func);
+            Self::timed(report, "dead_qubit_elimination", func, |func| self.dead_qubit_elimination(func));
         }
         
-        // 3. Gate cancellation (peep-hole optimization)
+        // 3. Rotation merging (RZ(a); RZ(b) -> RZ(a+b))
+        if self.enable_rotation_merging {
+            Self::timed(report, "rotation_merging", func, |func| self.rotation_merging(func));
+        }
+        
+        // 4. Gate cancellation (peep-hole optimization)
         if self.enable_gate_cancellation {
-            self.gate_cancellation(func);
+            Self::timed(report, "gate_cancellation", func, |func| self.gate_cancellation(func));
+            
+            // Every reordering makes a pair adjacent, so each round removes gates
+            while self.enable_commutation && Self::timed(report, "commute_gates", func, |func| self.commute_gates(func)) {
+                Self::timed(report, "gate_cancellation", func, |func| self.gate_cancellation(func));
+            }
+        }
+        
+        // 5. Single-qubit fusion, after cancellation has removed what it can
+        if self.enable_single_qubit_fusion {
+            Self::timed(report, "fuse_single_qubit", func, |func| self.fuse_single_qubit(func));
         }
         
-        // 4. CSE
+        // 6. CSE
         if self.enable_common_subexpression_elimination {
-            self.common_subexpression_elimination(func);
+            Self::timed(report, "common_subexpression_elimination", func, |func| self.common_subexpression_elimination(func));
+        }
+        
+        // 7. Dead temps, last so it also catches the ops CSE made redundant
+        if self.enable_dead_temp_elimination {
+            Self::timed(report, "remove_dead_temps", func, |func| self.remove_dead_temps(func));
         }
         
         // Clean up empty blocks created by optimizations
         self.remove_empty_blocks(func);
     }
     
+    /// Runs `pass` on `func`, recording its time and gate count change under `name`.
+    fn timed<R>(report: &mut OptimizationReport, name: &'static str, func: &mut QirFunction,
+                pass: impl FnOnce(&mut QirFunction) -> R) -> R {
+        let before = Self::gate_op_count(func);
+        let start = Instant::now();
+        let result = pass(func);
+        report.record(name, before, Self::gate_op_count(func), start.elapsed());
+        result
+    }
+    
+    fn timed_module(report: &mut OptimizationReport, name: &'static str, module: &mut QirModule,
+                    pass: impl FnOnce(&mut QirModule)) {
+        let count = |module: &QirModule| module.functions.iter().map(Self::gate_op_count).sum::<usize>();
+        let before = count(module);
+        let start = Instant::now();
+        pass(module);
+        report.record(name, before, count(module), start.elapsed());
+    }
+    
+    fn gate_op_count(func: &QirFunction) -> usize {
+        func.blocks.values()
+            .flat_map(|block| &block.ops)
+            .filter(|op| matches!(op, QirOp::ApplyGate { .. } | QirOp::ConditionalGate { .. }))
+            .count()
+    }
+    
+    /// Rewrites `SWAP a, b` as `CNOT a, b; CNOT b, a; CNOT a, b`.
+    fn decompose_swap(&self, func: &mut QirFunction) {
+        for block in func.blocks.values_mut() {
+            let mut ops = Vec::with_capacity(block.ops.len());
+            for op in block.ops.drain(..) {
+                match op {
+                    QirOp::ApplyGate { gate: QirGate::SWAP, args, result } if args.len() == 2 => {
+                        let (a, b) = (args[0].clone(), args[1].clone());
+                        for (i, (control, target)) in [(&a, &b), (&b, &a), (&a, &b)].into_iter().enumerate() {
+                            ops.push(QirOp::ApplyGate {
+                                gate: QirGate::CNOT,
+                                args: vec![control.clone(), target.clone()],
+                                // The last CNOT leaves the qubits in their swapped state
+                                result: if i == 2 { result } else { None },
+                            });
+                        }
+                    }
+                    QirOp::ConditionalGate { cbit, value, gate: QirGate::SWAP, qubits } if qubits.len() == 2 => {
+                        let (a, b) = (qubits[0], qubits[1]);
+                        for (control, target) in [(a, b), (b, a), (a, b)] {
+                            ops.push(QirOp::ConditionalGate {
+                                cbit,
+                                value,
+                                gate: QirGate::CNOT,
+                                qubits: vec![control, target],
+                            });
+                        }
+                    }
+                    other => ops.push(other),
+                }
+            }
+            block.ops = ops;
+        }
+    }
+    
     fn constant_folding(&self, func: &mut QirFunction) {
         // Placeholder for constant folding
         // Real implementation would propagate values through the CFG
@@ -123,14 +375,261 @@ impl QirOptimizer {
                         }
                     }
                     QirOp::Reset { qubit } => live_qubits.contains(qubit),
-                    // We don't remove AllocQubit yet as it might disrupt register indexing in the backend
-                    // A proper allocator rewrite would be needed to remove them safely
+                    // AllocQubit is dropped by `remove_unused_qubits`, which also renumbers the rest
                     _ => true
                 }
             });
         }
     }
 
+    /// Drops `AllocQubit` for qubits that no op refers to and renumbers the
+    /// remaining qubits. The QASM backend numbers qubits by allocation order,
+    /// so ids must stay dense and match the order of the `AllocQubit` ops.
+    /// Qubits of a declared `qreg` are always kept so registers stay whole.
+    fn remove_unused_qubits(&self, module: &mut QirModule) {
+        // Qubit ids restart in every function, so only a lone function can be renumbered
+        if module.functions.len() != 1 {
+            return;
+        }
+        
+        let registers: Vec<(String, usize, usize)> = module.metadata.iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix("qreg.")?;
+                let (start, size) = value.split_once(',')?;
+                Some((key.clone(), start.parse().ok()?, size.parse().ok()?))
+            })
+            .collect();
+        
+        let func = &mut module.functions[0];
+        let block_ids = func.block_ids();
+        
+        let allocations = block_ids.iter()
+            .flat_map(|id| &func.blocks[id].ops)
+            .filter(|op| matches!(op, QirOp::AllocQubit { .. }))
+            .count();
+        if allocations != func.next_qubit_id {
+            return;
+        }
+        
+        let mut used = vec![false; allocations];
+        for (_, start, size) in &registers {
+            if let Some(register) = used.get_mut(*start..(start + size).min(allocations)) {
+                register.fill(true);
+            }
+        }
+        for block in func.blocks.values_mut() {
+            for op in &mut block.ops {
+                Self::visit_op_qubits(op, &mut |qubit| {
+                    if let Some(flag) = used.get_mut(qubit.id()) {
+                        *flag = true;
+                    }
+                });
+            }
+        }
+        if used.iter().all(|&flag| flag) {
+            return;
+        }
+        
+        let mut remap = Vec::with_capacity(allocations);
+        let mut next = 0;
+        for &flag in &used {
+            remap.push(next);
+            if flag {
+                next += 1;
+            }
+        }
+        
+        let mut allocation = 0;
+        for block_id in &block_ids {
+            let block = func.blocks.get_mut(block_id).unwrap();
+            block.ops.retain(|op| {
+                if !matches!(op, QirOp::AllocQubit { .. }) {
+                    return true;
+                }
+                allocation += 1;
+                used[allocation - 1]
+            });
+            for op in &mut block.ops {
+                Self::visit_op_qubits(op, &mut |qubit| {
+                    if let Some(&id) = remap.get(qubit.id()) {
+                        *qubit = QubitId::new(id);
+                    }
+                });
+            }
+        }
+        func.next_qubit_id = next;
+        
+        for (key, start, size) in registers {
+            if start < allocations {
+                module.metadata.insert(key, format!("{},{}", remap[start], size));
+            }
+        }
+    }
+    
+    /// Gives a qubit allocated after another qubit's final measurement the
+    /// measured qubit's index instead of a new one, resetting it first. Ops
+    /// are taken in block order, the order the QASM backend emits them.
+    /// Like `remove_unused_qubits` this renumbers qubits, so it only runs on
+    /// a lone function, and qubits of a declared `qreg` are never reused.
+    fn reuse_ancillas(&self, module: &mut QirModule) {
+        if module.functions.len() != 1 {
+            return;
+        }
+        
+        let registers: Vec<(String, usize, usize)> = module.metadata.iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix("qreg.")?;
+                let (start, size) = value.split_once(',')?;
+                Some((key.clone(), start.parse().ok()?, size.parse().ok()?))
+            })
+            .collect();
+        
+        let func = &mut module.functions[0];
+        let block_ids = func.block_ids();
+        
+        let allocations = block_ids.iter()
+            .flat_map(|id| &func.blocks[id].ops)
+            .filter(|op| matches!(op, QirOp::AllocQubit { .. }))
+            .count();
+        if allocations != func.next_qubit_id {
+            return;
+        }
+        
+        let mut in_register = vec![false; allocations];
+        for (_, start, size) in &registers {
+            if let Some(register) = in_register.get_mut(*start..(start + size).min(allocations)) {
+                register.fill(true);
+            }
+        }
+        
+        // Position of each qubit's last op, and whether that op is a measurement
+        let mut last_use: Vec<Option<(usize, bool)>> = vec![None; allocations];
+        let mut position = 0;
+        for block_id in &block_ids {
+            for op in func.blocks.get_mut(block_id).unwrap().ops.iter_mut() {
+                let measured = matches!(op, QirOp::Measure { .. });
+                Self::visit_op_qubits(op, &mut |qubit| {
+                    if let Some(slot) = last_use.get_mut(qubit.id()) {
+                        *slot = Some((position, measured));
+                    }
+                });
+                position += 1;
+            }
+        }
+        
+        // Ops after which a qubit is measured for the last time and can be reused
+        let mut releases: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (logical, slot) in last_use.iter().enumerate() {
+            if let Some((position, true)) = slot {
+                if !in_register[logical] {
+                    releases.entry(*position).or_default().push(logical);
+                }
+            }
+        }
+        if releases.is_empty() {
+            return;
+        }
+        
+        // Logical id -> physical id, filled as allocations are reached
+        let mut remap: Vec<usize> = Vec::with_capacity(allocations);
+        let mut free: Vec<usize> = Vec::new();
+        let mut next = 0;
+        let mut position = 0;
+        for block_id in &block_ids {
+            let block = func.blocks.get_mut(block_id).unwrap();
+            let mut ops = Vec::with_capacity(block.ops.len());
+            
+            for mut op in block.ops.drain(..) {
+                if let QirOp::AllocQubit { init_state, .. } = &op {
+                    let reusable = !in_register[remap.len()] && !free.is_empty();
+                    if reusable {
+                        // Take the lowest free index so the output stays predictable
+                        free.sort_unstable_by(|a, b| b.cmp(a));
+                        let qubit = QubitId::new(free.pop().unwrap());
+                        let init_state = *init_state;
+                        ops.push(QirOp::Reset { qubit });
+                        if init_state == Some(BitState::One) {
+                            ops.push(QirOp::ApplyGate {
+                                gate: QirGate::X,
+                                args: vec![QirValue::Qubit(qubit)],
+                                result: None,
+                            });
+                        }
+                        remap.push(qubit.id());
+                    } else {
+                        ops.push(op);
+                        remap.push(next);
+                        next += 1;
+                    }
+                } else {
+                    Self::visit_op_qubits(&mut op, &mut |qubit| {
+                        if let Some(&physical) = remap.get(qubit.id()) {
+                            *qubit = QubitId::new(physical);
+                        }
+                    });
+                    ops.push(op);
+                }
+                
+                if let Some(released) = releases.get(&position) {
+                    free.extend(released.iter().map(|&logical| remap[logical]));
+                }
+                position += 1;
+            }
+            block.ops = ops;
+        }
+        func.next_qubit_id = next;
+        
+        for (key, start, size) in registers {
+            if let Some(&physical) = remap.get(start) {
+                module.metadata.insert(key, format!("{},{}", physical, size));
+            }
+        }
+    }
+    
+    /// Calls `f` on every qubit an op refers to.
+    fn visit_op_qubits(op: &mut QirOp, f: &mut impl FnMut(&mut QubitId)) {
+        match op {
+            QirOp::Measure { qubit, .. } | QirOp::Reset { qubit } => f(qubit),
+            QirOp::Barrier { qubits } | QirOp::ConditionalGate { qubits, .. } => {
+                for qubit in qubits {
+                    f(qubit);
+                }
+            }
+            QirOp::ApplyGate { args: values, .. }
+            | QirOp::MakeStruct { field_values: values, .. }
+            | QirOp::MakeArray { elements: values, .. } => {
+                values.iter_mut().for_each(|value| Self::visit_value_qubits(value, f));
+            }
+            QirOp::Return { value: Some(value) }
+            | QirOp::ClassicalAssign { value, .. }
+            | QirOp::Store { value, .. }
+            | QirOp::UnaryOp { operand: value, .. }
+            | QirOp::ExtractField { struct_val: value, .. }
+            | QirOp::ArrayGet { array: value, .. }
+            | QirOp::Branch { cond: value, .. } => Self::visit_value_qubits(value, f),
+            QirOp::BinaryOp { lhs, rhs, .. }
+            | QirOp::InsertField { struct_val: lhs, value: rhs, .. }
+            | QirOp::ArraySet { array: lhs, value: rhs, .. } => {
+                Self::visit_value_qubits(lhs, f);
+                Self::visit_value_qubits(rhs, f);
+            }
+            QirOp::Phi { incoming, .. } => {
+                incoming.iter_mut().for_each(|(_, value)| Self::visit_value_qubits(value, f));
+            }
+            _ => {}
+        }
+    }
+    
+    fn visit_value_qubits(value: &mut QirValue, f: &mut impl FnMut(&mut QubitId)) {
+        match value {
+            QirValue::Qubit(qubit) => f(qubit),
+            QirValue::Tuple(values) | QirValue::Array(values) => {
+                values.iter_mut().for_each(|value| Self::visit_value_qubits(value, f));
+            }
+            _ => {}
+        }
+    }
+    
     fn collect_qubits(&self, value: &QirValue, qubits: &mut HashSet<QubitId>) {
         match value {
             QirValue::Qubit(id) => { qubits.insert(*id); },
@@ -144,7 +643,9 @@ impl QirOptimizer {
     }
     
     fn gate_cancellation(&self, func: &mut QirFunction) {
-        // Look for consecutive gates on the same qubit that cancel each other
+        // Look for consecutive gates on the same qubit that cancel each other.
+        // Only directly adjacent ops are compared, so a barrier between two
+        // gates always keeps them apart.
         for block in func.blocks.values_mut() {
             let mut i = 0;
             while i < block.ops.len() {
@@ -172,6 +673,182 @@ impl QirOptimizer {
         }
     }
     
+    /// Moves a gate back next to an earlier gate it cancels with, provided every
+    /// op in between commutes with it. Returns true if anything was moved.
+    fn commute_gates(&self, func: &mut QirFunction) -> bool {
+        let mut moved = false;
+        for block in func.blocks.values_mut() {
+            for i in 0..block.ops.len() {
+                if let Some(j) = self.cancelling_partner(&block.ops, i) {
+                    if j > i + 1 {
+                        let op = block.ops.remove(j);
+                        block.ops.insert(i + 1, op);
+                        moved = true;
+                    }
+                }
+            }
+        }
+        moved
+    }
+    
+    /// Index of the first later gate that cancels `ops[i]` and can be moved
+    /// next to it. The partner acts on the same qubits and is diagonal exactly
+    /// when `ops[i]` is, so checking the ops in between against `ops[i]` suffices.
+    fn cancelling_partner(&self, ops: &[QirOp], i: usize) -> Option<usize> {
+        let QirOp::ApplyGate { gate, args, .. } = &ops[i] else {
+            return None;
+        };
+        let qubits = Self::gate_qubits(args)?;
+        
+        for (j, op) in ops.iter().enumerate().skip(i + 1) {
+            if let QirOp::ApplyGate { gate: other, args: other_args, .. } = op {
+                if self.gates_cancel(gate, other, args, other_args) {
+                    return Some(j);
+                }
+            }
+            if !Self::commutes_with(op, gate, &qubits) {
+                return None;
+            }
+        }
+        None
+    }
+    
+    /// True if `op` can swap places with `gate` acting on `qubits`. Gates on
+    /// disjoint qubits commute, as do single-qubit diagonal gates; nothing moves
+    /// across a measurement, reset or barrier touching the same qubits.
+    fn commutes_with(op: &QirOp, gate: &QirGate, qubits: &[QubitId]) -> bool {
+        let disjoint = |others: &[QubitId]| others.iter().all(|q| !qubits.contains(q));
+        match op {
+            QirOp::ApplyGate { gate: other, args, .. } => match Self::gate_qubits(args) {
+                Some(others) => disjoint(&others) || (Self::is_diagonal(gate) && Self::is_diagonal(other)),
+                None => false,
+            },
+            QirOp::ConditionalGate { qubits: others, .. } => disjoint(others),
+            QirOp::Measure { qubit, .. } | QirOp::Reset { qubit } => !qubits.contains(qubit),
+            // A barrier without operands covers every qubit
+            QirOp::Barrier { qubits: others } => !others.is_empty() && disjoint(others),
+            _ => true,
+        }
+    }
+    
+    fn is_diagonal(gate: &QirGate) -> bool {
+        matches!(
+            gate,
+            QirGate::Z | QirGate::S | QirGate::Sdg | QirGate::T | QirGate::Tdg | QirGate::RZ(_)
+        )
+    }
+    
+    /// The qubits a gate acts on, or `None` if an operand isn't a known qubit.
+    fn gate_qubits(args: &[QirValue]) -> Option<Vec<QubitId>> {
+        args.iter()
+            .map(|arg| match arg {
+                QirValue::Qubit(qubit) => Some(*qubit),
+                _ => None,
+            })
+            .collect()
+    }
+    
+    fn rotation_merging(&self, func: &mut QirFunction) {
+        for block in func.blocks.values_mut() {
+            let mut i = 0;
+            while i < block.ops.len() {
+                // Drop rotations that are a whole number of turns
+                if let QirOp::ApplyGate { gate: QirGate::RX(angle) | QirGate::RY(angle) | QirGate::RZ(angle), .. } = &block.ops[i] {
+                    if angle.is_full_turn() {
+                        block.ops.remove(i);
+                        i = i.saturating_sub(1);
+                        continue;
+                    }
+                }
+                
+                if i + 1 < block.ops.len() {
+                    let merged = if let (QirOp::ApplyGate { gate: gate1, args: args1, .. },
+                                         QirOp::ApplyGate { gate: gate2, args: args2, .. }) =
+                                         (&block.ops[i], &block.ops[i + 1]) {
+                        if args1 == args2 { self.merge_rotations(gate1, gate2) } else { None }
+                    } else {
+                        None
+                    };
+                    
+                    if let Some(merged) = merged {
+                        block.ops.remove(i + 1);
+                        if let QirOp::ApplyGate { gate, .. } = &mut block.ops[i] {
+                            *gate = merged;
+                        }
+                        // Re-check the merged gate against its new neighbour
+                        continue;
+                    }
+                }
+                i += 1;
+            }
+        }
+    }
+    
+    /// Replaces each run of two or more adjacent single-qubit gates on the same
+    /// qubit with one `U3` of their product, or drops the run when the product
+    /// is the identity up to a global phase. Gates with symbolic angles end a run.
+    fn fuse_single_qubit(&self, func: &mut QirFunction) {
+        for block in func.blocks.values_mut() {
+            let mut i = 0;
+            while i < block.ops.len() {
+                let Some(first) = Self::single_qubit_matrix(&block.ops[i]) else {
+                    i += 1;
+                    continue;
+                };
+                
+                let mut product = first;
+                let mut end = i + 1;
+                while end < block.ops.len() && Self::same_operands(&block.ops[i], &block.ops[end]) {
+                    match Self::single_qubit_matrix(&block.ops[end]) {
+                        // Later gates multiply from the left
+                        Some(matrix) => product = unitary::multiply(&matrix, &product),
+                        None => break,
+                    }
+                    end += 1;
+                }
+                
+                if end - i < 2 {
+                    i += 1;
+                    continue;
+                }
+                block.ops.drain(i + 1..end);
+                if unitary::is_identity(&product) {
+                    block.ops.remove(i);
+                    continue;
+                }
+                let (theta, phi, lambda) = unitary::u3_angles(&product);
+                if let QirOp::ApplyGate { gate, .. } = &mut block.ops[i] {
+                    *gate = QirGate::U3(theta, phi, lambda);
+                }
+                i += 1;
+            }
+        }
+    }
+    
+    /// The unitary of a gate applied to one qubit, if it has a known matrix.
+    fn single_qubit_matrix(op: &QirOp) -> Option<Matrix2> {
+        match op {
+            QirOp::ApplyGate { gate, args, .. } if args.len() == 1 => unitary::gate_matrix(gate),
+            _ => None,
+        }
+    }
+    
+    fn same_operands(a: &QirOp, b: &QirOp) -> bool {
+        matches!((a, b), (QirOp::ApplyGate { args: a, .. }, QirOp::ApplyGate { args: b, .. }) if a == b)
+    }
+    
+    fn merge_rotations(&self, gate1: &QirGate, gate2: &QirGate) -> Option<QirGate> {
+        match (gate1, gate2) {
+            (QirGate::RX(a), QirGate::RX(b)) => Some(QirGate::RX(a.combined_with(b))),
+            (QirGate::RY(a), QirGate::RY(b)) => Some(QirGate::RY(a.combined_with(b))),
+            (QirGate::RZ(a), QirGate::RZ(b)) => Some(QirGate::RZ(a.combined_with(b))),
+            (QirGate::CRX(a), QirGate::CRX(b)) => Some(QirGate::CRX(a.combined_with(b))),
+            (QirGate::CRY(a), QirGate::CRY(b)) => Some(QirGate::CRY(a.combined_with(b))),
+            (QirGate::CRZ(a), QirGate::CRZ(b)) => Some(QirGate::CRZ(a.combined_with(b))),
+            _ => None,
+        }
+    }
+    
     fn gates_cancel(&self, gate1: &QirGate, gate2: &QirGate, args1: &[QirValue], args2: &[QirValue]) -> bool {
         // Gates must operate on exactly the same arguments to cancel
         if args1 != args2 {
@@ -179,34 +856,232 @@ impl QirOptimizer {
         }
 
         match (gate1, gate2) {
-            // Self-inverse gates
-            (QirGate::H, QirGate::H) => true,
-            (QirGate::X, QirGate::X) => true,
-            (QirGate::Y, QirGate::Y) => true,
-            (QirGate::Z, QirGate::Z) => true,
-            (QirGate::CNOT, QirGate::CNOT) => true,
-            (QirGate::SWAP, QirGate::SWAP) => true,
+            (QirGate::RX(a), QirGate::RX(b)) | (QirGate::RY(a), QirGate::RY(b)) |
+            (QirGate::RZ(a), QirGate::RZ(b)) | (QirGate::CRX(a), QirGate::CRX(b)) |
+            (QirGate::CRY(a), QirGate::CRY(b)) | (QirGate::CRZ(a), QirGate::CRZ(b)) => {
+                Self::angles_cancel(a, b)
+            }
+            (QirGate::U3(..), _) | (_, QirGate::U3(..)) => false,
+            (QirGate::Custom { .. }, _) | (_, QirGate::Custom { .. }) => false,
+            _ => self.inverse_rules.inverse_of(&gate1.base_name()) == Some(gate2.base_name().as_str()),
+        }
+    }
+    
+    /// True when two known angles sum to zero. Only an exact zero is
+    /// accepted: a controlled rotation by 2π is not the identity.
+    fn angles_cancel(a: &QirAngle, b: &QirAngle) -> bool {
+        match (a.value(), b.value()) {
+            (Some(a), Some(b)) => (a + b).abs() < 1e-9,
+            _ => false,
+        }
+    }
+    
+    fn common_subexpression_elimination(&self, func: &mut QirFunction) {
+        // Temps whose defining op was removed, mapped to the equivalent earlier temp
+        let mut remap: HashMap<TempId, TempId> = HashMap::new();
+        
+        let block_ids = func.block_ids();
+        
+        for block_id in &block_ids {
+            let block = func.blocks.get_mut(block_id).unwrap();
+            
+            // Available expressions are only tracked within a single block, so the
+            // earlier definition always dominates the later one without needing
+            // dominance analysis.
+            let mut available: Vec<(QirOp, TempId)> = Vec::new();
+            let mut kept = Vec::with_capacity(block.ops.len());
             
-            // Inverse pairs
-            (QirGate::S, QirGate::Sdg) => true,
-            (QirGate::Sdg, QirGate::S) => true,
-            (QirGate::T, QirGate::Tdg) => true,
-            (QirGate::Tdg, QirGate::T) => true,
+            for mut op in block.ops.drain(..) {
+                Self::remap_op_temps(&mut op, &remap);
+                
+                match &op {
+                    QirOp::BinaryOp { op: bin_op, lhs, .. } if Self::is_assigning_op(bin_op) => {
+                        // The assigned value changes, so expressions using it are stale
+                        let target = lhs.clone();
+                        available.retain(|(expr, _)| !Self::expr_uses_value(expr, &target));
+                    }
+                    QirOp::BinaryOp { result, .. } | QirOp::UnaryOp { result, .. } if Self::is_pure_expr(&op) => {
+                        let result = *result;
+                        if let Some((_, existing)) = available.iter().find(|(expr, _)| Self::same_expr(expr, &op)) {
+                            remap.insert(result, *existing);
+                            continue;
+                        }
+                        available.push((op.clone(), result));
+                    }
+                    _ => {}
+                }
+                
+                kept.push(op);
+            }
             
-            // Rotation gates with opposite angles (simple case: 0)
-            // TODO: Implement angle addition/cancellation for rotations
+            block.ops = kept;
+        }
+        
+        // Rewrite uses of removed temps in every block (including earlier ones reached by back edges)
+        if !remap.is_empty() {
+            for block in func.blocks.values_mut() {
+                for op in &mut block.ops {
+                    Self::remap_op_temps(op, &remap);
+                }
+            }
+        }
+    }
+    
+    /// Removes constant `ClassicalAssign`s and pure `BinaryOp`/`UnaryOp`s whose
+    /// result temp is never used, repeating until no op feeds only dead ones.
+    fn remove_dead_temps(&self, func: &mut QirFunction) {
+        let analyzer = QirAnalyzer::new();
+        
+        loop {
+            let mut uses = Vec::new();
+            for block in func.blocks.values() {
+                for op in &block.ops {
+                    analyzer.collect_temp_uses(op, &mut uses);
+                }
+            }
+            let used: HashSet<TempId> = uses.into_iter().collect();
+            
+            let mut removed = false;
+            for block in func.blocks.values_mut() {
+                let before = block.ops.len();
+                block.ops.retain(|op| !Self::is_dead_temp(op, &used));
+                removed |= block.ops.len() != before;
+            }
+            
+            if !removed {
+                break;
+            }
+        }
+    }
+    
+    fn is_dead_temp(op: &QirOp, used: &HashSet<TempId>) -> bool {
+        match op {
+            QirOp::ClassicalAssign { target, value } => !used.contains(target) && matches!(
+                value,
+                QirValue::Int(_) | QirValue::Float(_) | QirValue::Bool(_) | QirValue::String(_) | QirValue::Null
+            ),
+            QirOp::BinaryOp { result, .. } | QirOp::UnaryOp { result, .. } => {
+                !used.contains(result) && Self::is_pure_expr(op)
+            }
+            _ => false,
+        }
+    }
+    
+    fn is_assigning_op(op: &BinaryOp) -> bool {
+        matches!(
+            op,
+            BinaryOp::Assign | BinaryOp::AddAssign | BinaryOp::SubAssign |
+            BinaryOp::MulAssign | BinaryOp::DivAssign
+        )
+    }
+    
+    fn is_pure_expr(op: &QirOp) -> bool {
+        match op {
+            QirOp::BinaryOp { op, .. } => !Self::is_assigning_op(op),
+            QirOp::UnaryOp { op, .. } => matches!(op, UnaryOp::Neg | UnaryOp::Not),
+            _ => false,
+        }
+    }
+    
+    fn same_expr(a: &QirOp, b: &QirOp) -> bool {
+        match (a, b) {
+            (QirOp::BinaryOp { op: op1, lhs: l1, rhs: r1, .. },
+             QirOp::BinaryOp { op: op2, lhs: l2, rhs: r2, .. }) => op1 == op2 && l1 == l2 && r1 == r2,
+            (QirOp::UnaryOp { op: op1, operand: v1, .. },
+             QirOp::UnaryOp { op: op2, operand: v2, .. }) => op1 == op2 && v1 == v2,
+            _ => false,
+        }
+    }
+    
+    fn expr_uses_value(expr: &QirOp, value: &QirValue) -> bool {
+        match expr {
+            QirOp::BinaryOp { lhs, rhs, .. } => lhs == value || rhs == value,
+            QirOp::UnaryOp { operand, .. } => operand == value,
             _ => false,
         }
     }
     
-    fn common_subexpression_elimination(&self, _func: &mut QirFunction) {
-        // Future Phase: CSE implementation
+    fn remap_value(value: &mut QirValue, remap: &HashMap<TempId, TempId>) {
+        match value {
+            QirValue::Temp(id) => {
+                if let Some(new_id) = remap.get(id) {
+                    *id = *new_id;
+                }
+            }
+            QirValue::Tuple(vals) | QirValue::Array(vals) => {
+                for v in vals {
+                    Self::remap_value(v, remap);
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    fn remap_op_temps(op: &mut QirOp, remap: &HashMap<TempId, TempId>) {
+        if remap.is_empty() {
+            return;
+        }
+        
+        let remap_temp = |id: &mut TempId| {
+            if let Some(new_id) = remap.get(id) {
+                *id = *new_id;
+            }
+        };
+        
+        match op {
+            QirOp::ApplyGate { args, .. } => {
+                for arg in args {
+                    Self::remap_value(arg, remap);
+                }
+            }
+            QirOp::ClassicalAssign { value, .. } => Self::remap_value(value, remap),
+            QirOp::BinaryOp { lhs, rhs, .. } => {
+                Self::remap_value(lhs, remap);
+                Self::remap_value(rhs, remap);
+            }
+            QirOp::UnaryOp { operand, .. } => Self::remap_value(operand, remap),
+            QirOp::Branch { cond, .. } => Self::remap_value(cond, remap),
+            QirOp::Return { value: Some(value) } => Self::remap_value(value, remap),
+            QirOp::Load { ptr, .. } => remap_temp(ptr),
+            QirOp::Store { ptr, value } => {
+                remap_temp(ptr);
+                Self::remap_value(value, remap);
+            }
+            QirOp::GetElementPtr { base, .. } => remap_temp(base),
+            QirOp::MakeStruct { field_values, .. } => {
+                for v in field_values {
+                    Self::remap_value(v, remap);
+                }
+            }
+            QirOp::ExtractField { struct_val, .. } => Self::remap_value(struct_val, remap),
+            QirOp::InsertField { struct_val, value, .. } => {
+                Self::remap_value(struct_val, remap);
+                Self::remap_value(value, remap);
+            }
+            QirOp::MakeArray { elements, .. } => {
+                for v in elements {
+                    Self::remap_value(v, remap);
+                }
+            }
+            QirOp::ArrayGet { array, .. } => Self::remap_value(array, remap),
+            QirOp::ArraySet { array, value, .. } => {
+                Self::remap_value(array, remap);
+                Self::remap_value(value, remap);
+            }
+            QirOp::Phi { incoming, .. } => {
+                for (_, v) in incoming {
+                    Self::remap_value(v, remap);
+                }
+            }
+            _ => {}
+        }
     }
     
     fn remove_empty_blocks(&self, func: &mut QirFunction) {
         let mut to_remove = Vec::new();
         
-        for (&block_id, block) in &func.blocks {
+        for block_id in func.block_ids() {
+            let block = &func.blocks[&block_id];
             if block.ops.is_empty() && block_id != func.entry_block {
                 // Only remove blocks that are purely pass-through and have 1 successor
                 if block.successors.len() == 1 {
@@ -223,4 +1098,201 @@ impl QirOptimizer {
             // For now, we skip removing to ensure stability
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, Compiler};
+
+    fn temp(id: usize) -> QirValue {
+        QirValue::Temp(TempId::new(id))
+    }
+
+    fn ops(func: &QirFunction) -> Vec<&QirOp> {
+        func.block_ids().into_iter().flat_map(|id| func.blocks[&id].ops.iter()).collect()
+    }
+
+    fn optimized(body: &str) -> QirModule {
+        let source = format!("fn main() -> int {{ let q: qubit = |0>; {} let c: cbit = measure(q); return 0; }}", body);
+        Compiler::compile_to_qir(&source, &CompileOptions { optimize: true, ..CompileOptions::default() }).unwrap()
+    }
+
+    fn applied_gates(module: &QirModule) -> Vec<QirGate> {
+        ops(&module.functions[0]).into_iter()
+            .filter_map(|op| match op {
+                QirOp::ApplyGate { gate, .. } => Some(gate.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cse_collapses_repeated_binary_ops() {
+        let mut func = QirFunction::new("sum", Vec::new(), QirType::Int);
+        func.add_op(QirOp::BinaryOp { op: BinaryOp::Add, lhs: temp(0), rhs: temp(1), result: TempId::new(2) });
+        func.add_op(QirOp::BinaryOp { op: BinaryOp::Add, lhs: temp(0), rhs: temp(1), result: TempId::new(3) });
+        func.add_op(QirOp::BinaryOp { op: BinaryOp::Mul, lhs: temp(2), rhs: temp(3), result: TempId::new(4) });
+        func.add_op(QirOp::Return { value: Some(temp(4)) });
+
+        QirOptimizer::new(true).optimize_function(&mut func);
+
+        let ops = ops(&func);
+        let adds = ops.iter().filter(|op| matches!(op, QirOp::BinaryOp { op: BinaryOp::Add, .. })).count();
+        assert_eq!(adds, 1, "{:?}", ops);
+        assert!(ops.iter().any(|op| matches!(op,
+            QirOp::BinaryOp { op: BinaryOp::Mul, lhs, rhs, .. } if *lhs == temp(2) && *rhs == temp(2))), "{:?}", ops);
+    }
+
+    #[test]
+    fn adjacent_rotations_merge() {
+        let gates = applied_gates(&optimized("H(q); RZ(pi / 4, q); RZ(pi / 4, q);"));
+        assert_eq!(gates.len(), 2, "{:?}", gates);
+        match &gates[1] {
+            QirGate::RZ(angle) => assert!((angle.value().unwrap() - std::f64::consts::FRAC_PI_2).abs() < 1e-9, "{:?}", angle),
+            other => panic!("expected RZ, got {:?}", other),
+        }
+
+        let gates = applied_gates(&optimized("H(q); RZ(pi, q); RZ(pi, q);"));
+        assert_eq!(gates, vec![QirGate::H]);
+    }
+
+    #[test]
+    fn gates_do_not_cancel_across_a_barrier() {
+        assert_eq!(applied_gates(&optimized("H(q); barrier(q); H(q);")), vec![QirGate::H, QirGate::H]);
+        assert_eq!(applied_gates(&optimized("H(q); H(q);")), vec![]);
+        assert_eq!(applied_gates(&optimized("H(q); RZ(pi / 4, q); barrier(q); RZ(pi / 4, q);")).len(), 3);
+    }
+
+    #[test]
+    fn swap_decomposes_into_three_cnots_when_enabled() {
+        let source = "fn main() -> int { let a: qubit = |1>; let b: qubit = |0>; SWAP(a, b); \
+            let x: cbit = measure(a); let y: cbit = measure(b); return 0; }";
+        let compile = |decompose_swap| Compiler::compile_with_options(source, &CompileOptions { decompose_swap, ..CompileOptions::default() }).unwrap();
+
+        let result = compile(true);
+        assert_eq!(applied_gates(&result.ir), vec![QirGate::CNOT, QirGate::CNOT, QirGate::CNOT]);
+        // The stats also count the `x` preparing `|1>`
+        assert_eq!(result.stats.gates, 4);
+
+        let result = compile(false);
+        assert_eq!(applied_gates(&result.ir), vec![QirGate::SWAP]);
+        assert_eq!(result.stats.gates, 2);
+    }
+
+    #[test]
+    fn commuting_disjoint_gates_exposes_cancellation() {
+        let gates = applied_gates(&optimized("let p: qubit = |0>; H(q); Z(p); H(q); let d: cbit = measure(p);"));
+        assert_eq!(gates, vec![QirGate::Z]);
+
+        // Gates never move across a measurement of their qubit
+        let gates = applied_gates(&optimized("H(q); let d: cbit = measure(q); reset(q); H(q);"));
+        assert_eq!(gates, vec![QirGate::H, QirGate::H]);
+    }
+
+    #[test]
+    fn unused_qubits_are_removed_and_reindexed() {
+        let source = "fn main() -> int { let idle: qubit = |0>; let q: qubit = |0>; X(q); let c: cbit = measure(q); return 0; }";
+        let compile = |optimize| Compiler::compile_with_stats(source, optimize).unwrap();
+
+        assert_eq!(compile(false).ir.qubit_count(), 2);
+        let result = compile(true);
+        assert_eq!(result.ir.qubit_count(), 1);
+        assert!(result.qasm.contains("qreg q[1];"), "{}", result.qasm);
+        assert!(result.qasm.contains("x q[0];"), "{}", result.qasm);
+    }
+
+    fn cancelled(gates: Vec<QirGate>) -> Vec<QirGate> {
+        let mut func = QirFunction::new("pair", Vec::new(), QirType::Int);
+        let qubit = func.allocate_qubit();
+        for gate in gates {
+            func.add_op(QirOp::ApplyGate { gate, args: vec![QirValue::Qubit(qubit)], result: None });
+        }
+        let cbit = func.allocate_cbit();
+        func.add_op(QirOp::Measure { qubit, cbit });
+        QirOptimizer::new(true).optimize_function(&mut func);
+        ops(&func).into_iter()
+            .filter_map(|op| match op {
+                QirOp::ApplyGate { gate, .. } => Some(gate.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inverse_pairs_cancel() {
+        assert_eq!(cancelled(vec![QirGate::S, QirGate::Sdg]), vec![]);
+        assert_eq!(cancelled(vec![QirGate::Tdg, QirGate::T]), vec![]);
+        assert_eq!(cancelled(vec![QirGate::S, QirGate::Tdg]), vec![QirGate::S, QirGate::Tdg]);
+    }
+
+    #[test]
+    fn opposite_rotations_cancel() {
+        assert_eq!(applied_gates(&optimized("H(q); RZ(pi / 3, q); RZ(-pi / 3, q);")), vec![QirGate::H]);
+    }
+
+    #[test]
+    fn measured_qubits_are_reused_by_later_allocations() {
+        let source = "fn main() -> int {
+            for i in range(0, 3) { let a: qubit = |0>; H(a); let m: cbit = measure(a); }
+            return 0;
+        }";
+        let reused = Compiler::compile_with_options(source, &CompileOptions { reuse_ancillas: true, ..CompileOptions::default() }).unwrap();
+        assert_eq!(reused.stats.qubits, 1, "{}", reused.qasm);
+        assert_eq!(reused.qasm.matches("reset q[0];").count(), 2, "{}", reused.qasm);
+        assert_eq!(reused.qasm.matches("measure q[0] -> c[").count(), 3, "{}", reused.qasm);
+
+        let plain = Compiler::compile_with_options(source, &CompileOptions::default()).unwrap();
+        assert_eq!(plain.stats.qubits, 3);
+        assert!(!plain.qasm.contains("reset"));
+    }
+
+    #[test]
+    fn adjacent_single_qubit_gates_fuse_into_u3() {
+        let source = "fn main() -> int { let q: qubit = |0>; H(q); S(q); H(q); let c: cbit = measure(q); return 0; }";
+        let module = Compiler::compile_to_qir(source, &CompileOptions { fuse_single_qubit: true, ..CompileOptions::default() }).unwrap();
+        let gates = applied_gates(&module);
+        let [QirGate::U3(theta, phi, lambda)] = gates[..] else {
+            panic!("expected one U3, got {:?}", gates);
+        };
+
+        let h = unitary::gate_matrix(&QirGate::H).unwrap();
+        let s = unitary::gate_matrix(&QirGate::S).unwrap();
+        let product = unitary::multiply(&h, &unitary::multiply(&s, &h));
+        assert!(unitary::equal_up_to_phase(&unitary::u3_matrix(theta, phi, lambda), &product, 1e-9));
+    }
+
+    #[test]
+    fn report_attributes_removed_gates_to_their_pass() {
+        let source = "fn main() -> int { let q: qubit = |0>; H(q); H(q); let c: cbit = measure(q); return 0; }";
+        let mut module = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        let report = QirOptimizer::new(true).optimize_module(&mut module);
+
+        let cancellation = report.get("gate_cancellation").unwrap();
+        assert_eq!((cancellation.gates_removed, cancellation.gates_added), (2, 0));
+        assert_eq!(report.passes.iter().map(|pass| pass.gates_removed).sum::<usize>(), 2);
+        assert_eq!(report.hottest().len(), report.passes.len());
+        assert!(applied_gates(&module).is_empty());
+    }
+
+    #[test]
+    fn dead_classical_temps_are_removed() {
+        let classical = |func: &QirFunction| ops(func).into_iter()
+            .filter(|op| matches!(op, QirOp::BinaryOp { .. } | QirOp::ClassicalAssign { .. }))
+            .count();
+        let source = "fn main() -> int { let mut a: int = 1; let unused: int = a + 2; let q: qubit = |0>; H(q); let c: cbit = measure(q); return 0; }";
+        let plain = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        assert_eq!(classical(&plain.functions[0]), 1);
+        let optimized = Compiler::compile_to_qir(source, &CompileOptions { optimize: true, ..CompileOptions::default() }).unwrap();
+        assert_eq!(classical(&optimized.functions[0]), 0);
+
+        // A chain of dead temps goes too, while a returned one stays
+        let mut func = QirFunction::new("chain", Vec::new(), QirType::Int);
+        func.add_op(QirOp::ClassicalAssign { target: TempId::new(0), value: QirValue::Int(1) });
+        func.add_op(QirOp::BinaryOp { op: BinaryOp::Add, lhs: temp(0), rhs: QirValue::Int(2), result: TempId::new(1) });
+        func.add_op(QirOp::ClassicalAssign { target: TempId::new(2), value: QirValue::Int(3) });
+        func.add_op(QirOp::Return { value: Some(temp(2)) });
+        QirOptimizer::new(true).optimize_function(&mut func);
+        assert_eq!(classical(&func), 1, "{:?}", ops(&func));
+    }
+}
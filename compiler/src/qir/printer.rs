@@ -0,0 +1,137 @@
+// src/qir/printer.rs - Human-readable text form of QIR, for debugging dumps
+use super::*;
+
+/// Lowercase gate name with its parameters, e.g. `rz(pi/3)` or `sdg`.
+fn gate_text(gate: &QirGate) -> String {
+    match gate {
+        QirGate::RX(angle) | QirGate::RY(angle) | QirGate::RZ(angle) |
+        QirGate::CRX(angle) | QirGate::CRY(angle) | QirGate::CRZ(angle) => {
+            format!("{}({})", gate.base_name(), angle)
+        }
+        QirGate::U3(theta, phi, lambda) => format!("u3({}, {}, {})", theta, phi, lambda),
+        _ => gate.base_name(),
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items.iter().map(|item| item.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+impl fmt::Display for QirValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QirValue::Qubit(qubit) => write!(f, "{}", qubit),
+            QirValue::Cbit(cbit) => write!(f, "{}", cbit),
+            QirValue::Int(n) => write!(f, "{}", n),
+            QirValue::Float(x) => write!(f, "{:?}", x),
+            QirValue::Bool(b) => write!(f, "{}", b),
+            QirValue::String(s) => write!(f, "{:?}", s),
+            QirValue::Tuple(elements) => write!(f, "({})", join(elements)),
+            QirValue::Array(elements) => write!(f, "[{}]", join(elements)),
+            QirValue::Temp(temp) => write!(f, "{}", temp),
+            QirValue::Variable(name) => write!(f, "@{}", name),
+            QirValue::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl fmt::Display for QirOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QirOp::AllocQubit { result, init_state } => match init_state {
+                Some(state) => write!(f, "{} = alloc_qubit {:?}", result, state),
+                None => write!(f, "{} = alloc_qubit", result),
+            },
+            QirOp::ApplyGate { gate, args, result } => match result {
+                Some(result) => write!(f, "{} = {} {}", result, gate_text(gate), join(args)),
+                None => write!(f, "{} {}", gate_text(gate), join(args)),
+            },
+            QirOp::Measure { qubit, cbit } => write!(f, "measure {} -> {}", qubit, cbit),
+            QirOp::Reset { qubit } => write!(f, "reset {}", qubit),
+            QirOp::Barrier { qubits } => write!(f, "barrier {}", join(qubits)),
+            QirOp::ConditionalGate { cbit, value, gate, qubits } => {
+                write!(f, "if {} == {}: {} {}", cbit, value, gate_text(gate), join(qubits))
+            }
+            QirOp::AllocCbit { result, init_value } => match init_value {
+                Some(value) => write!(f, "{} = alloc_cbit {}", result, value),
+                None => write!(f, "{} = alloc_cbit", result),
+            },
+            QirOp::ClassicalAssign { target, value } => write!(f, "{} = {}", target, value),
+            QirOp::BinaryOp { op, lhs, rhs, result } => {
+                write!(f, "{} = {} {}, {}", result, format!("{:?}", op).to_lowercase(), lhs, rhs)
+            }
+            QirOp::UnaryOp { op, operand, result } => {
+                write!(f, "{} = {} {}", result, format!("{:?}", op).to_lowercase(), operand)
+            }
+            QirOp::Jump { target } => write!(f, "jump {}", target),
+            QirOp::Branch { cond, then_block, else_block } => {
+                write!(f, "branch {}, {}, {}", cond, then_block, else_block)
+            }
+            QirOp::Return { value: Some(value) } => write!(f, "return {}", value),
+            QirOp::Return { value: None } => write!(f, "return"),
+            QirOp::Load { ptr, result } => write!(f, "{} = load {}", result, ptr),
+            QirOp::Store { ptr, value } => write!(f, "store {}, {}", ptr, value),
+            QirOp::GetElementPtr { base, indices, result } => {
+                write!(f, "{} = gep {}, {}", result, base, join(indices))
+            }
+            QirOp::MakeStruct { field_values, result } => {
+                write!(f, "{} = make_struct {}", result, join(field_values))
+            }
+            QirOp::ExtractField { struct_val, field_index, result } => {
+                write!(f, "{} = extract_field {}, {}", result, struct_val, field_index)
+            }
+            QirOp::InsertField { struct_val, field_index, value, result } => {
+                write!(f, "{} = insert_field {}, {}, {}", result, struct_val, field_index, value)
+            }
+            QirOp::MakeArray { elements, result } => write!(f, "{} = make_array {}", result, join(elements)),
+            QirOp::ArrayGet { array, index, result } => write!(f, "{} = array_get {}, {}", result, array, index),
+            QirOp::ArraySet { array, index, value, result } => {
+                write!(f, "{} = array_set {}, {}, {}", result, array, index, value)
+            }
+            QirOp::Phi { incoming, result } => {
+                let incoming: Vec<String> = incoming.iter()
+                    .map(|(block, value)| format!("[{}: {}]", block, value))
+                    .collect();
+                write!(f, "{} = phi {}", result, incoming.join(", "))
+            }
+            QirOp::Comment(text) => write!(f, "; {}", text),
+        }
+    }
+}
+
+impl fmt::Display for QirFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self.params.iter()
+            .map(|param| format!("{}: {:?}", param.name, param.ty))
+            .collect();
+        writeln!(f, "func {}({}) -> {:?} {{", self.name, params.join(", "), self.return_type)?;
+
+        // Blocks in creation order so dumps of the same program always match
+        for block_id in self.block_ids() {
+            writeln!(f, "{}:", block_id)?;
+            for op in &self.blocks[&block_id].ops {
+                writeln!(f, "    {}", op)?;
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for QirModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "; module {} (QIR {})", self.name, self.version)?;
+        if !self.global_qubits.is_empty() {
+            writeln!(f, "; global qubits: {}", join(&self.global_qubits))?;
+        }
+        if !self.global_cbits.is_empty() {
+            writeln!(f, "; global cbits: {}", join(&self.global_cbits))?;
+        }
+
+        for func in &self.functions {
+            writeln!(f)?;
+            write!(f, "{}", func)?;
+        }
+        Ok(())
+    }
+}
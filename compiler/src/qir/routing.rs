@@ -0,0 +1,198 @@
+// src/qir/routing.rs - SWAP insertion for devices with limited connectivity
+use super::*;
+use std::collections::VecDeque;
+
+/// Inserts SWAPs so that two-qubit gates act on physical qubits joined by
+/// an edge of `coupling_map`, returning how many were inserted.
+///
+/// `layout` gives the physical qubit of each qubit id, as from
+/// [`QASMGenerator::physical_qubits`](crate::codegen::QASMGenerator::physical_qubits).
+/// Before a gate on non-adjacent qubits, the first operand is swapped along
+/// a shortest path until it neighbours the second; later ops, measurements
+/// included, follow the qubits to their new places. Only straight-line
+/// functions are routed: with branches the placement would differ between
+/// paths, so such functions are left for the connectivity check to report.
+pub fn route_module(module: &mut QirModule, coupling_map: &[(usize, usize)], layout: &HashMap<usize, usize>) -> usize {
+    let mut neighbours: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(a, b) in coupling_map {
+        neighbours.entry(a).or_default().push(b);
+        neighbours.entry(b).or_default().push(a);
+    }
+    for list in neighbours.values_mut() {
+        list.sort_unstable();
+        list.dedup();
+    }
+
+    module.functions.iter_mut()
+        .map(|func| route_function(func, &neighbours, layout))
+        .sum()
+}
+
+fn route_function(func: &mut QirFunction, neighbours: &HashMap<usize, Vec<usize>>, layout: &HashMap<usize, usize>) -> usize {
+    let branches = func.blocks.values()
+        .flat_map(|block| &block.ops)
+        .any(|op| matches!(op, QirOp::Jump { .. } | QirOp::Branch { .. }));
+    if branches {
+        return 0;
+    }
+
+    let mut router = Router::new(layout);
+    let block = match func.blocks.get_mut(&func.entry_block) {
+        Some(block) => block,
+        None => return 0,
+    };
+
+    let mut ops = Vec::with_capacity(block.ops.len());
+    for op in block.ops.drain(..) {
+        if let Some((a, b)) = two_qubit_operands(&op) {
+            router.bring_together(a, b, neighbours, &mut ops);
+        }
+        ops.push(router.remap(op));
+    }
+    block.ops = ops;
+
+    router.swaps
+}
+
+/// The qubit ids of a gate on exactly two qubits.
+fn two_qubit_operands(op: &QirOp) -> Option<(usize, usize)> {
+    match op {
+        QirOp::ApplyGate { args, .. } => match args.as_slice() {
+            [QirValue::Qubit(a), QirValue::Qubit(b)] => Some((a.id(), b.id())),
+            _ => None,
+        },
+        QirOp::ConditionalGate { qubits, .. } => match qubits.as_slice() {
+            [a, b] => Some((a.id(), b.id())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Tracks which physical qubit holds each logical qubit. Physical qubit `p`
+/// is written as the qubit id the layout places there.
+struct Router {
+    id_at: HashMap<usize, usize>,
+    position: HashMap<usize, usize>,
+    occupant: HashMap<usize, usize>,
+    swaps: usize,
+}
+
+impl Router {
+    fn new(layout: &HashMap<usize, usize>) -> Self {
+        let id_at: HashMap<usize, usize> = layout.iter().map(|(&id, &physical)| (physical, id)).collect();
+        Self {
+            occupant: id_at.clone(),
+            position: layout.clone(),
+            id_at,
+            swaps: 0,
+        }
+    }
+
+    /// Swaps logical qubit `a` towards `b` until they are adjacent.
+    fn bring_together(&mut self, a: usize, b: usize, neighbours: &HashMap<usize, Vec<usize>>, ops: &mut Vec<QirOp>) {
+        let (Some(&from), Some(&to)) = (self.position.get(&a), self.position.get(&b)) else {
+            return;
+        };
+        let Some(path) = self.shortest_path(from, to, neighbours) else {
+            return;
+        };
+
+        // The path ends at `b`, so stop one step short of it
+        for step in path.windows(2).take(path.len().saturating_sub(2)) {
+            let (here, next) = (step[0], step[1]);
+            ops.push(QirOp::ApplyGate {
+                gate: QirGate::SWAP,
+                args: vec![
+                    QirValue::Qubit(QubitId::new(self.id_at[&here])),
+                    QirValue::Qubit(QubitId::new(self.id_at[&next])),
+                ],
+                result: None,
+            });
+
+            let (moving, displaced) = (self.occupant[&here], self.occupant[&next]);
+            self.occupant.insert(here, displaced);
+            self.occupant.insert(next, moving);
+            self.position.insert(moving, next);
+            self.position.insert(displaced, here);
+            self.swaps += 1;
+        }
+    }
+
+    /// Breadth-first search over physical qubits that exist in the layout.
+    fn shortest_path(&self, from: usize, to: usize, neighbours: &HashMap<usize, Vec<usize>>) -> Option<Vec<usize>> {
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        previous.insert(from, from);
+
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to];
+                let mut current = to;
+                while current != from {
+                    current = previous[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &next in neighbours.get(&node).into_iter().flatten() {
+                if self.id_at.contains_key(&next) && !previous.contains_key(&next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// The id now standing for logical qubit `id`'s physical position.
+    fn current(&self, id: usize) -> QubitId {
+        let physical = self.position.get(&id);
+        QubitId::new(physical.map(|p| self.id_at[p]).unwrap_or(id))
+    }
+
+    fn remap(&self, op: QirOp) -> QirOp {
+        let remap_value = |value: QirValue| match value {
+            QirValue::Qubit(qubit) => QirValue::Qubit(self.current(qubit.id())),
+            other => other,
+        };
+
+        match op {
+            QirOp::ApplyGate { gate, args, result } => QirOp::ApplyGate {
+                gate,
+                args: args.into_iter().map(remap_value).collect(),
+                result,
+            },
+            QirOp::Measure { qubit, cbit } => QirOp::Measure { qubit: self.current(qubit.id()), cbit },
+            QirOp::Reset { qubit } => QirOp::Reset { qubit: self.current(qubit.id()) },
+            QirOp::Barrier { qubits } => QirOp::Barrier {
+                qubits: qubits.iter().map(|q| self.current(q.id())).collect(),
+            },
+            QirOp::ConditionalGate { cbit, value, gate, qubits } => QirOp::ConditionalGate {
+                cbit,
+                value,
+                gate,
+                qubits: qubits.iter().map(|q| self.current(q.id())).collect(),
+            },
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, Compiler};
+
+    #[test]
+    fn distant_cnot_on_a_line_gets_a_swap() {
+        let source = "fn main() -> int { qreg r[3] = |000>; H(r[0]); CNOT(r[0], r[2]); return 0; }";
+        let line = CompileOptions { coupling_map: Some(vec![(0, 1), (1, 2)]), ..CompileOptions::default() };
+        let plain = Compiler::compile_with_options(source, &line).err().unwrap();
+        assert!(plain[0].contains("not adjacent"), "{:?}", plain);
+
+        let routed = Compiler::compile_with_options(source, &CompileOptions { route: true, ..line }).unwrap();
+        assert!(routed.qasm.contains("swap r[0], r[1];\n  cx r[1], r[2];"), "{}", routed.qasm);
+        assert_eq!(routed.stats.gates, 3);
+    }
+}
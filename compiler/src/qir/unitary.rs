@@ -0,0 +1,122 @@
+// src/qir/unitary.rs - 2x2 unitaries of single-qubit gates, for gate fusion
+use super::QirGate;
+use num_complex::Complex;
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_4, PI};
+
+/// A single-qubit unitary, indexed `[row][column]`.
+pub type Matrix2 = [[Complex<f64>; 2]; 2];
+
+const EPSILON: f64 = 1e-9;
+
+fn complex(re: f64, im: f64) -> Complex<f64> {
+    Complex::new(re, im)
+}
+
+/// The unitary of a single-qubit gate, or `None` for multi-qubit gates,
+/// custom gates and rotations by a symbolic angle.
+pub fn gate_matrix(gate: &QirGate) -> Option<Matrix2> {
+    let (zero, one) = (complex(0.0, 0.0), complex(1.0, 0.0));
+    let phase = |angle: f64| [[one, zero], [zero, Complex::from_polar(1.0, angle)]];
+    
+    Some(match gate {
+        QirGate::H => {
+            let h = complex(FRAC_1_SQRT_2, 0.0);
+            [[h, h], [h, -h]]
+        }
+        QirGate::X => [[zero, one], [one, zero]],
+        QirGate::Y => [[zero, complex(0.0, -1.0)], [complex(0.0, 1.0), zero]],
+        QirGate::Z => phase(PI),
+        QirGate::S => phase(PI / 2.0),
+        QirGate::Sdg => phase(-PI / 2.0),
+        QirGate::T => phase(FRAC_PI_4),
+        QirGate::Tdg => phase(-FRAC_PI_4),
+        QirGate::RX(angle) => {
+            let half = angle.value()? / 2.0;
+            [[complex(half.cos(), 0.0), complex(0.0, -half.sin())], [complex(0.0, -half.sin()), complex(half.cos(), 0.0)]]
+        }
+        QirGate::RY(angle) => {
+            let half = angle.value()? / 2.0;
+            [[complex(half.cos(), 0.0), complex(-half.sin(), 0.0)], [complex(half.sin(), 0.0), complex(half.cos(), 0.0)]]
+        }
+        QirGate::RZ(angle) => {
+            let half = angle.value()? / 2.0;
+            [[Complex::from_polar(1.0, -half), zero], [zero, Complex::from_polar(1.0, half)]]
+        }
+        QirGate::U3(theta, phi, lambda) => u3_matrix(*theta, *phi, *lambda),
+        _ => return None,
+    })
+}
+
+/// `U3(θ,φ,λ)` as defined by OpenQASM 2.
+pub fn u3_matrix(theta: f64, phi: f64, lambda: f64) -> Matrix2 {
+    let (cos, sin) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+    [
+        [complex(cos, 0.0), -Complex::from_polar(sin, lambda)],
+        [Complex::from_polar(sin, phi), Complex::from_polar(cos, phi + lambda)],
+    ]
+}
+
+/// The matrix product `a · b`, i.e. `b` applied first, then `a`.
+pub fn multiply(a: &Matrix2, b: &Matrix2) -> Matrix2 {
+    let mut product = [[complex(0.0, 0.0); 2]; 2];
+    for (row, product_row) in product.iter_mut().enumerate() {
+        for (column, entry) in product_row.iter_mut().enumerate() {
+            *entry = a[row][0] * b[0][column] + a[row][1] * b[1][column];
+        }
+    }
+    product
+}
+
+/// Euler angles `(θ, φ, λ)` with `U3(θ,φ,λ)` equal to `matrix` up to a
+/// global phase. `matrix` must be unitary.
+pub fn u3_angles(matrix: &Matrix2) -> (f64, f64, f64) {
+    let [[a, b], [c, d]] = *matrix;
+    let theta = 2.0 * c.norm().atan2(a.norm());
+    
+    // U3 has a real, non-negative top-left entry, which fixes the phase;
+    // when that entry vanishes, pick φ = 0 instead
+    let (phi, lambda) = if a.norm() > EPSILON {
+        let global = a.arg();
+        if c.norm() > EPSILON {
+            (c.arg() - global, (-b).arg() - global)
+        } else {
+            (0.0, d.arg() - global)
+        }
+    } else {
+        (0.0, (-b).arg() - c.arg())
+    };
+    
+    (normalize(theta), normalize(phi), normalize(lambda))
+}
+
+/// True when `a` and `b` differ only by a global phase.
+pub fn equal_up_to_phase(a: &Matrix2, b: &Matrix2, tolerance: f64) -> bool {
+    // Line the phases up on the largest entry of `a`, which can't be zero
+    let (row, column) = [(0, 0), (0, 1), (1, 0), (1, 1)].into_iter()
+        .max_by(|&(r1, c1), &(r2, c2)| a[r1][c1].norm().total_cmp(&a[r2][c2].norm()))
+        .unwrap_or((0, 0));
+    if b[row][column].norm() < EPSILON {
+        return false;
+    }
+    let phase = a[row][column] / b[row][column];
+    
+    (0..2).all(|r| (0..2).all(|col| (a[r][col] - phase * b[r][col]).norm() < tolerance))
+}
+
+/// True when `matrix` is the identity up to a global phase.
+pub fn is_identity(matrix: &Matrix2) -> bool {
+    let identity = [[complex(1.0, 0.0), complex(0.0, 0.0)], [complex(0.0, 0.0), complex(1.0, 0.0)]];
+    equal_up_to_phase(&identity, matrix, 1e-9)
+}
+
+/// Maps an angle into `(-π, π]`, snapping values within rounding error of
+/// zero to exactly zero.
+fn normalize(angle: f64) -> f64 {
+    let mut angle = angle % (2.0 * PI);
+    if angle > PI {
+        angle -= 2.0 * PI;
+    } else if angle <= -PI {
+        angle += 2.0 * PI;
+    }
+    if angle.abs() < EPSILON { 0.0 } else { angle }
+}
@@ -1,17 +1,44 @@
 // src/semantics/analyzer.rs - FULLY CORRECTED
-use crate::ast::{Program, Function, Stmt, Expr, Type, Span, BinaryOp, UnaryOp};
+use crate::ast::{Program, Function, Stmt, Expr, Type, Span, BinaryOp, UnaryOp, Gate};
+use crate::ast::pretty::format_expr;
 use crate::semantics::symbols::{SymbolTable, TypeRegistry, Symbol};
 use crate::semantics::errors::SemanticError;
+use crate::semantics::constants::{eval_const, ConstValue};
+use crate::error::Diagnostic;
+use std::collections::{HashMap, HashSet};
+
+/// Affine ownership state of a quantum variable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum QubitState {
+    Available,
+    Measured,
+    Consumed,
+}
 
 #[derive(Debug)]
 pub struct SemanticAnalyzer {
     pub symbol_table: SymbolTable,
     pub type_registry: TypeRegistry,
     pub errors: Vec<SemanticError>,
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Diagnostic>,
     pub current_function: Option<String>,
     pub in_quantum_context: bool,
     pub loop_depth: usize,
+    /// Warn wherever an `int` is implicitly widened to `float`.
+    pub warn_coercion: bool,
+    /// Warn when a declaration in a nested scope hides an outer variable.
+    pub warn_shadowing: bool,
+    /// Error when a qubit allocated in a function is never measured, reset,
+    /// returned or passed to another function.
+    pub deny_quantum_leaks: bool,
+    // Parallel to the symbol table scopes; keyed by variable or `struct.field`
+    qubit_states: Vec<HashMap<String, QubitState>>,
+    // Values of immutable lets with constant initializers, per scope
+    constants: Vec<HashMap<String, ConstValue>>,
+    // Where each binding was declared, per scope
+    declared_at: Vec<HashMap<String, Span>>,
+    // Bindings that were measured, reset, returned or passed on, per scope
+    released: Vec<HashSet<String>>,
 }
 
 impl SemanticAnalyzer {
@@ -24,6 +51,223 @@ impl SemanticAnalyzer {
             current_function: None,
             in_quantum_context: false,
             loop_depth: 0,
+            warn_coercion: false,
+            warn_shadowing: false,
+            deny_quantum_leaks: false,
+            qubit_states: vec![HashMap::new()],
+            constants: vec![HashMap::new()],
+            declared_at: vec![HashMap::new()],
+            released: vec![HashSet::new()],
+        }
+    }
+    
+    fn push_scope(&mut self) {
+        self.symbol_table.push_scope();
+        self.qubit_states.push(HashMap::new());
+        self.constants.push(HashMap::new());
+        self.declared_at.push(HashMap::new());
+        self.released.push(HashSet::new());
+    }
+    
+    fn pop_scope(&mut self) {
+        if self.deny_quantum_leaks && self.qubit_states.len() > 1 {
+            self.check_quantum_leaks();
+        }
+        self.symbol_table.pop_scope();
+        if self.qubit_states.len() > 1 {
+            self.qubit_states.pop();
+            self.constants.pop();
+            self.declared_at.pop();
+            self.released.pop();
+        }
+    }
+    
+    /// Reports the quantum bindings of the innermost scope that were never
+    /// released, in one error at the first of them.
+    fn check_quantum_leaks(&mut self) {
+        let (Some(declared), Some(released)) = (self.declared_at.last(), self.released.last()) else {
+            return;
+        };
+        let mut leaked: Vec<(&String, &Span)> = declared.iter()
+            .filter(|(name, _)| !released.contains(*name))
+            .filter(|(name, _)| self.symbol_table.lookup_variable(name)
+                .is_some_and(|(ty, _, _)| matches!(self.type_registry.is_quantum_type(ty), Ok(true))))
+            .collect();
+        if leaked.is_empty() {
+            return;
+        }
+        leaked.sort_by_key(|(_, span)| (span.line, span.column));
+        
+        let names: Vec<String> = leaked.iter().map(|(name, _)| format!("'{}'", name)).collect();
+        let error = SemanticError::new(
+            leaked[0].1,
+            &format!("Quantum {} never measured, reset, returned or passed to a function: {}",
+                     if names.len() == 1 { "variable" } else { "variables" }, names.join(", ")),
+            Some("--deny-quantum-leaks requires every allocated qubit to be measured or handed on before the end of its scope"),
+        );
+        self.errors.push(error);
+    }
+    
+    /// Marks `name` as released in the scope that declared it.
+    fn release(&mut self, name: &str) {
+        if let Some(depth) = self.qubit_states.iter().rposition(|scope| scope.contains_key(name)) {
+            self.released[depth].insert(name.to_string());
+        }
+    }
+    
+    /// Releases the variable `expr` is rooted in (`q`, `s.field`, `r[i]`).
+    fn release_operand(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(name, _) => self.release(name),
+            Expr::MemberAccess(base, _, _) | Expr::Index(base, _, _) => self.release_operand(base),
+            _ => {}
+        }
+    }
+    
+    /// Records a binding declared at `span`. It starts out available,
+    /// shadowing any state of an outer one.
+    fn declare_binding(&mut self, name: &str, span: &Span) {
+        self.note_shadowing(name, span);
+        if let Some(scope) = self.qubit_states.last_mut() {
+            scope.insert(name.to_string(), QubitState::Available);
+        }
+        if let Some(scope) = self.declared_at.last_mut() {
+            scope.insert(name.to_string(), span.clone());
+        }
+    }
+    
+    /// Under `warn_shadowing`, warns when `name` is already bound in an
+    /// enclosing scope. Redeclaring in the same scope is an error instead.
+    fn note_shadowing(&mut self, name: &str, span: &Span) {
+        if !self.warn_shadowing {
+            return;
+        }
+        let outer_scopes = &self.declared_at[..self.declared_at.len().saturating_sub(1)];
+        if let Some(outer) = outer_scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            let warning = Diagnostic::warning(&format!(
+                "Variable '{}' shadows the declaration at line {}:{}",
+                name, outer.line, outer.column
+            ));
+            self.warnings.push(warning.with_span(span.clone()));
+        }
+    }
+    
+    /// The constant value of `name`, if the binding it refers to is one.
+    /// Every declaration has a `qubit_states` entry, so that finds the scope.
+    fn constant(&self, name: &str) -> Option<ConstValue> {
+        let depth = self.qubit_states.iter().rposition(|scope| scope.contains_key(name))?;
+        self.constants[depth].get(name).copied()
+    }
+    
+    fn eval_constant(&self, expr: &Expr) -> Option<ConstValue> {
+        eval_const(expr, &|name| {
+            let declared = self.qubit_states.iter().any(|scope| scope.contains_key(name));
+            if declared {
+                self.constant(name)
+            } else {
+                (name == "pi").then_some(ConstValue::Float(std::f64::consts::PI))
+            }
+        })
+    }
+    
+    fn qubit_state(&self, key: &str) -> QubitState {
+        let name = key.split('.').next().unwrap_or(key);
+        for scope in self.qubit_states.iter().rev() {
+            if scope.contains_key(name) {
+                return scope.get(key).copied().unwrap_or(QubitState::Available);
+            }
+        }
+        QubitState::Available
+    }
+    
+    /// Updates the state in the scope that declared the variable.
+    fn set_qubit_state(&mut self, key: &str, state: QubitState) {
+        let name = key.split('.').next().unwrap_or(key);
+        let scope = self.qubit_states.iter_mut().rev()
+            .find(|scope| scope.contains_key(name));
+        if let Some(scope) = scope {
+            scope.insert(key.to_string(), state);
+        }
+    }
+    
+    /// The state key for `q` or `s.field`; other operands aren't tracked.
+    fn qubit_state_key(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Variable(name, _) => Some(name.clone()),
+            Expr::MemberAccess(base, field, _) => match &**base {
+                Expr::Variable(name, _) => Some(format!("{}.{}", name, field)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    
+    /// Reports a quantum operand that was measured or moved out earlier.
+    fn check_qubit_available(&mut self, expr: &Expr, ty: &Type) {
+        if !matches!(self.type_registry.is_quantum_type(ty), Ok(true)) {
+            return;
+        }
+        let key = match Self::qubit_state_key(expr) {
+            Some(key) => key,
+            None => return,
+        };
+        
+        match self.qubit_state(&key) {
+            QubitState::Measured => self.errors.push(SemanticError::new(
+                expr.span(),
+                &format!("Qubit '{}' used after measurement", key),
+                Some("Quantum resources are affine and cannot be used after measurement; reset() makes a qubit usable again"),
+            )),
+            QubitState::Consumed => self.errors.push(SemanticError::new(
+                expr.span(),
+                &format!("Use of consumed qubit '{}'", key),
+                Some("Quantum resources moved out by 'return' cannot be used again"),
+            )),
+            QubitState::Available => {}
+        }
+    }
+    
+    /// Reports measuring a qubit that is already measured, at the second
+    /// `measure` call. The operand is then analyzed as available, so the
+    /// general use-after-measurement error isn't reported as well.
+    fn check_measured_twice(&mut self, qubit_expr: &Expr, span: &Span) {
+        let key = match Self::qubit_state_key(qubit_expr) {
+            Some(key) => key,
+            None => return,
+        };
+        if self.qubit_state(&key) == QubitState::Measured {
+            self.errors.push(SemanticError::new(
+                span,
+                &format!("Qubit '{}' already measured", key),
+                Some("Use the earlier measurement result, or reset() the qubit before measuring it again"),
+            ));
+            self.set_qubit_state(&key, QubitState::Available);
+        }
+    }
+    
+    /// Returning a quantum value moves it out of the current scope.
+    fn consume_returned_value(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(name, _) => {
+                let is_quantum = self.symbol_table.lookup_variable(name)
+                    .map(|(ty, _, _)| ty.clone())
+                    .is_some_and(|ty| matches!(self.type_registry.is_quantum_type(&ty), Ok(true)));
+                if is_quantum {
+                    self.set_qubit_state(name, QubitState::Consumed);
+                    self.release(name);
+                }
+            }
+            Expr::Tuple(elements, _) => {
+                for element in elements {
+                    self.consume_returned_value(element);
+                }
+            }
+            Expr::StructLiteral(_, fields, _) => {
+                for (_, value) in fields {
+                    self.consume_returned_value(value);
+                }
+            }
+            _ => {}
         }
     }
     
@@ -174,7 +418,7 @@ impl SemanticAnalyzer {
         self.current_function = Some(function.name.clone());
         
         // Push function scope
-        self.symbol_table.push_scope();
+        self.push_scope();
         
         // Add parameters to scope
         for param in &function.params {
@@ -192,6 +436,9 @@ impl SemanticAnalyzer {
                     Some("Parameter names must be unique"),
                 ));
             }
+            self.declare_binding(&param.name, &param.span);
+            // Allocated by the caller, which hands them over
+            self.release(&param.name);
         }
         
         // Analyze function body
@@ -199,13 +446,16 @@ impl SemanticAnalyzer {
             self.analyze_statement(stmt);
         }
         
+        self.check_unreachable_statements(&function.body);
+        self.check_unused_variables(function);
+        
         // Check if function has a return statement if needed
         if !matches!(function.return_type, Type::Unit) {
             // TODO: Implement return statement checking
-            self.warnings.push(format!(
+            self.warnings.push(Diagnostic::warning(&format!(
                 "Function '{}' has non-unit return type but return statement checking not implemented",
                 function.name
-            ));
+            )).with_span(function.span.clone()));
         }
         
         // Mark function as defined
@@ -218,10 +468,146 @@ impl SemanticAnalyzer {
         }
         
         // Pop function scope
-        self.symbol_table.pop_scope();
+        self.pop_scope();
         self.current_function = None;
     }
     
+    /// Warns about the first statement that follows a guaranteed return.
+    fn check_unreachable_statements(&mut self, stmts: &[Stmt]) {
+        if let Some(pos) = stmts.iter().position(Self::always_returns) {
+            if let Some(next) = stmts.get(pos + 1) {
+                self.warnings.push(Diagnostic::warning("Unreachable statement").with_span(next.span().clone()));
+            }
+        }
+    }
+    
+    fn always_returns(stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Return(_, _) => true,
+            Stmt::Block(stmts, _) => stmts.iter().any(Self::always_returns),
+            Stmt::If(_, then_branch, Some(else_branch), _) => {
+                Self::always_returns(then_branch) && Self::always_returns(else_branch)
+            }
+            _ => false,
+        }
+    }
+    
+    fn check_unused_variables(&mut self, function: &Function) {
+        let mut declared = Vec::new();
+        let mut used = HashSet::new();
+        for stmt in &function.body {
+            Self::collect_variable_usage(stmt, &mut declared, &mut used);
+        }
+        
+        for (name, ty, span) in declared {
+            // Leading underscore opts out, as in Rust
+            if used.contains(&name) || name.starts_with('_') {
+                continue;
+            }
+            
+            // Cbits are the circuit's output registers, so measuring into one counts as a use
+            let is_cbit = match &ty {
+                Type::Cbit => true,
+                Type::Array(elem, _) => **elem == Type::Cbit,
+                _ => false,
+            };
+            if is_cbit {
+                continue;
+            }
+            
+            let kind = match self.type_registry.is_quantum_type(&ty) {
+                Ok(true) => "Qubit",
+                _ => "Variable",
+            };
+            self.warnings.push(Diagnostic::warning(&format!("{} '{}' is never used", kind, name)).with_span(span));
+        }
+    }
+    
+    fn collect_variable_usage(stmt: &Stmt, declared: &mut Vec<(String, Type, Span)>, used: &mut HashSet<String>) {
+        match stmt {
+            Stmt::Let(name, ty, expr, _, span) => {
+                Self::collect_expr_usage(expr, used);
+                declared.push((name.clone(), ty.clone(), span.clone()));
+            }
+            Stmt::Assign(_, expr, _) | Stmt::Expr(expr, _) => Self::collect_expr_usage(expr, used),
+            Stmt::AssignIndex(name, index, expr, _) => {
+                used.insert(name.clone());
+                Self::collect_expr_usage(index, used);
+                Self::collect_expr_usage(expr, used);
+            }
+            Stmt::Return(Some(expr), _) => Self::collect_expr_usage(expr, used),
+            Stmt::Block(stmts, _) => {
+                for stmt in stmts {
+                    Self::collect_variable_usage(stmt, declared, used);
+                }
+            }
+            Stmt::If(condition, then_branch, else_branch, _) => {
+                Self::collect_expr_usage(condition, used);
+                Self::collect_variable_usage(then_branch, declared, used);
+                if let Some(else_branch) = else_branch {
+                    Self::collect_variable_usage(else_branch, declared, used);
+                }
+            }
+            Stmt::QIf(condition, then_branch, else_branch, _) => {
+                Self::collect_expr_usage(condition, used);
+                Self::collect_variable_usage(then_branch, declared, used);
+                if let Some(else_branch) = else_branch {
+                    Self::collect_variable_usage(else_branch, declared, used);
+                }
+            }
+            Stmt::While(condition, body, _) => {
+                Self::collect_expr_usage(condition, used);
+                Self::collect_variable_usage(body, declared, used);
+            }
+            Stmt::ForRange(_, start, end, step, body, _)
+            | Stmt::QForRange(_, start, end, step, body, _) => {
+                Self::collect_expr_usage(start, used);
+                Self::collect_expr_usage(end, used);
+                if let Some(step) = step {
+                    Self::collect_expr_usage(step, used);
+                }
+                Self::collect_variable_usage(body, declared, used);
+            }
+            _ => {}
+        }
+    }
+    
+    fn collect_expr_usage(expr: &Expr, used: &mut HashSet<String>) {
+        match expr {
+            Expr::Variable(name, _) => {
+                used.insert(name.clone());
+            }
+            Expr::BinaryOp(left, _, right, _) | Expr::Index(left, right, _)
+            | Expr::MeasureInto(left, right, _) => {
+                Self::collect_expr_usage(left, used);
+                Self::collect_expr_usage(right, used);
+            }
+            Expr::UnaryOp(_, operand, _) | Expr::MemberAccess(operand, _, _)
+            | Expr::Measure(operand, _) | Expr::Reset(operand, _) => {
+                Self::collect_expr_usage(operand, used);
+            }
+            Expr::Call(_, args, _) | Expr::Tuple(args, _) | Expr::Barrier(args, _) => {
+                for arg in args {
+                    Self::collect_expr_usage(arg, used);
+                }
+            }
+            Expr::GateApply(gate, args, _) => {
+                if let Some(angle) = gate.angle() {
+                    Self::collect_expr_usage(angle, used);
+                }
+                for arg in args {
+                    Self::collect_expr_usage(arg, used);
+                }
+            }
+            Expr::StructLiteral(_, fields, _) => {
+                for (_, value) in fields {
+                    Self::collect_expr_usage(value, used);
+                }
+            }
+            _ => {}
+        }
+    }
+    
     fn analyze_statement(&mut self, stmt: &Stmt) {
         match stmt {
             Stmt::Let(name, ty, expr, mutable, span) => {
@@ -240,6 +626,14 @@ impl SemanticAnalyzer {
                 self.analyze_assign_stmt(name, expr, span);
             }
             
+            Stmt::AssignIndex(name, index, expr, span) => {
+                self.analyze_assign_index_stmt(name, index, expr, span);
+            }
+            
+            Stmt::Expr(Expr::UnaryOp(op @ (UnaryOp::PostIncrement | UnaryOp::PostDecrement), operand, _), span) => {
+                self.analyze_step_stmt(stmt, op, operand, span);
+            }
+            
             Stmt::Expr(expr, span) => {
                 let _ = self.analyze_expression(expr, span);
             }
@@ -249,11 +643,12 @@ impl SemanticAnalyzer {
             }
             
             Stmt::Block(stmts, _span) => {
-                self.symbol_table.push_scope();
+                self.push_scope();
                 for stmt in stmts {
                     self.analyze_statement(stmt);
                 }
-                self.symbol_table.pop_scope();
+                self.pop_scope();
+                self.check_unreachable_statements(stmts);
             }
             
             Stmt::If(condition, then_branch, else_branch, _span) => {
@@ -308,7 +703,7 @@ impl SemanticAnalyzer {
                         span,
                         &format!("Bit string length {} doesn't match qreg size {}", 
                                 bit_string.bits.len(), size),
-                        Some("Bit string must have same length as qreg size"),
+                        Some(&format!("Expected |{}> for qreg[{}], got {} bits", "0".repeat(size), size, bit_string.bits.len())),
                     ));
                 }
             }
@@ -336,6 +731,7 @@ impl SemanticAnalyzer {
                 None,
             ));
         }
+        self.declare_binding(name, span);
     }
     
     fn analyze_array_declaration(&mut self, name: &str, element_type: &Type, size: usize, 
@@ -360,10 +756,42 @@ impl SemanticAnalyzer {
             }
         }
         
+        // Check initializer (a bare literal of the element type is the parser's
+        // default fill value; integers also fill cbit arrays)
+        let array_ty = Type::Array(Box::new(element_type.clone()), size);
+        let is_fill = match expr {
+            Expr::LiteralInt(_, _) => true,
+            Expr::LiteralFloat(_, _) => *element_type == Type::Float,
+            Expr::LiteralBool(_, _) => *element_type == Type::Bool,
+            Expr::LiteralString(_, _) => *element_type == Type::String,
+            _ => false,
+        };
+        if !is_fill {
+            match self.analyze_expression_type(expr) {
+                Ok(expr_ty) => {
+                    if !self.are_types_compatible(&array_ty, &expr_ty) {
+                        self.errors.push(SemanticError::new(
+                            span,
+                            &format!("Type mismatch: array declared as {:?} but expression has type {:?}", 
+                                    array_ty, expr_ty),
+                            Some("Measuring a qreg yields one cbit per qubit; sizes must match"),
+                        ));
+                    }
+                }
+                Err(e) => {
+                    self.errors.push(SemanticError::new(
+                        expr.span(),
+                        &e,
+                        Some("Expression type could not be determined"),
+                    ));
+                }
+            }
+        }
+        
         // Add array to symbol table
         let symbol = Symbol::Variable {
             name: name.to_string(),
-            ty: Type::Array(Box::new(element_type.clone()), size),
+            ty: array_ty,
             mutable,
             defined: true,
         };
@@ -375,6 +803,7 @@ impl SemanticAnalyzer {
                 None,
             ));
         }
+        self.declare_binding(name, span);
     }
     
     fn analyze_let_stmt(&mut self, name: &str, ty: &Type, expr: &Expr, mutable: bool, span: &Span) {
@@ -417,6 +846,7 @@ impl SemanticAnalyzer {
         // Check type compatibility
         match expr_ty {
             Ok(expr_ty_resolved) => {
+                self.note_coercion(&resolved_ty, &expr_ty_resolved, expr.span());
                 if !self.are_types_compatible(&resolved_ty, &expr_ty_resolved) {
                     self.errors.push(SemanticError::new(
                         span,
@@ -435,6 +865,13 @@ impl SemanticAnalyzer {
             }
         }
         
+        // Immutable lets with a foldable initializer are compile-time constants
+        let value = if mutable { None } else { self.eval_constant(expr) };
+        let value = match (value, &resolved_ty) {
+            (Some(ConstValue::Int(v)), Type::Float) => Some(ConstValue::Float(v as f64)),
+            (value, _) => value,
+        };
+        
         // Add variable to symbol table
         let symbol = Symbol::Variable {
             name: name.to_string(),
@@ -450,6 +887,38 @@ impl SemanticAnalyzer {
                 None,
             ));
         }
+        self.declare_binding(name, span);
+        if let (Some(value), Some(scope)) = (value, self.constants.last_mut()) {
+            scope.insert(name.to_string(), value);
+        }
+    }
+    
+    /// `x++;` and `x--;` are checked as `x = x + 1;` and `x = x - 1;`, but
+    /// only on int variables.
+    fn analyze_step_stmt(&mut self, stmt: &Stmt, op: &UnaryOp, operand: &Expr, span: &Span) {
+        let symbol = if *op == UnaryOp::PostIncrement { "++" } else { "--" };
+        let Some((name, value)) = stmt.step_assignment() else {
+            self.errors.push(SemanticError::new(
+                span,
+                &format!("Cannot apply '{}' to '{}'", symbol, format_expr(operand)),
+                Some("'++' and '--' only apply to mutable int variables"),
+            ));
+            return;
+        };
+        
+        let ty = self.symbol_table.lookup_variable(name)
+            .and_then(|(ty, _, _)| self.type_registry.resolve_type(ty).ok());
+        if let Some(ty) = ty {
+            if ty != Type::Int {
+                self.errors.push(SemanticError::new(
+                    span,
+                    &format!("Cannot apply '{}' to '{}' of type {:?}", symbol, name, ty),
+                    Some("'++' and '--' only apply to mutable int variables"),
+                ));
+                return;
+            }
+        }
+        self.analyze_assign_stmt(name, &value, span);
     }
     
     fn analyze_assign_stmt(&mut self, name: &str, expr: &Expr, span: &Span) {
@@ -496,6 +965,7 @@ impl SemanticAnalyzer {
         
         match expr_ty {
             Ok(expr_ty_resolved) => {
+                self.note_coercion(&var_ty, &expr_ty_resolved, expr.span());
                 if !self.are_types_compatible(&var_ty, &expr_ty_resolved) {
                     self.errors.push(SemanticError::new(
                         span,
@@ -515,6 +985,71 @@ impl SemanticAnalyzer {
         }
     }
     
+    /// `qs[i] = H(qs[i]);` applies a gate to a register element in place.
+    fn analyze_assign_index_stmt(&mut self, name: &str, index: &Expr, expr: &Expr, span: &Span) {
+        let target = Expr::Index(Box::new(Expr::Variable(name.to_string(), span.clone())), Box::new(index.clone()), span.clone());
+        let element_ty = match self.analyze_expression_type(&target) {
+            Ok(ty) => ty,
+            Err(e) => {
+                self.errors.push(SemanticError::new(
+                    span,
+                    &e,
+                    Some("Only elements of declared arrays and registers can be assigned"),
+                ));
+                return;
+            }
+        };
+        
+        if matches!(element_ty, Type::Int | Type::Float | Type::Bool | Type::String) {
+            self.analyze_assign_element_stmt(name, &element_ty, expr, span);
+            return;
+        }
+        
+        if !matches!(element_ty, Type::Qubit) {
+            self.errors.push(SemanticError::new(
+                span,
+                &format!("Cannot assign to element of '{}'", name),
+                Some("Element assignment is supported on qubit registers and mut int, float, bool and string arrays"),
+            ));
+            return;
+        }
+        
+        if !matches!(expr, Expr::GateApply(..)) {
+            self.errors.push(SemanticError::new(
+                span,
+                &format!("Cannot reassign qubit in register '{}'", name),
+                Some("Register elements can only be updated by applying a gate, e.g. qs[i] = H(qs[i])"),
+            ));
+            return;
+        }
+        
+        let _ = self.analyze_expression(expr, span);
+    }
+    
+    /// `counts[i] = value;` on a classical array: the array must be `mut` and
+    /// the value must fit the element type.
+    fn analyze_assign_element_stmt(&mut self, name: &str, element_ty: &Type, expr: &Expr, span: &Span) {
+        if let Some((_, false, _)) = self.symbol_table.lookup_variable(name) {
+            self.errors.push(SemanticError::new(
+                span,
+                &format!("Cannot assign to element of immutable array '{}'", name),
+                Some("Declare the array with 'mut' to make its elements assignable"),
+            ));
+        }
+        
+        if let Ok(expr_ty) = self.analyze_expression(expr, span) {
+            self.note_coercion(element_ty, &expr_ty, expr.span());
+            if !self.are_types_compatible(element_ty, &expr_ty) {
+                self.errors.push(SemanticError::new(
+                    span,
+                    &format!("Type mismatch in assignment: element of '{}' is {:?} but expression is {:?}",
+                            name, element_ty, expr_ty),
+                    Some("Assignment types must be compatible"),
+                ));
+            }
+        }
+    }
+    
     fn analyze_expression(&mut self, expr: &Expr, span: &Span) -> Result<Type, ()> {
         match self.analyze_expression_type(expr) {
             Ok(ty) => Ok(ty),
@@ -529,6 +1064,28 @@ impl SemanticAnalyzer {
         }
     }
     
+    /// Multi-qubit gates need a distinct qubit for every operand.
+    fn check_distinct_gate_operands(&mut self, gate: &Gate, args: &[Expr], span: &Span) {
+        let mut seen = HashSet::new();
+        
+        for arg in args {
+            // Only compare plain qubit references; anything else is checked at runtime
+            if !matches!(arg, Expr::Variable(..) | Expr::Index(..) | Expr::MemberAccess(..)) {
+                continue;
+            }
+            
+            let operand = format_expr(arg);
+            if !seen.insert(operand.clone()) {
+                self.errors.push(SemanticError::new(
+                    span,
+                    &format!("{:?} applied to the same qubit '{}' more than once", gate, operand),
+                    Some("Control and target of a multi-qubit gate must be different qubits"),
+                ));
+                return;
+            }
+        }
+    }
+    
     fn analyze_expression_type(&mut self, expr: &Expr) -> Result<Type, String> {
         match expr {
             Expr::LiteralInt(_, _) => Ok(Type::Int),
@@ -537,6 +1094,11 @@ impl SemanticAnalyzer {
             Expr::LiteralString(_, _) => Ok(Type::String),
             Expr::LiteralQubit(_, _) => Ok(Type::Qubit),
             
+            // `pi` is a built-in float constant unless shadowed by a local
+            Expr::Variable(name, _) if name == "pi" && self.symbol_table.lookup_variable(name).is_none() => {
+                Ok(Type::Float)
+            }
+            
             Expr::Variable(name, _) => {
                 let (ty, _, defined) = self.symbol_table.lookup_variable(name)
                     .ok_or_else(|| format!("Variable '{}' not found", name))?;
@@ -545,7 +1107,9 @@ impl SemanticAnalyzer {
                     return Err(format!("Variable '{}' used before initialization", name));
                 }
                 
-                self.type_registry.resolve_type(ty)
+                let ty = self.type_registry.resolve_type(ty)?;
+                self.check_qubit_available(expr, &ty);
+                Ok(ty)
             }
             
             Expr::BinaryOp(left, op, right, _) => {
@@ -554,6 +1118,8 @@ impl SemanticAnalyzer {
                 
                 match op {
                     BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                        self.note_coercion(&right_ty, &left_ty, left.span());
+                        self.note_coercion(&left_ty, &right_ty, right.span());
                         self.check_arithmetic_types(&left_ty, &right_ty, op.clone())
                     }
                     
@@ -565,10 +1131,17 @@ impl SemanticAnalyzer {
                         self.check_relational_types(&left_ty, &right_ty)
                     }
                     
-                    BinaryOp::And | BinaryOp::Or | BinaryOp::Xor => {
+                    BinaryOp::And | BinaryOp::Or => {
                         self.check_logical_types(&left_ty, &right_ty)
                     }
                     
+                    // Bitwise on ints, logical on bools
+                    BinaryOp::Xor => match (&left_ty, &right_ty) {
+                        (Type::Int, Type::Int) => Ok(Type::Int),
+                        (Type::Bool, Type::Bool) => Ok(Type::Bool),
+                        _ => Err(format!("Operator ^ needs two ints or two bools, got {:?} and {:?}", left_ty, right_ty)),
+                    },
+                    
                     BinaryOp::Assign => {
                         // Assignment returns the assigned type
                         Ok(right_ty)
@@ -577,6 +1150,7 @@ impl SemanticAnalyzer {
                     BinaryOp::AddAssign | BinaryOp::SubAssign | 
                     BinaryOp::MulAssign | BinaryOp::DivAssign => {
                         // Compound assignments require compatible types
+                        self.note_coercion(&left_ty, &right_ty, right.span());
                         if self.are_types_compatible(&left_ty, &right_ty) {
                             Ok(left_ty)
                         } else {
@@ -611,6 +1185,60 @@ impl SemanticAnalyzer {
                 }
             }
             
+            // Built-in float functions
+            Expr::Call(name, args, _) if matches!(name.as_str(), "sqrt" | "sin" | "cos")
+                && self.symbol_table.lookup_function(name).is_none() => {
+                if args.len() != 1 {
+                    return Err(format!("Function '{}' expects 1 argument, got {}", name, args.len()));
+                }
+                
+                match self.analyze_expression_type(&args[0])? {
+                    Type::Int | Type::Float => {
+                        self.check_finite_call(name, &args[0]);
+                        Ok(Type::Float)
+                    }
+                    other => Err(format!("Function '{}' expects a numeric argument, got {:?}", name, other)),
+                }
+            }
+            
+            // Measurement in the X or Y basis types like a plain measurement
+            Expr::Call(name, args, span) if matches!(name.as_str(), "measure_x" | "measure_y")
+                && self.symbol_table.lookup_function(name).is_none() => {
+                if args.len() != 1 {
+                    return Err(format!("Function '{}' expects 1 argument, got {}", name, args.len()));
+                }
+                
+                self.analyze_expression_type(&Expr::Measure(Box::new(args[0].clone()), span.clone()))
+            }
+            
+            // Compile-time assertion; it emits no code
+            Expr::Call(name, args, span) if name == "assert"
+                && self.symbol_table.lookup_function(name).is_none() => {
+                if args.len() != 1 {
+                    return Err(format!("Function 'assert' expects 1 argument, got {}", args.len()));
+                }
+                
+                let cond_ty = self.analyze_expression_type(&args[0])?;
+                if cond_ty != Type::Bool {
+                    return Err(format!("assert condition must be bool, got {:?}", cond_ty));
+                }
+                
+                match self.eval_constant(&args[0]) {
+                    Some(ConstValue::Bool(true)) => {}
+                    Some(_) => self.errors.push(SemanticError::new(
+                        span,
+                        &format!("Assertion failed: {}", format_expr(&args[0])),
+                        Some("The condition is false for the constant values in this program"),
+                    )),
+                    None => self.errors.push(SemanticError::new(
+                        span,
+                        &format!("assert condition '{}' is not a compile-time constant", format_expr(&args[0])),
+                        Some("assert only checks conditions over literals and immutable variables with constant values"),
+                    )),
+                }
+                Ok(Type::Unit)
+            }
+            
             Expr::Call(name, args, _) => {
                 let (params, return_type, defined) = self.symbol_table.lookup_function(name)
                     .ok_or_else(|| format!("Function '{}' not found", name))?;
@@ -627,40 +1255,128 @@ impl SemanticAnalyzer {
                     ));
                 }
                 
+                // Quantum arguments are handed over to the callee
+                for arg in args {
+                    self.release_operand(arg);
+                }
+                
                 // Return function's return type
                 self.type_registry.resolve_type(&return_type)
             }
             
-            Expr::Measure(qubit_expr, _) => {
+            Expr::Measure(qubit_expr, span) => {
+                self.check_measured_twice(qubit_expr, span);
                 let qubit_ty = self.analyze_expression_type(qubit_expr)?;
+                if let Some(key) = Self::qubit_state_key(qubit_expr) {
+                    self.set_qubit_state(&key, QubitState::Measured);
+                }
+                self.release_operand(qubit_expr);
                 
                 // Ensure we're measuring a quantum type
+                match qubit_ty {
+                    // Measuring a whole register yields one cbit per qubit
+                    Type::Qreg(size) => Ok(Type::Array(Box::new(Type::Cbit), size)),
+                    Type::Array(ref elem, size) if **elem == Type::Qubit => {
+                        Ok(Type::Array(Box::new(Type::Cbit), size))
+                    }
+                    _ if self.type_registry.is_quantum_type(&qubit_ty)? => Ok(Type::Cbit),
+                    _ => Err(format!("Cannot measure non-quantum type {:?}", qubit_ty)),
+                }
+            }
+            
+            Expr::MeasureInto(qubit_expr, target_expr, span) => {
+                self.check_measured_twice(qubit_expr, span);
+                let qubit_ty = self.analyze_expression_type(qubit_expr)?;
+                let target_ty = self.analyze_expression_type(target_expr)?;
+                if let Some(key) = Self::qubit_state_key(qubit_expr) {
+                    self.set_qubit_state(&key, QubitState::Measured);
+                }
+                self.release_operand(qubit_expr);
+                
+                if qubit_ty != Type::Qubit {
+                    return Err(format!("measure with a target expects a single qubit, got {:?}", qubit_ty));
+                }
+                if target_ty != Type::Cbit {
+                    return Err(format!("Measurement target must be a cbit, got {:?}", target_ty));
+                }
+                Ok(Type::Cbit)
+            }
+            
+            Expr::Reset(qubit_expr, _) => {
+                // Reset returns the qubit to |0>, so it is usable again even after measurement
+                if let Some(key) = Self::qubit_state_key(qubit_expr) {
+                    if self.qubit_state(&key) == QubitState::Measured {
+                        self.set_qubit_state(&key, QubitState::Available);
+                    }
+                }
+                self.release_operand(qubit_expr);
+                let qubit_ty = self.analyze_expression_type(qubit_expr)?;
+                
                 if self.type_registry.is_quantum_type(&qubit_ty)? {
-                    Ok(Type::Cbit)
+                    Ok(Type::Unit)
                 } else {
-                    Err(format!("Cannot measure non-quantum type {:?}", qubit_ty))
+                    Err(format!("Cannot reset non-quantum type {:?}", qubit_ty))
                 }
             }
             
-            Expr::GateApply(gate, args, _) => {
+            Expr::Barrier(args, _) => {
+                for arg in args {
+                    let arg_ty = self.analyze_expression_type(arg)?;
+                    if !self.type_registry.is_quantum_type(&arg_ty)? {
+                        return Err(format!("barrier expects quantum arguments, got {:?}", arg_ty));
+                    }
+                }
+                Ok(Type::Unit)
+            }
+            
+            Expr::GateApply(gate, args, span) => {
                 // Check gate arity
                 let expected_arity = gate.arity();
                 if args.len() != expected_arity {
                     return Err(format!(
-                        "Gate {:?} expects {} arguments, got {}", 
-                        gate, expected_arity, args.len()
+                        "Gate {} expects {} arguments, got {}", 
+                        gate.canonical_name(), expected_arity, args.len()
                     ));
                 }
                 
+                self.check_distinct_gate_operands(gate, args, span);
+                
+                if let Some(angle) = gate.angle() {
+                    match self.analyze_expression_type(angle)? {
+                        Type::Int | Type::Float => {}
+                        other => return Err(format!(
+                            "Gate {} expects a numeric angle, got {:?}",
+                            gate.canonical_name(), other
+                        )),
+                    }
+                }
+                
                 // Gates return the type of their first argument
-                if let Some(first_arg) = args.first() {
-                    self.analyze_expression_type(first_arg)
-                } else {
-                    Err("Gate requires at least one argument".to_string())
+                let mut arg_types = Vec::new();
+                for arg in args {
+                    arg_types.push(self.analyze_expression_type(arg)?);
+                }
+                
+                // Register operands broadcast element by element, so their sizes must agree
+                let mut widths = arg_types.iter().filter_map(|ty| match ty {
+                    Type::Qreg(size) => Some(*size),
+                    Type::Array(elem, size) if **elem == Type::Qubit => Some(*size),
+                    _ => None,
+                });
+                if let Some(first) = widths.next() {
+                    if let Some(other) = widths.find(|size| *size != first) {
+                        return Err(format!(
+                            "Gate {} applied to registers of different sizes ({} and {})",
+                            gate.canonical_name(), first, other
+                        ));
+                    }
                 }
+                
+                arg_types.into_iter().next()
+                    .ok_or_else(|| "Gate requires at least one argument".to_string())
             }
             
-            Expr::Index(array_expr, index_expr, _) => {
+            Expr::Index(array_expr, index_expr, span) => {
                 let array_ty = self.analyze_expression_type(array_expr)?;
                 let index_ty = self.analyze_expression_type(index_expr)?;
                 
@@ -669,6 +1385,10 @@ impl SemanticAnalyzer {
                     return Err(format!("Array index must be int, got {:?}", index_ty));
                 }
                 
+                if let Type::Array(_, size) | Type::Qreg(size) = array_ty {
+                    self.check_index_bounds(array_expr, index_expr, size, span);
+                }
+                
                 match array_ty {
                     Type::Array(elem_type, _) => Ok(*elem_type.clone()),
                     Type::Qreg(_) => Ok(Type::Qubit),
@@ -685,10 +1405,10 @@ impl SemanticAnalyzer {
                             .ok_or_else(|| format!("'{}' is not a struct", name))?;
                         
                         // Find the field
-                        for field in &struct_def.fields {
-                            if field.name == *field_name {
-                                return self.type_registry.resolve_type(&field.ty);
-                            }
+                        if let Some(field) = struct_def.fields.iter().find(|field| field.name == *field_name) {
+                            let field_ty = self.type_registry.resolve_type(&field.ty)?;
+                            self.check_qubit_available(expr, &field_ty);
+                            return Ok(field_ty);
                         }
                         
                         Err(format!("Struct '{}' has no field '{}'", name, field_name))
@@ -764,6 +1484,44 @@ impl SemanticAnalyzer {
         }
     }
     
+    /// Reports a constant index outside `0..size`, which would otherwise
+    /// make the builder drop the operation using it.
+    fn check_index_bounds(&mut self, array_expr: &Expr, index_expr: &Expr, size: usize, span: &Span) {
+        let Some(ConstValue::Int(index)) = self.eval_constant(index_expr) else {
+            return;
+        };
+        if usize::try_from(index).is_ok_and(|index| index < size) {
+            return;
+        }
+        self.errors.push(SemanticError::new(
+            span,
+            &format!("Index {} out of bounds for '{}' of size {}", index, format_expr(array_expr), size),
+            Some(&format!("Valid indices are 0 to {}", size.saturating_sub(1))),
+        ));
+    }
+    
+    /// Reports a constant `name(arg)` whose result is NaN or infinite, which
+    /// would otherwise reach the output as an angle such as `rz(NaN)`.
+    fn check_finite_call(&mut self, name: &str, arg: &Expr) {
+        let call = Expr::Call(name.to_string(), vec![arg.clone()], arg.span().clone());
+        let Some(ConstValue::Float(value)) = self.eval_constant(&call) else {
+            return;
+        };
+        if value.is_finite() {
+            return;
+        }
+        let hint = if name == "sqrt" {
+            "sqrt is only defined for arguments of 0 or more"
+        } else {
+            "The argument must be a finite number"
+        };
+        self.errors.push(SemanticError::new(
+            arg.span(),
+            &format!("{}({}) is not a finite number", name, format_expr(arg)),
+            Some(hint),
+        ));
+    }
+    
     fn check_logical_types(&self, left: &Type, right: &Type) -> Result<Type, String> {
         match (left, right) {
             (Type::Bool, Type::Bool) => Ok(Type::Bool),
@@ -771,6 +1529,19 @@ impl SemanticAnalyzer {
         }
     }
     
+    /// Under `warn_coercion`, warns when an `int` at `span` is used where a
+    /// `float` is expected.
+    fn note_coercion(&mut self, expected: &Type, actual: &Type, span: &Span) {
+        if !self.warn_coercion || !matches!((expected, actual), (Type::Float, Type::Int)) {
+            return;
+        }
+        let warning = Diagnostic::warning("Implicit int to float conversion").with_span(span.clone());
+        // Expressions can be type-checked more than once
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+    
     fn are_types_compatible(&self, expected: &Type, actual: &Type) -> bool {
         // Basic type compatibility with some implicit conversions
         if expected == actual {
@@ -785,6 +1556,7 @@ impl SemanticAnalyzer {
         // Basic implementation - just check expression if present
         if let Some(expr) = expr {
             let _ = self.analyze_expression(expr, span);
+            self.consume_returned_value(expr);
         }
     }
     
@@ -816,7 +1588,20 @@ impl SemanticAnalyzer {
     
     fn analyze_for_range_stmt(&mut self, var_name: &str, start: &Expr, end: &Expr, 
                              step: &Option<Box<Expr>>, body: &Stmt, span: &Span) {
-        self.symbol_table.push_scope();
+        if let Some(step) = step {
+            match self.analyze_expression_type(step) {
+                Ok(Type::Int) | Err(_) => {}
+                Ok(ty) => {
+                    self.errors.push(SemanticError::new(
+                        step.span(),
+                        &format!("Loop step must be an int, got {:?}", ty),
+                        Some("Use an integer step such as 2 or -1"),
+                    ));
+                }
+            }
+        }
+        
+        self.push_scope();
         
         // Add loop variable
         let symbol = Symbol::Variable {
@@ -833,12 +1618,13 @@ impl SemanticAnalyzer {
                 Some("Loop variable names must be unique in their scope"),
             ));
         }
+        self.declare_binding(var_name, span);
         
         self.loop_depth += 1;
         self.analyze_statement(body);
         self.loop_depth -= 1;
         
-        self.symbol_table.pop_scope();
+        self.pop_scope();
     }
     
     fn analyze_qif_stmt(&mut self, condition: &Expr, then_branch: &Stmt, 
@@ -847,6 +1633,24 @@ impl SemanticAnalyzer {
         let old_context = self.in_quantum_context;
         self.in_quantum_context = true;
         
+        match self.analyze_expression_type(condition) {
+            Ok(Type::Qubit) => {}
+            Ok(ty) => {
+                self.errors.push(SemanticError::new(
+                    condition.span(),
+                    &format!("qif condition must be a qubit, got {:?}", ty),
+                    Some("Use 'if' for classical conditions"),
+                ));
+            }
+            Err(e) => {
+                self.errors.push(SemanticError::new(
+                    condition.span(),
+                    &e,
+                    Some("Expression type could not be determined"),
+                ));
+            }
+        }
+        
         self.analyze_statement(then_branch);
         if let Some(else_branch) = else_branch {
             self.analyze_statement(else_branch);
@@ -893,11 +1697,201 @@ impl SemanticAnalyzer {
         &self.errors
     }
     
-    pub fn get_warnings(&self) -> &[String] {
+    pub fn get_warnings(&self) -> &[Diagnostic] {
         &self.warnings
     }
     
     pub fn get_type_registry(&self) -> &TypeRegistry {
         &self.type_registry
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::qir::{QirAngle, QirGate, QirOp};
+    use crate::{CompileOptions, Compiler};
+
+    fn compile_errors(body: &str) -> String {
+        let source = format!("fn main() -> int {{\n{}\n}}\n", body);
+        Compiler::compile_with_stats(&source, false).err().unwrap_or_default().join("\n")
+    }
+
+    fn warnings(body: &str) -> String {
+        warnings_with(body, &CompileOptions::default())
+    }
+
+    fn warnings_with(body: &str, options: &CompileOptions) -> String {
+        let source = format!("fn main() -> int {{\n{}\n}}\n", body);
+        let (_, warnings) = Compiler::run_pipeline(&source, options);
+        warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn pi_over_two_type_checks_and_folds() {
+        let source = "fn main() -> int { let q: qubit = |0>; let a: float = pi / 2; RZ(a, q); return 0; }";
+        let module = Compiler::compile_to_qir(source, &CompileOptions::default()).unwrap();
+        let angle = module.functions[0].blocks.values()
+            .flat_map(|block| &block.ops)
+            .find_map(|op| match op {
+                QirOp::ApplyGate { gate: QirGate::RZ(QirAngle::Value(angle)), .. } => Some(*angle),
+                _ => None,
+            })
+            .unwrap();
+        assert!((angle - std::f64::consts::FRAC_PI_2).abs() < 1e-12, "{}", angle);
+    }
+
+    #[test]
+    fn non_finite_math_results_are_rejected() {
+        let errors = compile_errors("let q: qubit = |0>; RZ(sqrt(-1.0), q); return 0;");
+        assert!(errors.contains("sqrt(-1.0) is not a finite number"), "{}", errors);
+
+        let errors = compile_errors("let a: float = cos(1e400); return 0;");
+        assert!(errors.contains("is not a finite number"), "{}", errors);
+    }
+
+    #[test]
+    fn returned_qubits_cannot_be_used_again() {
+        let source = "fn f(q: qubit) -> qubit { return q; let c: cbit = measure(q); }\n\
+            fn main() -> int { let q: qubit = |0>; let r: qubit = f(q); let c: cbit = measure(r); return 0; }";
+        let errors = Compiler::compile_with_stats(source, false).err().unwrap_or_default().join("\n");
+        assert!(errors.contains("Use of consumed qubit 'q'"), "{}", errors);
+    }
+
+    #[test]
+    fn unused_variables_are_reported() {
+        let output = warnings("let unused: int = 5; return 0;");
+        assert!(output.contains("2:1: Variable 'unused' is never used"), "{}", output);
+
+        let output = warnings("let used: int = 5; return used;");
+        assert!(!output.contains("never used"), "{}", output);
+
+        let output = warnings("let q: qubit = |0>; return 0;");
+        assert!(output.contains("Qubit 'q' is never used"), "{}", output);
+    }
+
+    #[test]
+    fn two_qubit_gates_need_distinct_operands() {
+        for gate in ["CNOT", "SWAP"] {
+            let errors = compile_errors(&format!("let q1: qubit = |0>; {}(q1, q1); return 0;", gate));
+            assert!(errors.contains(&format!("2:22: [E0003] {} applied to the same qubit 'q1' more than once", gate)), "{}", errors);
+        }
+    }
+
+    #[test]
+    fn statements_after_return_are_unreachable() {
+        let output = warnings("let q: qubit = |0>; return 0;\nH(q);");
+        assert!(output.contains("3:1: Unreachable statement"), "{}", output);
+
+        let output = warnings("let b: bool = true; if (b) { return 1; } else { return 2; }\nreturn 0;");
+        assert!(output.contains("3:1: Unreachable statement"), "{}", output);
+
+        let output = warnings("let q: qubit = |0>; H(q); let c: cbit = measure(q); return 0;");
+        assert!(!output.contains("Unreachable"), "{}", output);
+    }
+
+    #[test]
+    fn measured_qubits_cannot_be_reused() {
+        let errors = compile_errors("let q: qubit = |0>; let c: cbit = measure(q); H(q); return 0;");
+        assert!(errors.contains("Qubit 'q' used after measurement"), "{}", errors);
+
+        let errors = compile_errors("let q: qubit = |0>; let c: cbit = measure(q); reset(q); H(q); return 0;");
+        assert!(errors.is_empty(), "{}", errors);
+    }
+
+    fn coercion_warnings(body: &str) -> String {
+        warnings_with(body, &CompileOptions { warn_coercion: true, ..CompileOptions::default() })
+    }
+
+    #[test]
+    fn int_to_float_coercion_warns_only_when_asked() {
+        let output = coercion_warnings("let x: float = 5; return 0;");
+        assert!(output.contains("2:16: Implicit int to float conversion"), "{}", output);
+        assert!(!warnings("let x: float = 5; return 0;").contains("Implicit"));
+        assert!(!coercion_warnings("let x: float = 5.0; return 0;").contains("Implicit"));
+    }
+
+    #[test]
+    fn false_constant_assert_is_an_error() {
+        let errors = compile_errors("let N: int = 4; assert(N == 3); return 0;");
+        assert!(errors.contains("Assertion failed: N == 3"), "{}", errors);
+        let errors = compile_errors("let mut n: int = 4; assert(n == 4); return 0;");
+        assert!(errors.contains("assert condition 'n == 4' is not a compile-time constant"), "{}", errors);
+    }
+
+    #[test]
+    fn true_constant_assert_compiles_away() {
+        let body = "let q: qubit = |0>; H(q); let c: cbit = measure(q);";
+        let with_assert = format!("fn main() -> int {{ let N: int = 4; assert(N * 2 == 8); {} return 0; }}", body);
+        let without = format!("fn main() -> int {{ let N: int = 4; {} return 0; }}", body);
+        assert_eq!(
+            Compiler::compile_with_stats(&with_assert, false).unwrap().qasm,
+            Compiler::compile_with_stats(&without, false).unwrap().qasm,
+        );
+    }
+
+    #[test]
+    fn inner_shadowing_warns_only_when_asked() {
+        let body = "let x: int = 1;\nif (x == 1) {\n    let x: int = 2;\n}\nreturn x;";
+        let output = warnings_with(body, &CompileOptions { warn_shadowing: true, ..CompileOptions::default() });
+        assert!(output.contains("4:5: Variable 'x' shadows the declaration at line 2:1"), "{}", output);
+        assert!(!warnings(body).contains("shadows"));
+    }
+
+    #[test]
+    fn measuring_a_qubit_twice_errors_at_the_second_measure() {
+        let errors = compile_errors("let q: qubit = |0>;\nlet a: cbit = measure(q);\nlet b: cbit = measure(q);\nreturn 0;");
+        assert!(errors.contains("4:15: [E0003] Qubit 'q' already measured"), "{}", errors);
+        assert!(errors.contains("hint: Use the earlier measurement result"), "{}", errors);
+        assert!(compile_errors("let q: qubit = |0>;\nlet a: cbit = measure(q);\nreset(q);\nlet b: cbit = measure(q);\nreturn 0;").is_empty());
+    }
+
+    #[test]
+    fn qreg_size_mismatch_hint_shows_the_expected_literal() {
+        let errors = compile_errors("qreg q[3] = |00>;\nreturn 0;");
+        assert!(errors.contains("Bit string length 2 doesn't match qreg size 3"), "{}", errors);
+        assert!(errors.contains("hint: Expected |000> for qreg[3], got 2 bits"), "{}", errors);
+    }
+
+    #[test]
+    fn deny_quantum_leaks_rejects_qubits_that_are_never_released() {
+        let options = CompileOptions { deny_quantum_leaks: true, ..CompileOptions::default() };
+        let leak = |body: &str| {
+            let source = format!("fn main() -> int {{\n{}\n}}\n", body);
+            match Compiler::run_pipeline(&source, &options).0 {
+                Ok(_) => String::new(),
+                Err(errors) => errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"),
+            }
+        };
+        let errors = leak("let q: qubit = |0>;\nH(q);\nreturn 0;");
+        assert!(errors.contains("Quantum variable never measured, reset, returned or passed to a function: 'q'"), "{}", errors);
+        assert!(leak("let q: qubit = |0>;\nH(q);\nlet c: cbit = measure(q);\nreturn 0;").is_empty());
+        assert!(compile_errors("let q: qubit = |0>;\nH(q);\nreturn 0;").is_empty());
+    }
+
+    #[test]
+    fn xor_is_bitwise_on_ints_and_logical_on_bools() {
+        assert!(compile_errors("let x: int = 6;\nlet y: int = 3;\nlet z: int = x ^ y;\nreturn z;").is_empty());
+        assert!(compile_errors("let x: int = 6;\nlet b: bool = (x == 6) ^ (x < 3);\nreturn 0;").is_empty());
+        // ^ binds tighter than ==, so this compares (x ^ y) with 5
+        assert!(compile_errors("let x: int = 6;\nlet y: int = 3;\nlet b: bool = x ^ y == 5;\nreturn 0;").is_empty());
+        let errors = compile_errors("let x: int = 6;\nlet f: float = 1.5;\nlet z: int = x ^ f;\nreturn 0;");
+        assert!(errors.contains("Operator ^ needs two ints or two bools, got Int and Float"), "{}", errors);
+    }
+
+    #[test]
+    fn increment_and_decrement_only_apply_to_mutable_ints() {
+        assert!(compile_errors("let mut i: int = 0;\ni++;\ni--;\nreturn i;").is_empty());
+        let errors = compile_errors("let j: int = 1;\nlet mut f: float = 1.0;\nj++;\nf--;\nreturn 0;");
+        assert!(errors.contains("4:1: [E0003] Cannot assign to immutable variable 'j'"), "{}", errors);
+        assert!(errors.contains("5:1: [E0003] Cannot apply '--' to 'f' of type Float"), "{}", errors);
+    }
+
+    #[test]
+    fn constant_indices_outside_the_register_are_rejected() {
+        let errors = compile_errors("qreg qs[3] = |000>;\nH(qs[5]);\nlet c: cbit = measure(qs[0]);\nreturn 0;");
+        assert!(errors.contains("3:3: [E0003] Index 5 out of bounds for 'qs' of size 3"), "{}", errors);
+        let errors = compile_errors("mut int xs[2];\nlet y: int = xs[2];\nreturn y;");
+        assert!(errors.contains("Index 2 out of bounds for 'xs' of size 2"), "{}", errors);
+        assert!(compile_errors("qreg qs[3] = |000>;\nH(qs[2]);\nlet c: cbit = measure(qs[2]);\nreturn 0;").is_empty());
+    }
+}
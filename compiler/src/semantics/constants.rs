@@ -0,0 +1,98 @@
+// src/semantics/constants.rs - Compile-time evaluation of classical expressions
+use crate::ast::{Expr, BinaryOp, UnaryOp};
+
+/// The value of a classical expression known at compile time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl ConstValue {
+    fn as_float(self) -> Option<f64> {
+        match self {
+            ConstValue::Int(v) => Some(v as f64),
+            ConstValue::Float(v) => Some(v),
+            ConstValue::Bool(_) => None,
+        }
+    }
+}
+
+/// Folds `expr` to a constant, or `None` when it depends on anything not
+/// known at compile time. `lookup` gives the value of constant variables.
+/// Integer arithmetic that overflows or divides by zero doesn't fold.
+pub fn eval_const(expr: &Expr, lookup: &dyn Fn(&str) -> Option<ConstValue>) -> Option<ConstValue> {
+    match expr {
+        Expr::LiteralInt(v, _) => Some(ConstValue::Int(*v)),
+        Expr::LiteralFloat(v, _) => Some(ConstValue::Float(*v)),
+        Expr::LiteralBool(b, _) => Some(ConstValue::Bool(*b)),
+        Expr::Variable(name, _) => lookup(name),
+        Expr::UnaryOp(UnaryOp::Neg, operand, _) => match eval_const(operand, lookup)? {
+            ConstValue::Int(v) => v.checked_neg().map(ConstValue::Int),
+            ConstValue::Float(v) => Some(ConstValue::Float(-v)),
+            ConstValue::Bool(_) => None,
+        },
+        Expr::UnaryOp(UnaryOp::Not, operand, _) => match eval_const(operand, lookup)? {
+            ConstValue::Bool(b) => Some(ConstValue::Bool(!b)),
+            _ => None,
+        },
+        Expr::Call(name, args, _) if args.len() == 1 => {
+            let arg = eval_const(&args[0], lookup)?.as_float()?;
+            match name.as_str() {
+                "sqrt" => Some(ConstValue::Float(arg.sqrt())),
+                "sin" => Some(ConstValue::Float(arg.sin())),
+                "cos" => Some(ConstValue::Float(arg.cos())),
+                _ => None,
+            }
+        }
+        Expr::BinaryOp(left, op, right, _) => {
+            let l = eval_const(left, lookup)?;
+            let r = eval_const(right, lookup)?;
+            eval_binary(op, l, r)
+        }
+        _ => None,
+    }
+}
+
+fn eval_binary(op: &BinaryOp, lhs: ConstValue, rhs: ConstValue) -> Option<ConstValue> {
+    match (lhs, rhs) {
+        (ConstValue::Int(l), ConstValue::Int(r)) => match op {
+            BinaryOp::Add => l.checked_add(r).map(ConstValue::Int),
+            BinaryOp::Sub => l.checked_sub(r).map(ConstValue::Int),
+            BinaryOp::Mul => l.checked_mul(r).map(ConstValue::Int),
+            BinaryOp::Div => l.checked_div(r).map(ConstValue::Int),
+            BinaryOp::Xor => Some(ConstValue::Int(l ^ r)),
+            BinaryOp::Eq => Some(ConstValue::Bool(l == r)),
+            BinaryOp::Neq => Some(ConstValue::Bool(l != r)),
+            BinaryOp::Lt => Some(ConstValue::Bool(l < r)),
+            BinaryOp::Gt => Some(ConstValue::Bool(l > r)),
+            BinaryOp::Le => Some(ConstValue::Bool(l <= r)),
+            BinaryOp::Ge => Some(ConstValue::Bool(l >= r)),
+            _ => None,
+        },
+        (ConstValue::Bool(l), ConstValue::Bool(r)) => match op {
+            BinaryOp::And => Some(ConstValue::Bool(l && r)),
+            BinaryOp::Or => Some(ConstValue::Bool(l || r)),
+            BinaryOp::Xor | BinaryOp::Neq => Some(ConstValue::Bool(l != r)),
+            BinaryOp::Eq => Some(ConstValue::Bool(l == r)),
+            _ => None,
+        },
+        _ => {
+            let (l, r) = (lhs.as_float()?, rhs.as_float()?);
+            match op {
+                BinaryOp::Add => Some(ConstValue::Float(l + r)),
+                BinaryOp::Sub => Some(ConstValue::Float(l - r)),
+                BinaryOp::Mul => Some(ConstValue::Float(l * r)),
+                BinaryOp::Div if r != 0.0 => Some(ConstValue::Float(l / r)),
+                BinaryOp::Eq => Some(ConstValue::Bool(l == r)),
+                BinaryOp::Neq => Some(ConstValue::Bool(l != r)),
+                BinaryOp::Lt => Some(ConstValue::Bool(l < r)),
+                BinaryOp::Gt => Some(ConstValue::Bool(l > r)),
+                BinaryOp::Le => Some(ConstValue::Bool(l <= r)),
+                BinaryOp::Ge => Some(ConstValue::Bool(l >= r)),
+                _ => None,
+            }
+        }
+    }
+}
@@ -2,9 +2,8 @@
 pub mod symbols;
 pub mod analyzer;
 pub mod errors;
-pub mod ownership_checker;
+pub mod constants;
 
 pub use analyzer::SemanticAnalyzer;
 pub use errors::SemanticError;
-pub use ownership_checker::OwnershipChecker;
 pub use symbols::{TypeRegistry, SymbolTable};
\ No newline at end of file
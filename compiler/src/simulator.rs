@@ -2,26 +2,65 @@
 use crate::qir::{QirModule, QirOp, QirGate, QirValue};
 use num_complex::Complex;
 use std::f64::consts::SQRT_2;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
 
 pub struct Simulator {
     state: Vec<Complex<f64>>,
     num_qubits: usize,
+    cbits: HashMap<usize, u8>,
+    rng: StdRng,
 }
 
 impl Simulator {
     pub fn new() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+
+    /// A simulator whose measurement outcomes are reproducible for `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(rng: StdRng) -> Self {
         Self {
             state: vec![Complex::new(1.0, 0.0)],
             num_qubits: 0,
+            cbits: HashMap::new(),
+            rng,
+        }
+    }
+
+    /// Runs the circuit `shots` times from a fresh state and counts the
+    /// classical bitstrings, written with the highest cbit first as in
+    /// Qiskit. Sorted by count, most frequent first, then by bitstring.
+    pub fn sample(&mut self, module: &QirModule, shots: usize) -> Result<Vec<(String, usize)>, String> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..shots {
+            self.state = vec![Complex::new(1.0, 0.0)];
+            self.num_qubits = 0;
+            self.cbits.clear();
+            self.execute(module)?;
+
+            let width = self.cbits.keys().max().map_or(0, |max| max + 1);
+            let bits: String = (0..width).rev()
+                .map(|i| if self.cbits.get(&i) == Some(&1) { '1' } else { '0' })
+                .collect();
+            *counts.entry(bits).or_insert(0) += 1;
         }
+
+        let mut histogram: Vec<(String, usize)> = counts.into_iter().collect();
+        histogram.sort_by(|(a, count_a), (b, count_b)| count_b.cmp(count_a).then_with(|| a.cmp(b)));
+        Ok(histogram)
     }
 
     pub fn execute(&mut self, module: &QirModule) -> Result<String, String> {
         let mut output = String::new();
         output.push_str("🚀 Simulation Log:\n");
 
-        if let Some(func) = module.functions.iter().find(|f| f.name == "main") {
+        let entry = module.functions.iter().find(|f| f.name == "main").or(module.functions.first());
+        if let Some(func) = entry {
             // FIX: Use Control Flow Graph traversal
             let mut current_block_id = func.entry_block;
             let mut steps = 0;
@@ -47,10 +86,20 @@ impl Simulator {
                             // FIX: Correct borrowing for arguments
                             self.apply_gate(gate, args)?;
                         }
-                        QirOp::Measure { qubit, .. } => {
+                        QirOp::Measure { qubit, cbit } => {
                             let result = self.measure(qubit.0);
+                            self.cbits.insert(cbit.0, result);
                             output.push_str(&format!("  MEASURE q[{}] -> {}\n", qubit.0, result));
                         }
+                        QirOp::ConditionalGate { cbit, value, gate, qubits }
+                            if self.cbits.get(&cbit.0).map(|bit| *bit as i64) == Some(*value) => {
+                            let args: Vec<QirValue> = qubits.iter().map(|q| QirValue::Qubit(*q)).collect();
+                            self.apply_gate(gate, &args)?;
+                        }
+                        QirOp::Reset { qubit } => {
+                            self.reset(qubit.0);
+                            output.push_str(&format!("  RESET q[{}]\n", qubit.0));
+                        }
                         // --- Control Flow Handling ---
                         QirOp::Jump { target } => {
                             current_block_id = *target;
@@ -85,6 +134,16 @@ impl Simulator {
         Ok(output)
     }
 
+    /// Amplitudes of the current state, indexed by basis state with qubit 0
+    /// as the least significant bit.
+    pub fn state_vector(&self) -> &[Complex<f64>] {
+        &self.state
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
     fn allocate_qubit(&mut self) {
         let old_len = self.state.len();
         let new_len = old_len * 2;
@@ -161,6 +220,17 @@ impl Simulator {
         self.state = new_state;
     }
 
+    // Reset = measure, then flip the qubit back to |0> if it collapsed to |1>
+    fn reset(&mut self, target: usize) {
+        if self.measure(target) == 1 {
+            for i in 0..self.state.len() {
+                if (i & (1 << target)) != 0 {
+                    self.state.swap(i, i ^ (1 << target));
+                }
+            }
+        }
+    }
+
     fn measure(&mut self, target: usize) -> u8 {
         let mut prob_one = 0.0;
         for i in 0..self.state.len() {
@@ -169,8 +239,7 @@ impl Simulator {
             }
         }
 
-        let mut rng = rand::thread_rng();
-        let result = if rng.gen::<f64>() < prob_one { 1 } else { 0 };
+        let result = if self.rng.gen::<f64>() < prob_one { 1 } else { 0 };
 
         let prob = if result == 1 { prob_one } else { 1.0 - prob_one };
         if prob > 0.0 {
@@ -187,4 +256,26 @@ impl Simulator {
         }
         result
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, Compiler};
+
+    fn module(body: &str) -> QirModule {
+        let source = format!("fn main() -> int {{ let q: qubit = |0>; {} return 0; }}", body);
+        Compiler::compile_to_qir(&source, &CompileOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn hadamard_shots_split_evenly() {
+        let histogram = Simulator::with_seed(7).sample(&module("H(q); let c: cbit = measure(q);"), 2000).unwrap();
+        assert_eq!(histogram.len(), 2, "{:?}", histogram);
+        assert!(histogram[0].1 >= histogram[1].1, "{:?}", histogram);
+        for (bits, count) in &histogram {
+            assert!(bits == "0" || bits == "1", "{:?}", histogram);
+            assert!((900..=1100).contains(count), "{:?}", histogram);
+        }
+        assert_eq!(histogram, Simulator::with_seed(7).sample(&module("H(q); let c: cbit = measure(q);"), 2000).unwrap());
+    }
+}
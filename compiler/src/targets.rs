@@ -0,0 +1,60 @@
+// src/targets.rs - Named device presets for --target
+
+/// A device the compiler can verify circuits against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    /// Name accepted by `--target`; matched case-insensitively.
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Native OpenQASM gate names, or `None` when any gate is accepted.
+    pub basis_gates: Option<&'static [&'static str]>,
+    /// Number of physical qubits.
+    pub max_qubits: usize,
+    /// Pairs of physical qubits a two-qubit gate can act on, in either
+    /// direction. Empty means all-to-all connectivity.
+    pub coupling_map: &'static [(usize, usize)],
+}
+
+const IBM_BASIS: &[&str] = &["rz", "sx", "x", "cx"];
+
+const TARGETS: &[Target] = &[
+    Target {
+        name: "ibmq_small",
+        description: "5-qubit IBM Falcon-style device with T-shaped connectivity",
+        basis_gates: Some(IBM_BASIS),
+        max_qubits: 5,
+        coupling_map: &[(0, 1), (1, 2), (1, 3), (3, 4)],
+    },
+    Target {
+        name: "ibmq_line",
+        description: "16-qubit IBM-basis device with nearest-neighbour line connectivity",
+        basis_gates: Some(IBM_BASIS),
+        max_qubits: 16,
+        coupling_map: &[
+            (0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 6), (6, 7), (7, 8),
+            (8, 9), (9, 10), (10, 11), (11, 12), (12, 13), (13, 14), (14, 15),
+        ],
+    },
+    Target {
+        name: "simulator",
+        description: "32-qubit ideal simulator: any gate, all-to-all connectivity",
+        basis_gates: None,
+        max_qubits: 32,
+        coupling_map: &[],
+    },
+];
+
+/// Every preset, in display order.
+pub fn all_targets() -> &'static [Target] {
+    TARGETS
+}
+
+/// Looks up a preset by name, ignoring case.
+pub fn lookup(name: &str) -> Option<&'static Target> {
+    TARGETS.iter().find(|t| t.name.eq_ignore_ascii_case(name))
+}
+
+/// Comma-separated list of preset names, used in error messages.
+pub fn name_list() -> String {
+    TARGETS.iter().map(|t| t.name).collect::<Vec<_>>().join(", ")
+}